@@ -0,0 +1,290 @@
+// Derives `minpl::convert::ToTerm` and `minpl::convert::FromTerm`. A struct
+// is a compound term named after it in snake case and an enum has one such
+// term per variant; `#[term(rename = "...")]` and `#[term(keyed)]` are
+// described in `minpl::convert`.
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as Tokens;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Error, Fields, Generics, Ident,
+    LitStr, Path, Result,
+};
+
+#[proc_macro_derive(ToTerm, attributes(term))]
+pub fn derive_to_term(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    to_term(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(FromTerm, attributes(term))]
+pub fn derive_from_term(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    from_term(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct Options {
+    rename: Option<String>,
+    keyed: bool,
+}
+
+fn options(attributes: &[Attribute]) -> Result<Options> {
+    let mut options = Options::default();
+    for attribute in attributes
+        .iter()
+        .filter(|attribute| attribute.path().is_ident("term"))
+    {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                options.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else if meta.path.is_ident("keyed") {
+                options.keyed = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `rename` or `keyed`"))
+            }
+        })?;
+    }
+    Ok(options)
+}
+
+// A struct or a variant: the functor it is written as and how its fields
+// are bound in patterns.
+struct Shape<'a> {
+    path: Path,
+    name: String,
+    fields: &'a Fields,
+    bindings: Vec<Ident>,
+    keys: Option<Vec<String>>,
+}
+
+impl<'a> Shape<'a> {
+    fn new(
+        path: Path,
+        ident: &Ident,
+        attributes: &[Attribute],
+        fields: &'a Fields,
+        keyed: bool,
+    ) -> Result<Self> {
+        let options = options(attributes)?;
+        let keyed = keyed || options.keyed;
+        let name = options
+            .rename
+            .unwrap_or_else(|| snake_case(&ident.to_string()));
+        let mut bindings = vec![];
+        let mut keys = vec![];
+        for (position, field) in fields.iter().enumerate() {
+            let field_options = options_of_field(&field.attrs)?;
+            match (&field.ident, keyed) {
+                (Some(ident), true) => {
+                    keys.push(field_options.unwrap_or_else(|| ident.to_string()));
+                }
+                (None, true) => {
+                    return Err(Error::new_spanned(field, "`keyed` needs named fields"));
+                }
+                (_, false) if field_options.is_some() => {
+                    return Err(Error::new_spanned(
+                        field,
+                        "`rename` on a field needs `#[term(keyed)]`",
+                    ));
+                }
+                _ => {}
+            }
+            bindings.push(format_ident!("field{}", position));
+        }
+        Ok(Self {
+            path,
+            name,
+            fields,
+            bindings,
+            keys: keyed.then_some(keys),
+        })
+    }
+
+    fn pattern(&self) -> Tokens {
+        let path = &self.path;
+        let bindings = &self.bindings;
+        match self.fields {
+            Fields::Named(fields) => {
+                let names = fields.named.iter().map(|field| &field.ident);
+                quote!(#path { #(#names: #bindings),* })
+            }
+            Fields::Unnamed(_) => quote!(#path(#(#bindings),*)),
+            Fields::Unit => quote!(#path),
+        }
+    }
+
+    fn written(&self) -> Tokens {
+        let name = &self.name;
+        let bindings = &self.bindings;
+        if bindings.is_empty() {
+            return quote!(::minpl::term::Term::atom(#name));
+        }
+        let arguments = match &self.keys {
+            Some(keys) => quote! {
+                #(::minpl::term::Term::functor(
+                    #keys,
+                    [::minpl::convert::ToTerm::to_term(#bindings)],
+                )),*
+            },
+            None => quote!(#(::minpl::convert::ToTerm::to_term(#bindings)),*),
+        };
+        quote!(::minpl::term::Term::functor(#name, [#arguments]))
+    }
+
+    // Returns from `from_term` when `term` has this shape.
+    fn read(&self) -> Tokens {
+        let name = &self.name;
+        let arity = self.bindings.len();
+        let pattern = self.pattern();
+        if arity == 0 {
+            return quote! {
+                if ::minpl::convert::arguments(term, #name, 0)?.is_some() {
+                    return ::std::result::Result::Ok(#pattern);
+                }
+            };
+        }
+        let bindings = &self.bindings;
+        let values = match &self.keys {
+            Some(keys) => {
+                let positions = 0..arity;
+                quote! {
+                    let values = ::minpl::convert::keyed_arguments(arguments, &[#(#keys),*])?;
+                    #(let #bindings = ::minpl::convert::FromTerm::from_term(values[#positions])?;)*
+                }
+            }
+            None => {
+                let positions = 0..arity;
+                quote! {
+                    #(let #bindings = ::minpl::convert::FromTerm::from_term(&arguments[#positions])?;)*
+                }
+            }
+        };
+        quote! {
+            if let ::std::option::Option::Some(arguments) = ::minpl::convert::arguments(term, #name, #arity)? {
+                #values
+                return ::std::result::Result::Ok(#pattern);
+            }
+        }
+    }
+
+    fn indicator(&self) -> Tokens {
+        let name = &self.name;
+        let arity = self.bindings.len();
+        quote!((#name, #arity))
+    }
+}
+
+fn options_of_field(attributes: &[Attribute]) -> Result<Option<String>> {
+    let options = options(attributes)?;
+    if options.keyed {
+        let attribute = attributes
+            .iter()
+            .find(|attribute| attribute.path().is_ident("term"));
+        return Err(Error::new_spanned(
+            attribute,
+            "`keyed` applies to types and variants",
+        ));
+    }
+    Ok(options.rename)
+}
+
+fn shapes(input: &DeriveInput) -> Result<Vec<Shape<'_>>> {
+    let keyed = options(&input.attrs)?.keyed;
+    match &input.data {
+        Data::Struct(data) => Ok(vec![Shape::new(
+            parse_quote!(Self),
+            &input.ident,
+            &input.attrs,
+            &data.fields,
+            false,
+        )?]),
+        Data::Enum(data) => data
+            .variants
+            .iter()
+            .map(|variant| {
+                let ident = &variant.ident;
+                Shape::new(
+                    parse_quote!(Self::#ident),
+                    ident,
+                    &variant.attrs,
+                    &variant.fields,
+                    keyed,
+                )
+            })
+            .collect(),
+        Data::Union(_) => Err(Error::new_spanned(
+            &input.ident,
+            "unions have no term representation",
+        )),
+    }
+}
+
+fn to_term(input: &DeriveInput) -> Result<Tokens> {
+    let shapes = shapes(input)?;
+    let ident = &input.ident;
+    let generics = bounded(&input.generics, quote!(::minpl::convert::ToTerm));
+    let (implementation, arguments, where_clause) = generics.split_for_impl();
+    let patterns = shapes.iter().map(Shape::pattern);
+    let terms = shapes.iter().map(Shape::written);
+    Ok(quote! {
+        impl #implementation ::minpl::convert::ToTerm for #ident #arguments #where_clause {
+            fn to_term(&self) -> ::minpl::term::Term {
+                match self {
+                    #(#patterns => #terms,)*
+                }
+            }
+        }
+    })
+}
+
+fn from_term(input: &DeriveInput) -> Result<Tokens> {
+    let shapes = shapes(input)?;
+    let ident = &input.ident;
+    let generics = bounded(&input.generics, quote!(::minpl::convert::FromTerm));
+    let (implementation, arguments, where_clause) = generics.split_for_impl();
+    let attempts = shapes.iter().map(Shape::read);
+    let indicators = shapes.iter().map(Shape::indicator);
+    Ok(quote! {
+        impl #implementation ::minpl::convert::FromTerm for #ident #arguments #where_clause {
+            fn from_term(
+                term: &::minpl::term::Term,
+            ) -> ::std::result::Result<Self, ::minpl::exception::Exception> {
+                #(#attempts)*
+                ::std::result::Result::Err(::minpl::convert::shape_error(&[#(#indicators),*], term))
+            }
+        }
+    })
+}
+
+// Every type parameter has to be convertible too.
+fn bounded(generics: &Generics, bound: Tokens) -> Generics {
+    let mut generics = generics.clone();
+    let parameters = generics
+        .type_params()
+        .map(|parameter| parameter.ident.clone())
+        .collect::<Vec<_>>();
+    let where_clause = generics.make_where_clause();
+    for parameter in parameters {
+        where_clause
+            .predicates
+            .push(parse_quote!(#parameter: #bound));
+    }
+    generics
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (position, character) in name.chars().enumerate() {
+        if character.is_uppercase() && position > 0 {
+            snake.push('_');
+        }
+        snake.extend(character.to_lowercase());
+    }
+    snake
+}
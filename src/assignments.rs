@@ -1,10 +1,16 @@
-use crate::term::Term;
+use crate::term::{Term, WriteOptions};
 use std::{
     collections::{HashMap, HashSet},
     fmt::Display,
 };
 
+// Serialized as a map from variable names to their values.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
 pub struct Assignments {
     assignments: HashMap<String, Term>,
 }
@@ -15,6 +21,10 @@ impl Display for Assignments {
         let mut length = self.assignments.len();
         for (key, value) in &self.assignments {
             length -= 1;
+            let value = value.formatted(WriteOptions {
+                priority: 699,
+                ..WriteOptions::WRITEQ
+            });
             write!(f, "{} := {}", key, value)?;
             if length > 0 {
                 write!(f, ", ")?;
@@ -46,6 +56,14 @@ impl Assignments {
         self.assignments.insert(variable.to_string(), value);
     }
 
+    pub fn get(&self, variable: &str) -> Option<&Term> {
+        self.assignments.get(variable)
+    }
+
+    pub fn remove(&mut self, variable: &str) {
+        self.assignments.remove(variable);
+    }
+
     pub fn with<S: ToString>(mut self, variable: S, value: Term) -> Self {
         self.assignments.insert(variable.to_string(), value);
         self
@@ -73,7 +91,7 @@ impl Assignments {
         let mut normalised_assignments = HashMap::new();
         for name in scope {
             if let Some(value) = self.assignments.get(name) {
-                normalised_assignments.insert(String::from(name), self.normalize_term(&value));
+                normalised_assignments.insert(String::from(name), self.normalize_term(value));
             }
         }
         Self {
@@ -81,20 +99,18 @@ impl Assignments {
         }
     }
 
-    pub fn normalize_term(&self, term: &Term) -> Term {
-        match term {
-            Term::Variable { name } => {
-                if let Some(value) = self.assignments.get(name) {
-                    self.normalize_term(value).clone()
-                } else {
-                    term.clone()
-                }
-            }
-            Term::Functor { name, arguments } => {
-                let arguments = arguments.iter().map(|term| self.normalize_term(term));
-                Term::functor(name, arguments)
+    pub fn dereference<'a>(&'a self, mut term: &'a Term) -> &'a Term {
+        while let Term::Variable { name } = term {
+            match self.assignments.get(name) {
+                Some(value) => term = value,
+                None => break,
             }
         }
+        term
+    }
+
+    pub fn normalize_term(&self, term: &Term) -> Term {
+        term.rebuild(|term| Ok(self.dereference(term)))
     }
 }
 
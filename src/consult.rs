@@ -0,0 +1,1023 @@
+use crate::{
+    database::{dcg_rule, meta_predicate_heads, predicate_indicators, Database, Rule, USER},
+    exception::Exception,
+    library,
+    parser::{MinplParser, Sentence},
+    proof_search::solve,
+    term::Term,
+};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+pub fn consult_str(database: &mut Database, code: &str) -> Result<(), Exception> {
+    let mut loader = Loader::new(database, None, USER);
+    loader.load(code)?;
+    loader.initialize();
+    Ok(())
+}
+
+// Consulting a file again replaces the clauses it added the first time. If
+// the file defines a module, its exports are imported into `context`.
+pub fn consult_file(database: &mut Database, path: &Path, context: &str) -> Result<(), Exception> {
+    let module = load_file(database, path, context)?;
+    import(database, context, &module, None)
+}
+
+pub fn consult(database: &mut Database, files: &Term, context: &str) -> Result<(), Exception> {
+    for file in one_or_many(files)? {
+        let path = source_path(database, &file)?;
+        consult_file(database, &path, context)?;
+    }
+    Ok(())
+}
+
+pub fn ensure_loaded(
+    database: &mut Database,
+    files: &Term,
+    context: &str,
+) -> Result<(), Exception> {
+    use_module(database, files, None, context)
+}
+
+pub fn use_module(
+    database: &mut Database,
+    files: &Term,
+    imports: Option<&Term>,
+    context: &str,
+) -> Result<(), Exception> {
+    for file in one_or_many(files)? {
+        let path = source_path(database, &file)?;
+        let module = match database.loaded_module(&path) {
+            Some(module) => module.to_string(),
+            None => load_file(database, &path, context)?,
+        };
+        import(database, context, &module, imports)?;
+    }
+    Ok(())
+}
+
+// Relative paths are resolved against the file being loaded, and the `.pl`
+// extension may be left out. Paths may be written `Dir/File`, and
+// `library(Name)` refers to a bundled library.
+pub fn source_path(database: &Database, file: &Term) -> Result<PathBuf, Exception> {
+    let name = match file {
+        Term::Variable { .. } => return Err(Exception::instantiation_error()),
+        Term::Functor { name, arguments } if arguments.is_empty() => name.clone(),
+        Term::Functor { name, arguments } if name == "library" && arguments.len() == 1 => {
+            return path_name(&arguments[0])
+                .and_then(|name| library::path(&name))
+                .ok_or_else(|| Exception::existence_error("source_sink", file.clone()))
+        }
+        Term::Functor { name, arguments } if name == "/" && arguments.len() == 2 => {
+            path_name(file).ok_or_else(|| Exception::domain_error("source_sink", file.clone()))?
+        }
+        Term::Str { value } => value.clone(),
+        file => return Err(Exception::domain_error("source_sink", file.clone())),
+    };
+    let mut path = PathBuf::from(&name);
+    if path.is_relative() {
+        if let Some(directory) = database.current_file().and_then(Path::parent) {
+            path = directory.join(path);
+        }
+    }
+    let candidates = match path.extension() {
+        Some(_) => vec![path],
+        None => vec![path.with_extension("pl"), path],
+    };
+    candidates
+        .into_iter()
+        .find(|path| path.is_file())
+        .and_then(|path| path.canonicalize().ok())
+        .ok_or_else(|| Exception::existence_error("source_sink", file.clone()))
+}
+
+// `dcg/basics` names the file `dcg/basics.pl`.
+fn path_name(name: &Term) -> Option<String> {
+    match (name.name()?, name.arguments()) {
+        (name, []) => Some(name.to_string()),
+        ("/", [directory, name]) => Some(format!("{}/{}", path_name(directory)?, path_name(name)?)),
+        _ => None,
+    }
+}
+
+// The elements of a list, or a term that is not a list on its own.
+fn one_or_many(files: &Term) -> Result<Vec<Term>, Exception> {
+    let mut list = vec![];
+    let mut tail = files;
+    loop {
+        match tail {
+            Term::Functor { name, arguments } if name == "." && arguments.len() == 2 => {
+                list.push(arguments[0].clone());
+                tail = &arguments[1];
+            }
+            Term::Functor { name, arguments } if name == "[]" && arguments.is_empty() => {
+                return Ok(list)
+            }
+            file if list.is_empty() => return Ok(vec![file.clone()]),
+            Term::Variable { .. } => return Err(Exception::instantiation_error()),
+            _ => return Err(Exception::type_error("list", files.clone())),
+        }
+    }
+}
+
+fn load_file(database: &mut Database, path: &Path, context: &str) -> Result<String, Exception> {
+    database.unload(path);
+    database.mark_loaded(path, context);
+    let mut loader = Loader::new(database, Some(Rc::new(path.to_path_buf())), context);
+    loader.include_file(path)?;
+    loader.initialize();
+    let module = loader.module;
+    database.mark_loaded(path, &module);
+    if module != context {
+        check_exports(database, &module)?;
+    }
+    Ok(module)
+}
+
+fn import(
+    database: &mut Database,
+    into: &str,
+    from: &str,
+    imports: Option<&Term>,
+) -> Result<(), Exception> {
+    if into == from {
+        return Ok(());
+    }
+    let exports = database.exports(from).to_vec();
+    let keys = match imports {
+        Some(imports) => predicate_indicators(imports)?,
+        None => exports.clone(),
+    };
+    for key in keys {
+        if !exports.contains(&key) {
+            return Err(Exception::permission_error(
+                "import",
+                "private_procedure",
+                qualified_indicator(from, &key),
+            ));
+        }
+        database.import(into, from, key);
+    }
+    Ok(())
+}
+
+fn check_exports(database: &Database, module: &str) -> Result<(), Exception> {
+    for key in database.exports(module) {
+        if !database.is_defined(module, &key.0, key.1) {
+            return Err(Exception::existence_error(
+                "procedure",
+                qualified_indicator(module, key),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn qualified_indicator(module: &str, (name, arity): &(String, usize)) -> Term {
+    Term::functor(
+        ":",
+        [
+            Term::atom(module),
+            Term::functor("/", [Term::atom(name), Term::number(*arity as i64)]),
+        ],
+    )
+}
+
+fn warning(location: &str, message: &str) -> String {
+    format!("Warning: {}: {}", location, message)
+}
+
+struct Loader<'a> {
+    database: &'a mut Database,
+    source: Option<Rc<PathBuf>>,
+    module: String,
+    initialization: Vec<(Term, String)>,
+    previous: Option<(String, usize)>,
+    defined: HashSet<(String, usize)>,
+    discontiguous: HashSet<(String, usize)>,
+}
+
+impl<'a> Loader<'a> {
+    fn new(database: &'a mut Database, source: Option<Rc<PathBuf>>, module: &str) -> Self {
+        Self {
+            database,
+            source,
+            module: module.to_string(),
+            initialization: vec![],
+            previous: None,
+            defined: HashSet::new(),
+            discontiguous: HashSet::new(),
+        }
+    }
+
+    fn include_file(&mut self, path: &Path) -> Result<(), Exception> {
+        let code = match library::source(path) {
+            Some(code) => code.to_string(),
+            None => fs::read_to_string(path).map_err(|_| {
+                Exception::existence_error("source_sink", Term::atom(path.display()))
+            })?,
+        };
+        self.database.push_loading(path.to_path_buf());
+        let result = self.load(&code);
+        self.database.pop_loading();
+        result
+    }
+
+    fn load(&mut self, code: &str) -> Result<(), Exception> {
+        let sentences = MinplParser::parse_program(code).map_err(|error| {
+            let error = match self.database.current_file() {
+                Some(file) => error.with_path(&file.display().to_string()),
+                None => *error,
+            };
+            Exception::syntax_error(error)
+        })?;
+        for sentence in sentences {
+            let (term, line) = match sentence {
+                Sentence::Clause { clause, line } => (clause, line),
+                Sentence::Directive { goal, line } => (Term::functor(":-", [goal]), line),
+            };
+            let terms = match self.expand_term(term) {
+                Ok(terms) => terms,
+                Err(exception) => {
+                    self.warn(line, &exception.to_string());
+                    continue;
+                }
+            };
+            for term in terms {
+                match (term.name(), term.arguments()) {
+                    (Some(":-"), [goal]) => match self.expand_goal(goal.clone()) {
+                        Ok(goal) => self.directive(goal, line)?,
+                        Err(exception) => self.warn(line, &exception.to_string()),
+                    },
+                    _ => {
+                        if let Err(exception) = self.add_clause(term, line) {
+                            self.warn(line, &exception.to_string());
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // A sentence read from the file goes through `term_expansion/2`, which
+    // may turn it into a list of clauses. When the hook fails it is kept.
+    fn expand_term(&mut self, term: Term) -> Result<Vec<Term>, Exception> {
+        match self.call_hook("term_expansion", &term)? {
+            Some(expanded) => one_or_many(&expanded),
+            None => Ok(vec![term]),
+        }
+    }
+
+    // Body goals go through `goal_expansion/2` until it fails, gives the goal
+    // back unchanged or gives back a goal it is already expanding. Control
+    // constructs are expanded goal by goal.
+    fn expand_goal(&mut self, goal: Term) -> Result<Term, Exception> {
+        self.expand_goal_along(goal, &mut Vec::new())
+    }
+
+    fn expand_goal_along(&mut self, goal: Term, path: &mut Vec<Term>) -> Result<Term, Exception> {
+        match (goal.name(), goal.arguments()) {
+            (Some(name @ ("," | ";" | "->")), [first, second]) => {
+                return Ok(Term::functor(
+                    name,
+                    [
+                        self.expand_goal_along(first.clone(), path)?,
+                        self.expand_goal_along(second.clone(), path)?,
+                    ],
+                ))
+            }
+            (Some(name @ ("\\+" | "call" | "once" | "ignore")), [inner]) => {
+                return Ok(Term::functor(
+                    name,
+                    [self.expand_goal_along(inner.clone(), path)?],
+                ))
+            }
+            _ => {}
+        }
+        if let Term::Variable { .. } = goal {
+            return Ok(goal);
+        }
+        if path.iter().any(|seen| seen.is_variant(&goal)) {
+            return Ok(goal);
+        }
+        match self.call_hook("goal_expansion", &goal)? {
+            Some(expanded) if !expanded.is_variant(&goal) => {
+                path.push(goal);
+                let expanded = self.expand_goal_along(expanded, path);
+                path.pop();
+                expanded
+            }
+            _ => Ok(goal),
+        }
+    }
+
+    // The first answer of `Hook(Term, Expanded)`, tried in the module being
+    // loaded and then in `user`, wherever the hook is defined and succeeds.
+    fn call_hook(&mut self, hook: &str, term: &Term) -> Result<Option<Term>, Exception> {
+        let used = term.variables();
+        let expanded = (0..)
+            .map(|count| format!("Expanded{}", count))
+            .find(|name| !used.contains(name))
+            .unwrap();
+        let mut tried = Vec::new();
+        for module in [self.module.clone(), USER.to_string()] {
+            let definition = match self.database.lookup(&module, hook, 2) {
+                Some((definition, _)) if !tried.contains(&definition) => definition,
+                _ => continue,
+            };
+            let goal = Term::functor(
+                ":",
+                [
+                    Term::atom(&definition),
+                    Term::functor(hook, [term.clone(), Term::variable(&expanded)]),
+                ],
+            );
+            match solve(self.database, goal).next() {
+                Some(Ok(answer)) => return Ok(answer.get(&expanded).cloned()),
+                Some(Err(exception)) => return Err(exception),
+                None => tried.push(definition),
+            }
+        }
+        Ok(None)
+    }
+
+    // Runs the `initialization/1` goals once the whole file is loaded.
+    fn initialize(&mut self) {
+        for (goal, location) in std::mem::take(&mut self.initialization) {
+            self.run(goal, &location);
+        }
+    }
+
+    fn add_clause(&mut self, clause: Term, line: usize) -> Result<(), Exception> {
+        let clause = match (clause.name(), clause.arguments()) {
+            (Some("-->"), [head, body]) => {
+                let used = clause.variables();
+                let mut count = 0;
+                let mut fresh = || loop {
+                    let name = format!("S{}", count);
+                    count += 1;
+                    if !used.contains(&name) {
+                        break Term::variable(name);
+                    }
+                };
+                dcg_rule(head, body, &mut fresh)?
+            }
+            _ => clause,
+        };
+        let clause = match (clause.name(), clause.arguments()) {
+            (Some(":-"), [head, body]) => match self.expand_goal(body.clone()) {
+                Ok(body) => Term::functor(":-", [head.clone(), body]),
+                Err(exception) => {
+                    self.warn(line, &exception.to_string());
+                    return Ok(());
+                }
+            },
+            _ => clause,
+        };
+        let rule = Rule::from_term(clause);
+        let key = match &rule.head {
+            Term::Functor { name, arguments } => (name.clone(), arguments.len()),
+            Term::Variable { .. } => return Err(Exception::instantiation_error()),
+            head => return Err(Exception::type_error("callable", head.clone())),
+        };
+        if self.previous.as_ref() != Some(&key)
+            && self.defined.contains(&key)
+            && !self.discontiguous.contains(&key)
+        {
+            self.warn(
+                line,
+                &format!(
+                    "clauses of {}/{} are not together in the source file",
+                    key.0, key.1
+                ),
+            );
+        }
+        self.defined.insert(key.clone());
+        self.previous = Some(key);
+        self.database
+            .add_from(&self.module, rule, self.source.clone())
+    }
+
+    fn directive(&mut self, goal: Term, line: usize) -> Result<(), Exception> {
+        let arguments = match &goal {
+            Term::Functor { arguments, .. } => arguments.as_slice(),
+            _ => &[],
+        };
+        match (goal.name(), arguments) {
+            (Some("module"), [name, exports]) => {
+                self.module = match name {
+                    Term::Functor { name, arguments } if arguments.is_empty() => name.clone(),
+                    Term::Variable { .. } => return Err(Exception::instantiation_error()),
+                    name => return Err(Exception::type_error("atom", name.clone())),
+                };
+                let exports = predicate_indicators(exports)?;
+                self.database.define_module(&self.module, exports);
+            }
+            (Some("dynamic"), [specification]) => match predicate_indicators(specification) {
+                Ok(indicators) => {
+                    for (name, arity) in indicators {
+                        self.database.declare_dynamic(&self.module, &name, arity);
+                    }
+                }
+                Err(exception) => self.warn(line, &exception.to_string()),
+            },
+            (Some("meta_predicate"), [specification]) => {
+                match meta_predicate_heads(specification) {
+                    Ok(heads) => {
+                        for (name, meta_arguments) in heads {
+                            self.database.declare_meta_predicate(
+                                &self.module,
+                                &name,
+                                meta_arguments,
+                            );
+                        }
+                    }
+                    Err(exception) => self.warn(line, &exception.to_string()),
+                }
+            }
+            (Some("discontiguous"), [specification]) => match predicate_indicators(specification) {
+                Ok(indicators) => self.discontiguous.extend(indicators),
+                Err(exception) => self.warn(line, &exception.to_string()),
+            },
+            (Some("include"), [file]) => {
+                let path = source_path(self.database, file)?;
+                self.include_file(&path)?;
+            }
+            (Some("initialization"), [initialization]) => {
+                let location = self.location(line);
+                self.initialization
+                    .push((self.qualify(initialization.clone()), location));
+            }
+            (Some("initialization"), [initialization, when]) => match when.name() {
+                Some("now") => self.run(self.qualify(initialization.clone()), &self.location(line)),
+                Some("after_load") => {
+                    let location = self.location(line);
+                    self.initialization
+                        .push((self.qualify(initialization.clone()), location));
+                }
+                Some("main") => self.database.set_main(self.qualify(initialization.clone())),
+                _ => self.warn(
+                    line,
+                    &Exception::domain_error("initialization_type", when.clone()).to_string(),
+                ),
+            },
+            _ => self.run(self.qualify(goal), &self.location(line)),
+        }
+        Ok(())
+    }
+
+    fn run(&mut self, goal: Term, location: &str) {
+        match solve(self.database, goal.clone()).next() {
+            Some(Ok(_)) => {}
+            Some(Err(exception)) => eprintln!("{}", warning(location, &exception.to_string())),
+            None => eprintln!(
+                "{}",
+                warning(location, &format!("goal (directive) failed: {}", goal))
+            ),
+        }
+    }
+
+    fn qualify(&self, goal: Term) -> Term {
+        Term::functor(":", [Term::atom(&self.module), goal])
+    }
+
+    fn location(&self, line: usize) -> String {
+        match self.database.current_file() {
+            Some(file) => format!("{}:{}", file.display(), line),
+            None => format!("line {}", line),
+        }
+    }
+
+    fn warn(&self, line: usize, message: &str) {
+        eprintln!("{}", warning(&self.location(line), message));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{assignments::Assignments, proof_search::prove};
+    use std::env;
+
+    struct Files(PathBuf);
+
+    impl Files {
+        fn new(name: &str, files: &[(&str, &str)]) -> Self {
+            let directory = env::temp_dir().join(format!("minpl-{}-{}", name, std::process::id()));
+            for (file, content) in files {
+                let path = directory.join(file);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, content).unwrap();
+            }
+            Self(directory.canonicalize().unwrap())
+        }
+
+        fn path(&self, file: &str) -> PathBuf {
+            self.0.join(file)
+        }
+    }
+
+    impl Drop for Files {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn heads(database: &Database, name: &str, arity: usize) -> Vec<Term> {
+        database
+            .clauses(USER, name, arity)
+            .iter()
+            .map(|clause| clause.rule.head.clone())
+            .collect()
+    }
+
+    fn fact(name: &str, argument: &str) -> Term {
+        Term::functor(name, [Term::atom(argument)])
+    }
+
+    #[test]
+    fn consult_several_files() {
+        let files = Files::new("several", &[("a.pl", "f(a)."), ("b.pl", "f(b). g(b).")]);
+        let mut database = Database::empty();
+
+        consult_file(&mut database, &files.path("a.pl"), USER).unwrap();
+        consult_file(&mut database, &files.path("b.pl"), USER).unwrap();
+
+        assert_eq!(
+            heads(&database, "f", 1),
+            vec![fact("f", "a"), fact("f", "b")]
+        );
+        assert_eq!(heads(&database, "g", 1), vec![fact("g", "b")]);
+    }
+
+    #[test]
+    fn reconsult_replaces_clauses_of_the_file() {
+        let files = Files::new("reconsult", &[("a.pl", "f(a). g(a).")]);
+        let mut database = Database::empty().with_fact(fact("f", "z"));
+
+        consult_file(&mut database, &files.path("a.pl"), USER).unwrap();
+        fs::write(files.path("a.pl"), "f(b).").unwrap();
+        consult_file(&mut database, &files.path("a.pl"), USER).unwrap();
+
+        assert_eq!(
+            heads(&database, "f", 1),
+            vec![fact("f", "z"), fact("f", "b")]
+        );
+        assert!(!database.is_defined(USER, "g", 1));
+    }
+
+    #[test]
+    fn include_resolves_paths_against_including_file() {
+        let files = Files::new(
+            "include",
+            &[
+                ("main.pl", ":- include('lib/extra'). f(main)."),
+                ("lib/extra.pl", ":- include(more). f(extra)."),
+                ("lib/more.pl", "f(more)."),
+            ],
+        );
+        let mut database = Database::empty();
+
+        consult_file(&mut database, &files.path("main.pl"), USER).unwrap();
+
+        assert_eq!(
+            heads(&database, "f", 1),
+            vec![fact("f", "more"), fact("f", "extra"), fact("f", "main")]
+        );
+    }
+
+    #[test]
+    fn ensure_loaded_loads_a_file_once() {
+        let files = Files::new(
+            "ensure",
+            &[
+                ("main.pl", ":- ensure_loaded(lib). :- ensure_loaded([lib])."),
+                ("lib.pl", "f(lib)."),
+            ],
+        );
+        let mut database = Database::empty();
+
+        consult_file(&mut database, &files.path("main.pl"), USER).unwrap();
+
+        assert_eq!(heads(&database, "f", 1), vec![fact("f", "lib")]);
+    }
+
+    #[test]
+    fn load_files_from_a_subdirectory() {
+        let files = Files::new(
+            "subdirectory",
+            &[
+                (
+                    "main.pl",
+                    ":- ensure_loaded(sub/lib). :- consult(sub/deeper/more). f(main).",
+                ),
+                ("sub/lib.pl", "f(lib)."),
+                ("sub/deeper/more.pl", "f(more)."),
+            ],
+        );
+        let mut database = Database::empty();
+
+        consult_file(&mut database, &files.path("main.pl"), USER).unwrap();
+
+        assert_eq!(
+            heads(&database, "f", 1),
+            vec![fact("f", "lib"), fact("f", "more"), fact("f", "main")]
+        );
+    }
+
+    #[test]
+    fn consult_missing_file_is_an_existence_error() {
+        let mut database = Database::empty();
+
+        let result = consult(&mut database, &Term::atom("/no/such/file"), USER);
+
+        assert_eq!(
+            result,
+            Err(Exception::existence_error(
+                "source_sink",
+                Term::atom("/no/such/file")
+            ))
+        );
+    }
+
+    #[test]
+    fn syntax_error_in_file() {
+        let files = Files::new("syntax", &[("bad.pl", "f(a.")]);
+        let mut database = Database::empty();
+
+        let result = consult_file(&mut database, &files.path("bad.pl"), USER);
+
+        assert!(result.is_err());
+    }
+
+    fn answers(database: &mut Database, code: &str) -> Result<Vec<Assignments>, Exception> {
+        prove(database, MinplParser::parse_query(code).unwrap())
+    }
+
+    const LISTS: &str = ":- module(lists, [append/3, last/2]).
+        append([], L, L).
+        append([H|T], L, [H|R]) :- append(T, L, R).
+        last(L, X) :- append(_, [X], L).
+        helper(lists).";
+
+    #[test]
+    fn module_exports_are_imported_into_user() {
+        let files = Files::new("exports", &[("lists.pl", LISTS)]);
+        let mut database = Database::empty();
+
+        consult_file(&mut database, &files.path("lists.pl"), USER).unwrap();
+
+        assert_eq!(
+            answers(&mut database, "last([a, b], X)."),
+            Ok(vec![Assignments::empty().with("X", Term::atom("b"))])
+        );
+        assert_eq!(answers(&mut database, "helper(X)."), Ok(vec![]));
+        assert_eq!(
+            answers(&mut database, "lists:helper(X)."),
+            Ok(vec![Assignments::empty().with("X", Term::atom("lists"))])
+        );
+    }
+
+    #[test]
+    fn clause_bodies_run_in_their_module() {
+        let files = Files::new(
+            "bodies",
+            &[(
+                "m.pl",
+                ":- module(m, [get/1, run/1, shared/1]).
+                name(m).
+                get(X) :- name(X).
+                run(X) :- G = secret, call(G, X).
+                secret(s).
+                shared(X) :- from_user(X).",
+            )],
+        );
+        let mut database = Database::empty()
+            .with_fact(Term::functor("name", [Term::atom("user")]))
+            .with_fact(Term::functor("from_user", [Term::atom("u")]));
+
+        consult_file(&mut database, &files.path("m.pl"), USER).unwrap();
+
+        assert_eq!(
+            answers(&mut database, "get(X), name(Y), run(Z), shared(W)."),
+            Ok(vec![Assignments::empty()
+                .with("X", Term::atom("m"))
+                .with("Y", Term::atom("user"))
+                .with("Z", Term::atom("s"))
+                .with("W", Term::atom("u"))])
+        );
+    }
+
+    #[test]
+    fn use_module_with_import_list() {
+        let files = Files::new(
+            "imports",
+            &[
+                ("lists.pl", LISTS),
+                (
+                    "app.pl",
+                    ":- module(app, [main/1]).
+                    :- use_module(lists, [last/2]).
+                    main(X) :- last([x, y], X).
+                    main(X) :- append(X, [], []).",
+                ),
+            ],
+        );
+        let mut database = Database::empty();
+
+        consult_file(&mut database, &files.path("app.pl"), USER).unwrap();
+
+        assert_eq!(
+            answers(&mut database, "main(X)."),
+            Ok(vec![Assignments::empty().with("X", Term::atom("y"))])
+        );
+        assert_eq!(answers(&mut database, "last([a], X)."), Ok(vec![]));
+    }
+
+    #[test]
+    fn importing_a_private_predicate_is_an_error() {
+        let files = Files::new("private", &[("lists.pl", LISTS)]);
+        let mut database = Database::empty();
+        let query = format!(
+            "use_module('{}', [helper/1]).",
+            files.path("lists.pl").display()
+        );
+
+        let result = answers(&mut database, &query);
+
+        assert_eq!(
+            result,
+            Err(Exception::permission_error(
+                "import",
+                "private_procedure",
+                qualified_indicator("lists", &(String::from("helper"), 1))
+            ))
+        );
+    }
+
+    #[test]
+    fn exporting_an_undefined_predicate_is_an_error() {
+        let files = Files::new(
+            "undefined",
+            &[("m.pl", ":- module(m, [defined/0, missing/1]). defined.")],
+        );
+        let mut database = Database::empty();
+
+        let result = consult_file(&mut database, &files.path("m.pl"), USER);
+
+        assert_eq!(
+            result,
+            Err(Exception::existence_error(
+                "procedure",
+                qualified_indicator("m", &(String::from("missing"), 1))
+            ))
+        );
+    }
+
+    #[test]
+    fn assert_in_module_context() {
+        let files = Files::new(
+            "assert",
+            &[(
+                "m.pl",
+                ":- module(m, [remember/1]).
+                :- dynamic seen/1.
+                remember(X) :- assertz(seen(X)).",
+            )],
+        );
+        let mut database = Database::empty();
+
+        consult_file(&mut database, &files.path("m.pl"), USER).unwrap();
+
+        assert_eq!(
+            answers(&mut database, "remember(a), m:seen(X)."),
+            Ok(vec![Assignments::empty().with("X", Term::atom("a"))])
+        );
+        assert_eq!(answers(&mut database, "seen(X)."), Ok(vec![]));
+    }
+
+    #[test]
+    fn meta_arguments_run_in_the_caller_module() {
+        let files = Files::new(
+            "meta",
+            &[(
+                "m.pl",
+                ":- module(m, [run/1, run_plain/1]).
+                :- meta_predicate run(0).
+                name(m).
+                run(G) :- call(G).
+                run_plain(G) :- call(G).",
+            )],
+        );
+        let mut database = Database::empty().with_fact(Term::functor("name", [Term::atom("user")]));
+
+        consult_file(&mut database, &files.path("m.pl"), USER).unwrap();
+
+        assert_eq!(
+            answers(&mut database, "run(name(X)), run_plain(name(Y))."),
+            Ok(vec![Assignments::empty()
+                .with("X", Term::atom("user"))
+                .with("Y", Term::atom("m"))])
+        );
+    }
+
+    #[test]
+    fn grammar_rules_are_translated() {
+        let mut database = Database::empty();
+
+        consult_str(
+            &mut database,
+            ":- use_module(library(dcg/basics)).
+            greeting --> [hello], name.
+            name --> [world].
+            name --> [prolog].
+            natural(Ds) --> digits(Ds), eos.",
+        )
+        .unwrap();
+
+        assert_eq!(
+            answers(&mut database, "phrase(greeting, [hello, X])."),
+            Ok(vec![
+                Assignments::empty().with("X", Term::atom("world")),
+                Assignments::empty().with("X", Term::atom("prolog"))
+            ])
+        );
+        assert_eq!(
+            answers(&mut database, "phrase(natural(Ds), [0'4, 0'2])."),
+            Ok(vec![Assignments::empty().with(
+                "Ds",
+                MinplParser::parse_query("[52, 50].").unwrap()
+            )])
+        );
+    }
+
+    #[test]
+    fn term_expansion_rewrites_clauses() {
+        let mut database = Database::empty();
+
+        consult_str(
+            &mut database,
+            "term_expansion(pair(X), [left(X), right(X)]).
+            term_expansion(hidden(_), []).
+            term_expansion((:- note(X)), left(X)).
+            pair(a).
+            hidden(b).
+            :- note(c).",
+        )
+        .unwrap();
+
+        assert_eq!(
+            heads(&database, "left", 1),
+            vec![fact("left", "a"), fact("left", "c")]
+        );
+        assert_eq!(heads(&database, "right", 1), vec![fact("right", "a")]);
+        assert_eq!(heads(&database, "hidden", 1), vec![]);
+    }
+
+    #[test]
+    fn goal_expansion_reaches_a_fixpoint() {
+        let mut database = Database::empty();
+
+        consult_str(
+            &mut database,
+            "goal_expansion(twice(G), (G, G)).
+            goal_expansion(say(X), hear(X)).
+            goal_expansion(hear(X), X = hi).
+            p(X, Y) :- twice(say(X)), \\+ say(bye), (say(Y) -> true ; fail).",
+        )
+        .unwrap();
+
+        assert_eq!(
+            answers(&mut database, "p(X, Y)."),
+            Ok(vec![Assignments::empty()
+                .with("X", Term::atom("hi"))
+                .with("Y", Term::atom("hi"))])
+        );
+    }
+
+    #[test]
+    fn goal_expansion_stops_on_cycles() {
+        let mut database = Database::empty();
+
+        consult_str(
+            &mut database,
+            "goal_expansion(ping, pong).
+            goal_expansion(pong, ping).
+            goal_expansion(step, (step, done)).
+            ping.
+            step.
+            done.
+            p :- ping.
+            q :- step.",
+        )
+        .unwrap();
+
+        assert_eq!(
+            answers(&mut database, "p, q."),
+            Ok(vec![Assignments::empty()])
+        );
+    }
+
+    #[test]
+    fn expansion_hooks_fall_back_to_user() {
+        let files = Files::new(
+            "hooks",
+            &[(
+                "m.pl",
+                ":- module(m, [run/1]).
+                goal_expansion(local, true).
+                run(X) :- shout(X), local.",
+            )],
+        );
+        let mut database = Database::empty();
+        consult_str(&mut database, "goal_expansion(shout(X), X = loud).").unwrap();
+
+        consult_file(&mut database, &files.path("m.pl"), USER).unwrap();
+
+        assert_eq!(
+            answers(&mut database, "run(X)."),
+            Ok(vec![Assignments::empty().with("X", Term::atom("loud"))])
+        );
+    }
+
+    #[test]
+    fn expansion_errors_skip_the_clause() {
+        let mut database = Database::empty();
+
+        consult_str(
+            &mut database,
+            "term_expansion(bad, _) :- throw(oops).
+            goal_expansion(worse, _) :- throw(oops).
+            bad.
+            f(a) :- worse.
+            f(b).",
+        )
+        .unwrap();
+
+        assert_eq!(heads(&database, "bad", 0), vec![]);
+        assert_eq!(heads(&database, "f", 1), vec![fact("f", "b")]);
+    }
+
+    #[test]
+    fn failing_directives_do_not_stop_loading() {
+        let mut database = Database::empty();
+
+        consult_str(&mut database, ":- fail. :- throw(oops). f(a).").unwrap();
+
+        assert_eq!(heads(&database, "f", 1), vec![fact("f", "a")]);
+    }
+
+    #[test]
+    fn clauses_without_a_callable_head_are_skipped() {
+        let mut database = Database::empty();
+
+        consult_str(&mut database, "f(a). 1 :- true. (X :- true). f(b).").unwrap();
+
+        assert_eq!(
+            heads(&database, "f", 1),
+            vec![fact("f", "a"), fact("f", "b")]
+        );
+    }
+
+    #[test]
+    fn initialization_runs_after_loading() {
+        let mut database = Database::empty();
+
+        consult_str(
+            &mut database,
+            ":- dynamic log/1.
+            :- initialization(check).
+            check :- ready, assertz(log(ok)).
+            ready.",
+        )
+        .unwrap();
+
+        assert_eq!(heads(&database, "log", 1), vec![fact("log", "ok")]);
+    }
+
+    #[test]
+    fn initialization_main_is_kept_for_later() {
+        let mut database = Database::empty();
+
+        consult_str(&mut database, ":- initialization(main, main). main.").unwrap();
+
+        assert_eq!(
+            database.take_main(),
+            Some(Term::functor(":", [Term::atom(USER), Term::atom("main")]))
+        );
+    }
+
+    #[test]
+    fn directives_run_as_goals() {
+        let mut database = Database::empty();
+
+        consult_str(&mut database, ":- set_prolog_flag(unknown, error).").unwrap();
+
+        assert_eq!(database.flag("unknown"), Some(&Term::atom("error")));
+    }
+}
@@ -0,0 +1,396 @@
+use crate::{exception::Exception, term::Term};
+#[cfg(feature = "derive")]
+pub use minpl_derive::{FromTerm, ToTerm};
+
+// Conversions between Rust values and terms. Besides the implementations
+// here, `#[derive(ToTerm, FromTerm)]` maps a struct to a compound term named
+// after it in snake case, with one argument per field, and an enum to one
+// such term per variant. Fieldless structs and variants are atoms.
+//
+// `#[term(rename = "name")]` on a type, variant or field changes its name,
+// and `#[term(keyed)]` on a type writes named fields as `field(Value)`
+// arguments, read back in any order.
+pub trait ToTerm {
+    fn to_term(&self) -> Term;
+}
+
+pub trait FromTerm: Sized {
+    fn from_term(term: &Term) -> Result<Self, Exception>;
+}
+
+// An atom, where a `String` is a Prolog string.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Atom(pub String);
+
+impl ToTerm for Term {
+    fn to_term(&self) -> Term {
+        self.clone()
+    }
+}
+
+impl FromTerm for Term {
+    fn from_term(term: &Term) -> Result<Self, Exception> {
+        Ok(term.clone())
+    }
+}
+
+impl ToTerm for i64 {
+    fn to_term(&self) -> Term {
+        Term::number(*self)
+    }
+}
+
+impl FromTerm for i64 {
+    fn from_term(term: &Term) -> Result<Self, Exception> {
+        match term {
+            Term::Number { value } => Ok(*value),
+            term => Err(mismatch("integer", term)),
+        }
+    }
+}
+
+impl ToTerm for bool {
+    fn to_term(&self) -> Term {
+        Term::atom(self)
+    }
+}
+
+impl FromTerm for bool {
+    fn from_term(term: &Term) -> Result<Self, Exception> {
+        match term.name() {
+            Some("true") if term.arity() == 0 => Ok(true),
+            Some("false") if term.arity() == 0 => Ok(false),
+            _ => Err(mismatch("boolean", term)),
+        }
+    }
+}
+
+impl ToTerm for String {
+    fn to_term(&self) -> Term {
+        Term::string(self)
+    }
+}
+
+// Atoms are read as strings too.
+impl FromTerm for String {
+    fn from_term(term: &Term) -> Result<Self, Exception> {
+        match term {
+            Term::Str { value } => Ok(value.clone()),
+            Term::Functor { name, arguments } if arguments.is_empty() => Ok(name.clone()),
+            term => Err(mismatch("string", term)),
+        }
+    }
+}
+
+impl ToTerm for str {
+    fn to_term(&self) -> Term {
+        Term::string(self)
+    }
+}
+
+impl ToTerm for Atom {
+    fn to_term(&self) -> Term {
+        Term::atom(&self.0)
+    }
+}
+
+impl FromTerm for Atom {
+    fn from_term(term: &Term) -> Result<Self, Exception> {
+        match term {
+            Term::Functor { name, arguments } if arguments.is_empty() => Ok(Atom(name.clone())),
+            term => Err(mismatch("atom", term)),
+        }
+    }
+}
+
+impl<T: ToTerm> ToTerm for Vec<T> {
+    fn to_term(&self) -> Term {
+        Term::list(self.iter().map(ToTerm::to_term))
+    }
+}
+
+impl<T: FromTerm> FromTerm for Vec<T> {
+    fn from_term(term: &Term) -> Result<Self, Exception> {
+        term.list_elements()?.iter().map(T::from_term).collect()
+    }
+}
+
+// `None` is the atom `none` and `Some(X)` is `some(X)`.
+impl<T: ToTerm> ToTerm for Option<T> {
+    fn to_term(&self) -> Term {
+        match self {
+            Some(value) => Term::functor("some", [value.to_term()]),
+            None => Term::atom("none"),
+        }
+    }
+}
+
+impl<T: FromTerm> FromTerm for Option<T> {
+    fn from_term(term: &Term) -> Result<Self, Exception> {
+        if let Some(arguments) = arguments(term, "some", 1)? {
+            return T::from_term(&arguments[0]).map(Some);
+        }
+        match arguments(term, "none", 0)? {
+            Some(_) => Ok(None),
+            None => Err(shape_error(&[("some", 1), ("none", 0)], term)),
+        }
+    }
+}
+
+impl<T: ToTerm + ?Sized> ToTerm for Box<T> {
+    fn to_term(&self) -> Term {
+        self.as_ref().to_term()
+    }
+}
+
+impl<T: FromTerm> FromTerm for Box<T> {
+    fn from_term(term: &Term) -> Result<Self, Exception> {
+        T::from_term(term).map(Box::new)
+    }
+}
+
+impl<T: ToTerm + ?Sized> ToTerm for &T {
+    fn to_term(&self) -> Term {
+        (*self).to_term()
+    }
+}
+
+// The arguments of `term` if it is `name/arity`. Used by the derived
+// implementations, as are the errors below.
+pub fn arguments<'t>(
+    term: &'t Term,
+    name: &str,
+    arity: usize,
+) -> Result<Option<&'t [Term]>, Exception> {
+    match term {
+        Term::Variable { .. } => Err(Exception::instantiation_error()),
+        Term::Functor {
+            name: functor,
+            arguments,
+        } if functor == name && arguments.len() == arity => Ok(Some(arguments)),
+        _ => Ok(None),
+    }
+}
+
+// The arguments of a keyed term, `field(Value)`, in the order of `fields`.
+pub fn keyed_arguments<'t>(
+    arguments: &'t [Term],
+    fields: &[&str],
+) -> Result<Vec<&'t Term>, Exception> {
+    let mut values = vec![None; fields.len()];
+    for argument in arguments {
+        let position = match argument {
+            Term::Variable { .. } => return Err(Exception::instantiation_error()),
+            Term::Functor { name, arguments } if arguments.len() == 1 => {
+                fields.iter().position(|field| field == name)
+            }
+            _ => None,
+        };
+        match position {
+            Some(position) if values[position].is_none() => {
+                values[position] = Some(&argument.arguments()[0])
+            }
+            Some(_) => {
+                return Err(Exception::permission_error(
+                    "repeat",
+                    "field",
+                    argument.clone(),
+                ))
+            }
+            None => {
+                let shapes = fields.iter().map(|field| (*field, 1)).collect::<Vec<_>>();
+                return Err(shape_error(&shapes, argument));
+            }
+        }
+    }
+    values
+        .into_iter()
+        .zip(fields)
+        .map(|(value, field)| {
+            value.ok_or_else(|| Exception::existence_error("field", Term::atom(field)))
+        })
+        .collect()
+}
+
+// `type_error(Shapes, Culprit)`, where `Shapes` is the indicator of the one
+// expected shape or the list of the indicators of all of them.
+pub fn shape_error(shapes: &[(&str, usize)], culprit: &Term) -> Exception {
+    if let Term::Variable { .. } = culprit {
+        return Exception::instantiation_error();
+    }
+    let mut indicators = shapes
+        .iter()
+        .map(|(name, arity)| Term::functor("/", [Term::atom(name), Term::number(*arity as i64)]))
+        .collect::<Vec<_>>();
+    let expected = match indicators.len() {
+        1 => indicators.remove(0),
+        _ => Term::list(indicators),
+    };
+    Exception::error(Term::functor("type_error", [expected, culprit.clone()]))
+}
+
+fn mismatch(kind: &str, culprit: &Term) -> Exception {
+    match culprit {
+        Term::Variable { .. } => Exception::instantiation_error(),
+        culprit => Exception::type_error(kind, culprit.clone()),
+    }
+}
+
+// The tests use the derive macros.
+#[cfg(all(test, feature = "derive"))]
+mod test {
+    use super::*;
+    use crate::parser::MinplParser;
+
+    #[derive(Debug, PartialEq, ToTerm, FromTerm)]
+    struct Person {
+        name: Atom,
+        age: i64,
+        emails: Vec<String>,
+    }
+
+    #[derive(Debug, PartialEq, ToTerm, FromTerm)]
+    enum Shape {
+        Circle(i64),
+        #[term(rename = "rect")]
+        Rectangle {
+            width: i64,
+            height: i64,
+        },
+        Empty,
+    }
+
+    #[derive(Debug, PartialEq, ToTerm, FromTerm)]
+    #[term(keyed, rename = "config")]
+    struct Settings {
+        verbose: bool,
+        #[term(rename = "level")]
+        log_level: Option<i64>,
+    }
+
+    #[derive(Debug, PartialEq, ToTerm, FromTerm)]
+    struct Pair<T>(T, T);
+
+    fn term(code: &str) -> Term {
+        MinplParser::parse_query(&format!("{}.", code)).unwrap()
+    }
+
+    #[test]
+    fn structs_are_compound_terms() {
+        let person = Person {
+            name: Atom(String::from("ada")),
+            age: 36,
+            emails: vec![String::from("ada@example.com")],
+        };
+
+        assert_eq!(
+            person.to_term(),
+            term("person(ada, 36, [\"ada@example.com\"])")
+        );
+        assert_eq!(Person::from_term(&person.to_term()), Ok(person));
+    }
+
+    #[test]
+    fn enum_variants_are_alternative_functors() {
+        let shapes = vec![
+            Shape::Circle(1),
+            Shape::Rectangle {
+                width: 2,
+                height: 3,
+            },
+            Shape::Empty,
+        ];
+
+        assert_eq!(shapes.to_term(), term("[circle(1), rect(2, 3), empty]"));
+        assert_eq!(Vec::<Shape>::from_term(&shapes.to_term()), Ok(shapes));
+    }
+
+    #[test]
+    fn keyed_fields_are_read_in_any_order() {
+        let settings = Settings {
+            verbose: true,
+            log_level: Some(2),
+        };
+
+        assert_eq!(
+            settings.to_term(),
+            term("config(verbose(true), level(some(2)))")
+        );
+        assert_eq!(
+            Settings::from_term(&term("config(level(none), verbose(false))")),
+            Ok(Settings {
+                verbose: false,
+                log_level: None
+            })
+        );
+    }
+
+    #[test]
+    fn generic_types() {
+        let pair = Pair(Atom(String::from("a")), Atom(String::from("b")));
+
+        assert_eq!(pair.to_term(), term("pair(a, b)"));
+        assert_eq!(Pair::from_term(&term("pair(a, b)")), Ok(pair));
+    }
+
+    #[test]
+    fn shape_mismatches_name_the_expected_shapes() {
+        assert_eq!(
+            Person::from_term(&term("person(ada, 36)")),
+            Err(Exception::error(term(
+                "type_error(person/3, person(ada, 36))"
+            )))
+        );
+        assert_eq!(
+            Shape::from_term(&term("square(1)")),
+            Err(Exception::error(term(
+                "type_error([circle/1, rect/2, empty/0], square(1))"
+            )))
+        );
+        assert_eq!(
+            Person::from_term(&term("person(ada, old, [])")),
+            Err(Exception::type_error("integer", Term::atom("old")))
+        );
+        assert_eq!(
+            Shape::from_term(&term("_")),
+            Err(Exception::instantiation_error())
+        );
+    }
+
+    #[test]
+    fn keyed_field_errors() {
+        assert_eq!(
+            Settings::from_term(&term("config(verbose(true), verbose(false))")),
+            Err(Exception::permission_error(
+                "repeat",
+                "field",
+                term("verbose(false)")
+            ))
+        );
+        assert_eq!(
+            Settings::from_term(&term("config(verbose(true), colour(red))")),
+            Err(Exception::error(term(
+                "type_error([verbose/1, level/1], colour(red))"
+            )))
+        );
+        assert_eq!(
+            keyed_arguments(&[term("verbose(true)")], &["verbose", "level"]),
+            Err(Exception::existence_error("field", Term::atom("level")))
+        );
+    }
+
+    #[test]
+    fn primitives() {
+        assert_eq!(i64::from_term(&Term::number(3)), Ok(3));
+        assert_eq!(String::from_term(&term("abc")), Ok(String::from("abc")));
+        assert_eq!(
+            Vec::<i64>::from_term(&term("[1|_]")),
+            Err(Exception::instantiation_error())
+        );
+        assert_eq!(
+            bool::from_term(&term("yes")),
+            Err(Exception::type_error("boolean", Term::atom("yes")))
+        );
+        assert_eq!(Some(1).to_term(), term("some(1)"));
+    }
+}
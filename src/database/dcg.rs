@@ -0,0 +1,156 @@
+use crate::{exception::Exception, term::Term};
+
+// Translates `Head --> Body` into a clause whose non-terminals take the list
+// to parse and the rest of it as two extra arguments. `fresh` gives the
+// variables threading the list between the goals of the body.
+pub fn dcg_rule(
+    head: &Term,
+    body: &Term,
+    fresh: &mut dyn FnMut() -> Term,
+) -> Result<Term, Exception> {
+    let (start, end) = (fresh(), fresh());
+    let (head, body) = match head {
+        Term::Functor { name, arguments } if name == "," && arguments.len() == 2 => {
+            let middle = fresh();
+            let pushback = terminals(&arguments[1], end.clone(), middle.clone())?;
+            let body = Term::functor(
+                ",",
+                [dcg_body(body, start.clone(), middle, fresh)?, pushback],
+            );
+            (non_terminal(&arguments[0], start, end)?, body)
+        }
+        head => (
+            non_terminal(head, start.clone(), end.clone())?,
+            dcg_body(body, start, end, fresh)?,
+        ),
+    };
+    Ok(Term::functor(":-", [head, body]))
+}
+
+// The goal parsing `start` with `body`, leaving `end`.
+pub fn dcg_body(
+    body: &Term,
+    start: Term,
+    end: Term,
+    fresh: &mut dyn FnMut() -> Term,
+) -> Result<Term, Exception> {
+    let arguments = body.arguments();
+    let goal = match (body, body.name(), arguments) {
+        (Term::Variable { .. }, _, _) => Term::functor("phrase", [body.clone(), start, end]),
+        (_, Some(","), [first, second]) => {
+            let middle = fresh();
+            Term::functor(
+                ",",
+                [
+                    dcg_body(first, start, middle.clone(), fresh)?,
+                    dcg_body(second, middle, end, fresh)?,
+                ],
+            )
+        }
+        (_, Some(";"), [first, second]) => Term::functor(
+            ";",
+            [
+                dcg_body(first, start.clone(), end.clone(), fresh)?,
+                dcg_body(second, start, end, fresh)?,
+            ],
+        ),
+        (_, Some("->"), [condition, then]) => {
+            let middle = fresh();
+            Term::functor(
+                "->",
+                [
+                    dcg_body(condition, start, middle.clone(), fresh)?,
+                    dcg_body(then, middle, end, fresh)?,
+                ],
+            )
+        }
+        (_, Some("\\+"), [goal]) => Term::functor(
+            ",",
+            [
+                Term::functor("\\+", [dcg_body(goal, start.clone(), fresh(), fresh)?]),
+                Term::functor("=", [start, end]),
+            ],
+        ),
+        (_, Some("{}"), [goal]) => {
+            Term::functor(",", [goal.clone(), Term::functor("=", [start, end])])
+        }
+        (_, Some("!"), []) => {
+            Term::functor(",", [Term::atom("!"), Term::functor("=", [start, end])])
+        }
+        (_, Some("call"), [_, ..]) => {
+            Term::functor("call", arguments.iter().cloned().chain([start, end]))
+        }
+        (_, Some(":"), [module, goal]) => {
+            Term::functor(":", [module.clone(), dcg_body(goal, start, end, fresh)?])
+        }
+        (_, Some("[]" | "."), _) | (Term::Str { .. }, _, _) => terminals(body, start, end)?,
+        _ => non_terminal(body, start, end)?,
+    };
+    Ok(goal)
+}
+
+// `start = [T1, ..., Tn|end]` for a list or a string of terminals.
+fn terminals(list: &Term, start: Term, end: Term) -> Result<Term, Exception> {
+    let elements = match list {
+        Term::Str { value } => value.chars().map(|c| Term::number(c as i64)).collect(),
+        list => {
+            let mut elements = vec![];
+            let mut tail = list;
+            loop {
+                match (tail.name(), tail.arguments()) {
+                    (Some("."), [element, rest]) => {
+                        elements.push(element.clone());
+                        tail = rest;
+                    }
+                    (Some("[]"), []) => break elements,
+                    _ => return Err(Exception::type_error("list", list.clone())),
+                }
+            }
+        }
+    };
+    Ok(Term::functor(
+        "=",
+        [start, Term::list_with_tail(elements, end)],
+    ))
+}
+
+fn non_terminal(term: &Term, start: Term, end: Term) -> Result<Term, Exception> {
+    match term {
+        Term::Functor { name, arguments } => Ok(Term::functor(
+            name,
+            arguments.iter().cloned().chain([start, end]),
+        )),
+        Term::Variable { .. } => Err(Exception::instantiation_error()),
+        term => Err(Exception::type_error("callable", term.clone())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::MinplParser;
+    use test_case::test_case;
+
+    fn translate(code: &str) -> Result<String, Exception> {
+        let rule = MinplParser::parse_query(code).unwrap();
+        let mut count = 0;
+        let mut fresh = || {
+            count += 1;
+            Term::variable(format!("S{}", count - 1))
+        };
+        let arguments = rule.arguments();
+        dcg_rule(&arguments[0], &arguments[1], &mut fresh).map(|clause| clause.to_string())
+    }
+
+    #[test_case("a --> [x], b." => Ok("a(S0, S1):-S0=[x|S2], b(S2, S1)".into()); "terminals and non terminals")]
+    #[test_case("a --> \"ab\"." => Ok("a(S0, S1):-S0=[97, 98|S1]".into()); "string terminals")]
+    #[test_case("a(X) --> {X = 1}, !." => Ok("a(X, S0, S1):-(X=1, S0=S2), !, S2=S1".into()); "goals and cut")]
+    #[test_case("a --> b ; []." => Ok("a(S0, S1):-b(S0, S1);S0=S1".into()); "disjunction")]
+    #[test_case("a, [x] --> b." => Ok("a(S0, S1):-b(S0, S2), S1=[x|S2]".into()); "pushback")]
+    #[test_case("a --> X." => Ok("a(S0, S1):-phrase(X, S0, S1)".into()); "variable body")]
+    #[test_case("a --> call(g, x)." => Ok("a(S0, S1):-call(g, x, S0, S1)".into()); "call")]
+    #[test_case("a --> 1." => Err(Exception::type_error("callable", Term::number(1))); "not callable")]
+    fn translation(code: &str) -> Result<String, Exception> {
+        translate(code)
+    }
+}
@@ -0,0 +1,207 @@
+use crate::{exception::Exception, term::Term};
+use std::{collections::HashMap, fmt, rc::Rc};
+
+// A predicate implemented in Rust. It is called with its arguments resolved
+// against the current bindings, and each solution gives one term per argument
+// for the argument to unify with.
+pub trait ForeignPredicate {
+    fn solve(&self, arguments: &[Term]) -> Result<Solutions, Exception>;
+}
+
+pub enum Solutions {
+    // At most one solution, leaving no choicepoint.
+    Deterministic(Option<Vec<Term>>),
+    // Solutions tried one at a time on backtracking. An error stops the
+    // iteration and is thrown from the call.
+    Nondeterministic(Box<dyn Iterator<Item = Result<Vec<Term>, Exception>>>),
+}
+
+impl<F> ForeignPredicate for F
+where
+    F: Fn(&[Term]) -> Result<Solutions, Exception>,
+{
+    fn solve(&self, arguments: &[Term]) -> Result<Solutions, Exception> {
+        self(arguments)
+    }
+}
+
+// A predicate with at most one solution, `None` being failure.
+pub fn deterministic<F>(predicate: F) -> impl ForeignPredicate
+where
+    F: Fn(&[Term]) -> Result<Option<Vec<Term>>, Exception>,
+{
+    move |arguments: &[Term]| predicate(arguments).map(Solutions::Deterministic)
+}
+
+// A predicate with any number of solutions, produced lazily.
+pub fn nondeterministic<F, I>(predicate: F) -> impl ForeignPredicate
+where
+    F: Fn(&[Term]) -> Result<I, Exception>,
+    I: IntoIterator<Item = Vec<Term>>,
+    I::IntoIter: 'static,
+{
+    move |arguments: &[Term]| {
+        let solutions = predicate(arguments)?.into_iter().map(Ok);
+        Ok(Solutions::Nondeterministic(Box::new(solutions)))
+    }
+}
+
+// Foreign predicates registered by name and arity.
+#[derive(Clone, Default)]
+pub struct ForeignPredicates(HashMap<(String, usize), Rc<dyn ForeignPredicate>>);
+
+impl ForeignPredicates {
+    pub fn insert(&mut self, name: &str, arity: usize, predicate: Rc<dyn ForeignPredicate>) {
+        self.0.insert((name.to_string(), arity), predicate);
+    }
+
+    pub fn get(&self, name: &str, arity: usize) -> Option<Rc<dyn ForeignPredicate>> {
+        self.0.get(&(name.to_string(), arity)).cloned()
+    }
+}
+
+impl fmt::Debug for ForeignPredicates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
+
+impl PartialEq for ForeignPredicates {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len()
+            && self.0.iter().all(|(key, predicate)| {
+                other
+                    .0
+                    .get(key)
+                    .is_some_and(|other| Rc::ptr_eq(predicate, other))
+            })
+    }
+}
+
+impl Eq for ForeignPredicates {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        assignments::Assignments, database::Database, parser::MinplParser, proof_search::prove,
+    };
+
+    const USERS: &[(i64, &str)] = &[(1, "ada"), (2, "alan"), (3, "grace")];
+
+    fn database() -> Database {
+        let mut database = MinplParser::parse_database(
+            "lookup_user(1, nobody).
+            admin(Name) :- lookup_user(1, Name).",
+        )
+        .unwrap();
+        database.register_foreign(
+            "lookup_user",
+            2,
+            nondeterministic(|arguments: &[Term]| {
+                let id = match &arguments[0] {
+                    Term::Number { value } => Some(*value),
+                    Term::Variable { .. } => None,
+                    id => return Err(Exception::type_error("integer", id.clone())),
+                };
+                Ok(USERS
+                    .iter()
+                    .filter(move |(user, _)| id.is_none_or(|id| id == *user))
+                    .map(|(id, name)| vec![Term::number(*id), Term::atom(name)]))
+            }),
+        );
+        database.register_foreign(
+            "succ_or_zero",
+            2,
+            deterministic(|arguments: &[Term]| match &arguments[0] {
+                Term::Number { value } if *value >= 0 => {
+                    Ok(Some(vec![arguments[0].clone(), Term::number(value + 1)]))
+                }
+                Term::Number { .. } => Ok(None),
+                _ => Err(Exception::instantiation_error()),
+            }),
+        );
+        database
+    }
+
+    fn answers(code: &str) -> Result<Vec<Assignments>, Exception> {
+        prove(&mut database(), MinplParser::parse_query(code).unwrap())
+    }
+
+    #[test]
+    fn deterministic_predicates_bind_their_arguments() {
+        assert_eq!(
+            answers("succ_or_zero(1, X)."),
+            Ok(vec![Assignments::empty().with("X", Term::number(2))])
+        );
+        assert_eq!(answers("succ_or_zero(-1, X)."), Ok(vec![]));
+        assert_eq!(answers("succ_or_zero(1, 3)."), Ok(vec![]));
+        assert_eq!(
+            answers("succ_or_zero(X, Y)."),
+            Err(Exception::instantiation_error())
+        );
+    }
+
+    #[test]
+    fn nondeterministic_predicates_backtrack_over_solutions() {
+        assert_eq!(
+            answers("lookup_user(Id, Name), Id \\== 2."),
+            Ok(vec![
+                Assignments::empty()
+                    .with("Id", Term::number(1))
+                    .with("Name", Term::atom("ada")),
+                Assignments::empty()
+                    .with("Id", Term::number(3))
+                    .with("Name", Term::atom("grace")),
+            ])
+        );
+        assert_eq!(
+            answers("lookup_user(Id, grace)."),
+            Ok(vec![Assignments::empty().with("Id", Term::number(3))])
+        );
+    }
+
+    #[test]
+    fn foreign_predicates_come_before_clauses() {
+        assert_eq!(
+            answers("admin(Name)."),
+            Ok(vec![Assignments::empty().with("Name", Term::atom("ada"))])
+        );
+    }
+
+    #[test]
+    fn errors_are_thrown_from_the_call() {
+        assert_eq!(
+            answers("catch(lookup_user(a, _), error(E, _), true)."),
+            Ok(vec![Assignments::empty().with(
+                "E",
+                Term::functor("type_error", [Term::atom("integer"), Term::atom("a")])
+            )])
+        );
+    }
+
+    #[test]
+    fn errors_of_later_solutions_are_thrown_on_backtracking() {
+        let mut database = Database::empty();
+        database.register_foreign("items", 1, |_: &[Term]| {
+            let solutions = vec![
+                Ok(vec![Term::atom("a")]),
+                Err(Exception::new(Term::atom("broken"))),
+            ];
+            Ok(Solutions::Nondeterministic(Box::new(solutions.into_iter())))
+        });
+
+        let result = prove(
+            &mut database,
+            MinplParser::parse_query("catch(items(X), E, true).").unwrap(),
+        );
+
+        assert_eq!(
+            result,
+            Ok(vec![
+                Assignments::empty().with("X", Term::atom("a")),
+                Assignments::empty().with("E", Term::atom("broken")),
+            ])
+        );
+    }
+}
@@ -1,39 +1,653 @@
+mod dcg;
+mod foreign;
 mod rule;
+#[cfg(feature = "serde")]
+mod serialize;
 
-use crate::term::Term;
+use crate::{exception::Exception, proof_search::streams::Streams, term::Term};
+pub use dcg::*;
+pub use foreign::*;
 pub use rule::*;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Clause {
+    pub id: usize,
+    pub rule: Rc<Rule>,
+    pub source: Option<Rc<PathBuf>>,
+}
+
+pub type Clauses = Rc<Vec<Clause>>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Predicate {
+    clauses: Clauses,
+    dynamic: bool,
+    // For each argument, whether it is qualified with the calling module.
+    meta_arguments: Option<Vec<bool>>,
+}
+
+type Key = (String, usize);
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Module {
+    #[cfg_attr(feature = "serde", serde(with = "serialize::entries"))]
+    predicates: HashMap<Key, Predicate>,
+    exports: Vec<Key>,
+    #[cfg_attr(feature = "serde", serde(with = "serialize::entries"))]
+    imports: HashMap<Key, String>,
+}
+
+pub const USER: &str = "user";
+
+// Serialized with its clauses, flags and loaded files. Foreign predicates
+// are left out and must be registered again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Database {
-    rules: Vec<Rule>,
+    modules: HashMap<String, Module>,
+    next_clause: usize,
+    loaded: HashMap<PathBuf, String>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    loading: Vec<PathBuf>,
+    flags: HashMap<String, Term>,
+    // Global variables set with `nb_setval/2`, kept across queries.
+    globals: HashMap<String, Term>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    streams: Streams,
+    main: Option<Term>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    foreign: ForeignPredicates,
 }
 
 impl Database {
     pub fn empty() -> Self {
-        Self { rules: vec![] }
+        Self {
+            modules: HashMap::new(),
+            next_clause: 0,
+            loaded: HashMap::new(),
+            loading: vec![],
+            flags: HashMap::from([
+                (String::from("bounded"), Term::atom("true")),
+                (String::from("evaluation"), Term::atom("top_down")),
+                (String::from("max_integer"), Term::number(i64::MAX)),
+                (String::from("min_integer"), Term::number(i64::MIN)),
+                (String::from("unknown"), Term::atom("fail")),
+            ]),
+            globals: HashMap::new(),
+            streams: Streams::default(),
+            main: None,
+            foreign: ForeignPredicates::default(),
+        }
     }
 
     pub fn with_fact(mut self, fact: Term) -> Self {
-        self.rules.push(Rule {
+        self.add(Rule {
             head: fact,
             body: vec![],
-        });
+        })
+        .expect("facts need a callable head");
         self
     }
 
     pub fn with_rule<I: IntoIterator<Item = Term>>(mut self, head: Term, body: I) -> Self {
-        self.rules.push(Rule {
+        self.add(Rule {
             head,
             body: body.into_iter().collect(),
-        });
+        })
+        .expect("rules need a callable head");
         self
     }
 
-    pub fn add(&mut self, rule: Rule) {
-        self.rules.push(rule);
+    pub fn add(&mut self, rule: Rule) -> Result<(), Exception> {
+        self.insert(USER, rule, None, false)
+    }
+
+    pub fn add_from(
+        &mut self,
+        module: &str,
+        rule: Rule,
+        source: Option<Rc<PathBuf>>,
+    ) -> Result<(), Exception> {
+        self.insert(module, rule, source, false)
+    }
+
+    pub fn asserta(&mut self, module: &str, rule: Rule) -> Result<(), Exception> {
+        self.insert(module, rule, None, true)
+    }
+
+    pub fn assertz(&mut self, module: &str, rule: Rule) -> Result<(), Exception> {
+        self.insert(module, rule, None, false)
+    }
+
+    // Running calls hold on to the clause list they started with, so changes
+    // copy the list instead of modifying it under them.
+    pub fn clauses(&self, module: &str, name: &str, arity: usize) -> Clauses {
+        self.predicate(module, name, arity)
+            .map(|predicate| predicate.clauses.clone())
+            .unwrap_or_default()
+    }
+
+    // A predicate is looked up in the module itself, then in the modules it
+    // imports from and finally in `user`. Returns the module defining it.
+    pub fn lookup(&self, module: &str, name: &str, arity: usize) -> Option<(String, Clauses)> {
+        let key = (name.to_string(), arity);
+        let definition = self.definition_module(module, &key);
+        match self.predicate(&definition, name, arity) {
+            Some(predicate) => Some((definition, predicate.clauses.clone())),
+            None if module != USER => self.lookup(USER, name, arity),
+            None => None,
+        }
+    }
+
+    // The module whose predicate a goal in `module` would change: the one it
+    // was imported from, if any.
+    pub fn definition_module(&self, module: &str, key: &(String, usize)) -> String {
+        match self.modules.get(module) {
+            Some(local) if !local.predicates.contains_key(key) => local
+                .imports
+                .get(key)
+                .cloned()
+                .unwrap_or_else(|| module.to_string()),
+            _ => module.to_string(),
+        }
+    }
+
+    pub fn erase(&mut self, module: &str, name: &str, arity: usize, id: usize) -> bool {
+        match self.predicate_mut(module, name, arity) {
+            Some(predicate) => {
+                let length = predicate.clauses.len();
+                Rc::make_mut(&mut predicate.clauses).retain(|clause| clause.id != id);
+                predicate.clauses.len() < length
+            }
+            None => false,
+        }
+    }
+
+    pub fn abolish(&mut self, module: &str, name: &str, arity: usize) {
+        if let Some(module) = self.modules.get_mut(module) {
+            module.predicates.remove(&(name.to_string(), arity));
+        }
+    }
+
+    pub fn is_defined(&self, module: &str, name: &str, arity: usize) -> bool {
+        self.predicate(module, name, arity).is_some()
+    }
+
+    pub fn is_dynamic(&self, module: &str, name: &str, arity: usize) -> bool {
+        self.predicate(module, name, arity)
+            .is_some_and(|predicate| predicate.dynamic)
+    }
+
+    pub fn declare_dynamic(&mut self, module: &str, name: &str, arity: usize) {
+        self.module_mut(module)
+            .predicates
+            .entry((name.to_string(), arity))
+            .or_default()
+            .dynamic = true;
+    }
+
+    pub fn declare_meta_predicate(&mut self, module: &str, name: &str, meta_arguments: Vec<bool>) {
+        let key = (name.to_string(), meta_arguments.len());
+        self.module_mut(module)
+            .predicates
+            .entry(key)
+            .or_default()
+            .meta_arguments = Some(meta_arguments);
     }
 
-    pub fn rules(&self) -> &[Rule] {
-        &self.rules
+    pub fn meta_arguments(&self, module: &str, name: &str, arity: usize) -> Option<&[bool]> {
+        self.predicate(module, name, arity)?
+            .meta_arguments
+            .as_deref()
+    }
+
+    pub fn define_module(&mut self, module: &str, exports: Vec<(String, usize)>) {
+        self.module_mut(module).exports = exports;
+    }
+
+    pub fn exports(&self, module: &str) -> &[(String, usize)] {
+        self.modules
+            .get(module)
+            .map_or(&[], |module| module.exports.as_slice())
+    }
+
+    pub fn import(&mut self, into: &str, from: &str, key: (String, usize)) {
+        self.module_mut(into).imports.insert(key, from.to_string());
+    }
+
+    // Removes everything a file added, so that loading it again does not
+    // duplicate its clauses.
+    pub fn unload(&mut self, source: &Path) {
+        let from_source =
+            |clause: &Clause| clause.source.as_deref().map(PathBuf::as_path) == Some(source);
+        for module in self.modules.values_mut() {
+            module.predicates.retain(|_, predicate| {
+                if predicate.clauses.iter().any(from_source) {
+                    Rc::make_mut(&mut predicate.clauses).retain(|clause| !from_source(clause));
+                    predicate.dynamic || !predicate.clauses.is_empty()
+                } else {
+                    true
+                }
+            });
+        }
+        self.loaded.remove(source);
+    }
+
+    pub fn mark_loaded(&mut self, source: &Path, module: &str) {
+        self.loaded.insert(source.to_path_buf(), module.to_string());
+    }
+
+    pub fn loaded_module(&self, source: &Path) -> Option<&str> {
+        self.loaded.get(source).map(String::as_str)
+    }
+
+    pub fn push_loading(&mut self, file: PathBuf) {
+        self.loading.push(file);
+    }
+
+    pub fn pop_loading(&mut self) {
+        self.loading.pop();
+    }
+
+    pub fn current_file(&self) -> Option<&Path> {
+        self.loading.last().map(PathBuf::as_path)
+    }
+
+    pub fn flag(&self, name: &str) -> Option<&Term> {
+        self.flags.get(name)
+    }
+
+    pub fn flags(&self) -> Vec<(String, Term)> {
+        let mut flags = self
+            .flags
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect::<Vec<_>>();
+        flags.sort();
+        flags
+    }
+
+    pub fn set_flag(&mut self, name: &str, value: Term) {
+        self.flags.insert(name.to_string(), value);
+    }
+
+    pub fn global(&self, key: &str) -> Option<&Term> {
+        self.globals.get(key)
+    }
+
+    // Returns the value it replaces.
+    pub fn set_global(&mut self, key: &str, value: Option<Term>) -> Option<Term> {
+        match value {
+            Some(value) => self.globals.insert(key.to_string(), value),
+            None => self.globals.remove(key),
+        }
+    }
+
+    // Open streams and the current input and output, which stay as they are
+    // from one query to the next.
+    pub fn streams(&mut self) -> &mut Streams {
+        &mut self.streams
+    }
+
+    // The goal of `initialization(Goal, main)`, run once everything is loaded.
+    pub fn set_main(&mut self, goal: Term) {
+        self.main = Some(goal);
+    }
+
+    pub fn take_main(&mut self) -> Option<Term> {
+        self.main.take()
+    }
+
+    // Calls of `name/arity` run `predicate` instead of looking for clauses.
+    pub fn register_foreign<P: ForeignPredicate + 'static>(
+        &mut self,
+        name: &str,
+        arity: usize,
+        predicate: P,
+    ) {
+        self.foreign.insert(name, arity, Rc::new(predicate));
+    }
+
+    pub fn foreign(&self, name: &str, arity: usize) -> Option<Rc<dyn ForeignPredicate>> {
+        self.foreign.get(name, arity)
+    }
+
+    fn predicate(&self, module: &str, name: &str, arity: usize) -> Option<&Predicate> {
+        self.modules
+            .get(module)?
+            .predicates
+            .get(&(name.to_string(), arity))
+    }
+
+    fn predicate_mut(&mut self, module: &str, name: &str, arity: usize) -> Option<&mut Predicate> {
+        self.modules
+            .get_mut(module)?
+            .predicates
+            .get_mut(&(name.to_string(), arity))
+    }
+
+    fn module_mut(&mut self, module: &str) -> &mut Module {
+        self.modules.entry(module.to_string()).or_default()
+    }
+
+    fn insert(
+        &mut self,
+        module: &str,
+        rule: Rule,
+        source: Option<Rc<PathBuf>>,
+        front: bool,
+    ) -> Result<(), Exception> {
+        let key = match &rule.head {
+            Term::Functor { name, arguments } => (name.clone(), arguments.len()),
+            Term::Variable { .. } => return Err(Exception::instantiation_error()),
+            head => return Err(Exception::type_error("callable", head.clone())),
+        };
+        let clause = Clause {
+            id: self.next_clause,
+            rule: Rc::new(rule),
+            source,
+        };
+        self.next_clause += 1;
+        let predicate = self.module_mut(module).predicates.entry(key).or_default();
+        let clauses = Rc::make_mut(&mut predicate.clauses);
+        if front {
+            clauses.insert(0, clause);
+        } else {
+            clauses.push(clause);
+        }
+        Ok(())
+    }
+}
+
+pub fn predicate_indicators(specification: &Term) -> Result<Vec<(String, usize)>, Exception> {
+    match specification {
+        Term::Variable { .. } => Err(Exception::instantiation_error()),
+        Term::Functor { name, arguments }
+            if (name == "," || name == ".") && arguments.len() == 2 =>
+        {
+            let mut indicators = predicate_indicators(&arguments[0])?;
+            indicators.extend(predicate_indicators(&arguments[1])?);
+            Ok(indicators)
+        }
+        Term::Functor { name, arguments } if name == "[]" && arguments.is_empty() => Ok(vec![]),
+        indicator => predicate_indicator(indicator).map(|indicator| vec![indicator]),
+    }
+}
+
+// Heads like `maplist(1, ?)`: integers, `:`, `^` and `//` mark the arguments
+// that are qualified with the calling module.
+pub fn meta_predicate_heads(specification: &Term) -> Result<Vec<(String, Vec<bool>)>, Exception> {
+    match specification {
+        Term::Variable { .. } => Err(Exception::instantiation_error()),
+        Term::Functor { name, arguments }
+            if (name == "," || name == ".") && arguments.len() == 2 =>
+        {
+            let mut heads = meta_predicate_heads(&arguments[0])?;
+            heads.extend(meta_predicate_heads(&arguments[1])?);
+            Ok(heads)
+        }
+        Term::Functor { name, arguments } if name == "[]" && arguments.is_empty() => Ok(vec![]),
+        Term::Functor { name, arguments } => {
+            let meta_arguments = arguments
+                .iter()
+                .map(|argument| match argument {
+                    Term::Number { value } => Ok((0..=9).contains(value)),
+                    Term::Functor { name, arguments } if arguments.is_empty() => {
+                        match name.as_str() {
+                            ":" | "^" | "//" => Ok(true),
+                            "?" | "+" | "-" | "*" => Ok(false),
+                            _ => Err(Exception::domain_error(
+                                "meta_argument_specifier",
+                                argument.clone(),
+                            )),
+                        }
+                    }
+                    Term::Variable { .. } => Err(Exception::instantiation_error()),
+                    _ => Err(Exception::domain_error(
+                        "meta_argument_specifier",
+                        argument.clone(),
+                    )),
+                })
+                .collect::<Result<_, _>>()?;
+            Ok(vec![(name.clone(), meta_arguments)])
+        }
+        head => Err(Exception::type_error("callable", head.clone())),
+    }
+}
+
+pub fn predicate_indicator(indicator: &Term) -> Result<(String, usize), Exception> {
+    match indicator {
+        Term::Variable { .. } => Err(Exception::instantiation_error()),
+        // `Name//Arity` is a grammar rule, with two more arguments.
+        Term::Functor {
+            name: separator,
+            arguments,
+        } if (separator == "/" || separator == "//") && arguments.len() == 2 => {
+            let extra = if separator == "//" { 2 } else { 0 };
+            match (&arguments[0], &arguments[1]) {
+                (Term::Variable { .. }, _) | (_, Term::Variable { .. }) => {
+                    Err(Exception::instantiation_error())
+                }
+                (Term::Functor { name, arguments }, Term::Number { value })
+                    if arguments.is_empty() && *value >= 0 =>
+                {
+                    Ok((name.clone(), *value as usize + extra))
+                }
+                (Term::Functor { arguments, .. }, Term::Number { value })
+                    if arguments.is_empty() =>
+                {
+                    Err(Exception::domain_error(
+                        "not_less_than_zero",
+                        Term::number(*value),
+                    ))
+                }
+                (Term::Functor { arguments, .. }, arity) if arguments.is_empty() => {
+                    Err(Exception::type_error("integer", arity.clone()))
+                }
+                (name, _) => Err(Exception::type_error("atom", name.clone())),
+            }
+        }
+        indicator => Err(Exception::type_error(
+            "predicate_indicator",
+            indicator.clone(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    fn fact(name: &str, argument: &str) -> Rule {
+        Rule {
+            head: Term::functor(name, [Term::atom(argument)]),
+            body: vec![],
+        }
+    }
+
+    fn heads(clauses: &Clauses) -> Vec<Term> {
+        clauses
+            .iter()
+            .map(|clause| clause.rule.head.clone())
+            .collect()
+    }
+
+    #[test]
+    fn asserta_and_assertz_add_clauses_at_both_ends() {
+        let mut database = Database::empty();
+
+        database.assertz(USER, fact("f", "b")).unwrap();
+        database.asserta(USER, fact("f", "a")).unwrap();
+        database.assertz(USER, fact("f", "c")).unwrap();
+
+        assert_eq!(
+            heads(&database.clauses(USER, "f", 1)),
+            vec![
+                fact("f", "a").head,
+                fact("f", "b").head,
+                fact("f", "c").head
+            ]
+        );
+    }
+
+    #[test_case(Term::variable("X"), Exception::instantiation_error(); "variable")]
+    #[test_case(Term::number(1), Exception::type_error("callable", Term::number(1)); "number")]
+    fn clauses_need_a_callable_head(head: Term, expected: Exception) {
+        let mut database = Database::empty();
+
+        let result = database.assertz(USER, Rule { head, body: vec![] });
+
+        assert_eq!(result, Err(expected));
+    }
+
+    #[test]
+    fn clause_snapshot_is_not_affected_by_changes() {
+        let mut database = Database::empty().with_fact(fact("f", "a").head);
+        let snapshot = database.clauses(USER, "f", 1);
+
+        database.assertz(USER, fact("f", "b")).unwrap();
+        database.erase(USER, "f", 1, snapshot[0].id);
+
+        assert_eq!(heads(&snapshot), vec![fact("f", "a").head]);
+        assert_eq!(
+            heads(&database.clauses(USER, "f", 1)),
+            vec![fact("f", "b").head]
+        );
+    }
+
+    #[test]
+    fn unload_removes_clauses_from_a_file() {
+        let source = Rc::new(PathBuf::from("a.pl"));
+        let mut database = Database::empty().with_fact(fact("f", "a").head);
+        database
+            .add_from(USER, fact("f", "b"), Some(source.clone()))
+            .unwrap();
+        database
+            .add_from(USER, fact("g", "b"), Some(source.clone()))
+            .unwrap();
+        database.mark_loaded(&source, USER);
+
+        database.unload(&source);
+
+        assert_eq!(
+            heads(&database.clauses(USER, "f", 1)),
+            vec![fact("f", "a").head]
+        );
+        assert!(!database.is_defined(USER, "g", 1));
+        assert_eq!(database.loaded_module(&source), None);
+    }
+
+    #[test]
+    fn declared_predicates_are_dynamic() {
+        let mut database = Database::empty().with_fact(fact("f", "a").head);
+
+        database.declare_dynamic(USER, "g", 2);
+
+        assert!(!database.is_dynamic(USER, "f", 1));
+        assert!(database.is_dynamic(USER, "g", 2));
+        assert!(database.is_defined(USER, "g", 2));
+        assert!(database.clauses(USER, "g", 2).is_empty());
+    }
+
+    #[test]
+    fn lookup_through_imports_and_user() {
+        let mut database = Database::empty().with_fact(fact("f", "user").head);
+        database
+            .add_from("lists", fact("g", "lists"), None)
+            .unwrap();
+        database.define_module("lists", vec![(String::from("g"), 1)]);
+        database.import("app", "lists", (String::from("g"), 1));
+        database.add_from("app", fact("h", "app"), None).unwrap();
+
+        let lookup = |name| {
+            database
+                .lookup("app", name, 1)
+                .map(|(module, clauses)| (module, heads(&clauses)))
+        };
+
+        assert_eq!(
+            lookup("g"),
+            Some((String::from("lists"), vec![fact("g", "lists").head]))
+        );
+        assert_eq!(
+            lookup("f"),
+            Some((String::from(USER), vec![fact("f", "user").head]))
+        );
+        assert_eq!(
+            lookup("h"),
+            Some((String::from("app"), vec![fact("h", "app").head]))
+        );
+        assert_eq!(database.lookup(USER, "h", 1), None);
+    }
+
+    #[test]
+    fn parse_predicate_indicator_list() {
+        let specification = Term::functor(
+            ",",
+            [
+                Term::functor("/", [Term::atom("f"), Term::number(1)]),
+                Term::list([Term::functor("/", [Term::atom("g"), Term::number(0)])]),
+            ],
+        );
+
+        let indicators = predicate_indicators(&specification);
+
+        assert_eq!(
+            indicators,
+            Ok(vec![(String::from("f"), 1), (String::from("g"), 0)])
+        );
+    }
+
+    #[test]
+    fn grammar_rule_indicator() {
+        let indicator = Term::functor("//", [Term::atom("digits"), Term::number(1)]);
+
+        assert_eq!(
+            predicate_indicator(&indicator),
+            Ok((String::from("digits"), 3))
+        );
+    }
+
+    #[test]
+    fn invalid_predicate_indicator() {
+        let indicator = Term::functor("f", [Term::number(1)]);
+
+        assert_eq!(
+            predicate_indicator(&indicator),
+            Err(Exception::type_error("predicate_indicator", indicator))
+        );
+    }
+
+    #[test]
+    fn meta_predicate_specifications() {
+        let specification =
+            crate::parser::MinplParser::parse_query("meta_predicate((f(0, ?), g(:, -, ^))).")
+                .unwrap()
+                .arguments()[0]
+                .clone();
+
+        assert_eq!(
+            meta_predicate_heads(&specification),
+            Ok(vec![
+                ("f".to_string(), vec![true, false]),
+                ("g".to_string(), vec![true, false, true]),
+            ])
+        );
+        assert_eq!(
+            meta_predicate_heads(&Term::functor("f", [Term::atom("x")])),
+            Err(Exception::domain_error(
+                "meta_argument_specifier",
+                Term::atom("x")
+            ))
+        );
     }
 }
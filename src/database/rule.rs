@@ -2,6 +2,7 @@ use crate::term::Term;
 use std::fmt::Display;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Rule {
     pub head: Term,
     pub body: Vec<Term>,
@@ -22,3 +23,56 @@ impl Display for Rule {
         Ok(())
     }
 }
+
+impl Rule {
+    pub fn from_term(mut term: Term) -> Self {
+        match term {
+            Term::Functor {
+                ref name,
+                ref mut arguments,
+            } if name == ":-" && arguments.len() == 2 => {
+                let body = arguments.pop().unwrap();
+                let head = arguments.pop().unwrap();
+                Rule {
+                    head,
+                    body: conjunction(body),
+                }
+            }
+            head => Rule { head, body: vec![] },
+        }
+    }
+
+    // The clause as written: the head alone for a fact.
+    pub fn to_term(&self) -> Term {
+        match self.body.is_empty() {
+            true => self.head.clone(),
+            false => Term::functor(":-", [self.head.clone(), self.body_term()]),
+        }
+    }
+
+    pub fn body_term(&self) -> Term {
+        self.body
+            .iter()
+            .rev()
+            .cloned()
+            .reduce(|conjunction, goal| Term::functor(",", [goal, conjunction]))
+            .unwrap_or_else(|| Term::atom("true"))
+    }
+}
+
+fn conjunction(mut term: Term) -> Vec<Term> {
+    match term {
+        Term::Functor {
+            ref name,
+            ref mut arguments,
+        } if name == "," && arguments.len() == 2 => std::mem::take(arguments)
+            .into_iter()
+            .flat_map(conjunction)
+            .collect(),
+        Term::Functor {
+            ref name,
+            ref arguments,
+        } if name == "true" && arguments.is_empty() => vec![],
+        goal => vec![goal],
+    }
+}
@@ -0,0 +1,109 @@
+// Maps keyed by predicate indicators are written as lists of key and value
+// pairs, as JSON only has string keys.
+pub mod entries {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::{collections::HashMap, hash::Hash};
+
+    pub fn serialize<K, V, S>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        K: Serialize,
+        V: Serialize,
+        S: Serializer,
+    {
+        serializer.collect_seq(map)
+    }
+
+    pub fn deserialize<'de, K, V, D>(deserializer: D) -> Result<HashMap<K, V>, D::Error>
+    where
+        K: Deserialize<'de> + Eq + Hash,
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        Vec::<(K, V)>::deserialize(deserializer).map(|entries| entries.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        assignments::Assignments,
+        consult::consult_str,
+        database::{Database, Rule},
+        parser::MinplParser,
+        proof_search::prove,
+        term::Term,
+    };
+    use serde::{de::DeserializeOwned, Serialize};
+    use std::fmt::Debug;
+
+    fn round_trip<T: Serialize + DeserializeOwned + PartialEq + Debug>(value: &T) {
+        let json = serde_json::to_string(value).unwrap();
+        assert_eq!(&serde_json::from_str::<T>(&json).unwrap(), value);
+        let bytes = postcard::to_stdvec(value).unwrap();
+        assert_eq!(&postcard::from_bytes::<T>(&bytes).unwrap(), value);
+    }
+
+    fn database() -> Database {
+        let mut database = Database::empty();
+        consult_str(
+            &mut database,
+            ":- module(family, [ancestor/2]).
+            :- dynamic parent/2.
+            :- meta_predicate twice(0).
+            parent(tom, bob).
+            parent(bob, ann).
+            ancestor(X, Y) :- parent(X, Y).
+            ancestor(X, Z) :- parent(X, Y), ancestor(Y, Z).
+            twice(G) :- G, G.",
+        )
+        .unwrap();
+        database
+    }
+
+    #[test]
+    fn rules_and_assignments_round_trip() {
+        round_trip(&Rule {
+            head: MinplParser::parse_query("f(X).").unwrap(),
+            body: vec![MinplParser::parse_query("g(X, \"s\", 1).").unwrap()],
+        });
+        round_trip(
+            &Assignments::empty()
+                .with("X", Term::atom("a"))
+                .with("Y", Term::variable("Z")),
+        );
+    }
+
+    #[test]
+    fn assignments_are_a_map() {
+        let assignments = Assignments::empty().with("X", Term::number(1));
+
+        assert_eq!(
+            serde_json::to_string(&assignments).unwrap(),
+            "{\"X\":{\"n\":1}}"
+        );
+    }
+
+    #[test]
+    fn database_round_trips() {
+        let database = database();
+
+        round_trip(&database);
+    }
+
+    #[test]
+    fn deserialized_database_answers_queries() {
+        let json = serde_json::to_string(&database()).unwrap();
+        let mut database = serde_json::from_str::<Database>(&json).unwrap();
+
+        assert_eq!(
+            prove(
+                &mut database,
+                MinplParser::parse_query("family:ancestor(tom, X).").unwrap()
+            ),
+            Ok(vec![
+                Assignments::empty().with("X", Term::atom("bob")),
+                Assignments::empty().with("X", Term::atom("ann")),
+            ])
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use super::{goals, is_bound, Key};
+use crate::{database::Rule, term::Term};
+use std::collections::HashSet;
+
+// The magic-sets rewriting of Datalog `rules` for `goal`, and the goal to
+// evaluate against it in place of `goal`.
+//
+// Each predicate defined by a rule with a body is specialized by how it is
+// called, with an adornment of `b` for each bound argument and `f` for each
+// free one: `ancestor(john, X)` calls `ancestor_bf`. The rules of an adorned
+// predicate only apply to the bound arguments in its magic predicate,
+// `magic_ancestor_bf`, which holds those of the goal and those passed from
+// left to right through the bodies calling it. Predicates defined by facts
+// alone are left as they are.
+pub fn rewrite(rules: &[Rule], goal: &Term) -> (Vec<Rule>, Term) {
+    let derived = rules
+        .iter()
+        .filter(|rule| !rule.body.is_empty())
+        .map(|rule| key(&rule.head))
+        .collect::<HashSet<_>>();
+    if !derived.contains(&key(goal)) {
+        return (rules.to_vec(), goal.clone());
+    }
+    let mut rewritten = rules
+        .iter()
+        .filter(|rule| !derived.contains(&key(&rule.head)))
+        .cloned()
+        .collect::<Vec<_>>();
+    let adornment = adornment_of(goal.arguments(), &HashSet::new());
+    rewritten.push(Rule {
+        head: magic(goal, &adornment),
+        body: vec![],
+    });
+    let mut calls = vec![(key(goal), adornment.clone())];
+    let mut index = 0;
+    while index < calls.len() {
+        let (called, adornment) = calls[index].clone();
+        index += 1;
+        for rule in rules.iter().filter(|rule| key(&rule.head) == called) {
+            let guard = magic(&rule.head, &adornment);
+            let mut bound = rule
+                .head
+                .arguments()
+                .iter()
+                .zip(adornment.chars())
+                .filter(|(_, binding)| *binding == 'b')
+                .flat_map(|(argument, _)| argument.variables())
+                .collect::<HashSet<_>>();
+            let mut body = vec![guard.clone()];
+            // The goals that can be passed to a magic rule: those before
+            // the call, leaving out tests whose variables are not bound yet.
+            let mut passed = vec![guard];
+            for goal in goals(&rule.body) {
+                if derived.contains(&key(goal)) {
+                    let call_adornment = adornment_of(goal.arguments(), &bound);
+                    let head = magic(goal, &call_adornment);
+                    if !passed.contains(&head) {
+                        rewritten.push(Rule {
+                            head,
+                            body: passed.clone(),
+                        });
+                    }
+                    let call = (key(goal), call_adornment.clone());
+                    if !calls.contains(&call) {
+                        calls.push(call);
+                    }
+                    let goal = adorned(goal, &call_adornment);
+                    bound.extend(goal.variables());
+                    body.push(goal.clone());
+                    passed.push(goal);
+                    continue;
+                }
+                body.push(goal.clone());
+                let binds = match goal.name() {
+                    Some("=") if goal.arity() == 2 => {
+                        goal.arguments().iter().any(|side| is_bound(side, &bound))
+                    }
+                    Some("==" | "\\=" | "\\==") if goal.arity() == 2 => false,
+                    _ => true,
+                };
+                if binds {
+                    bound.extend(goal.variables());
+                }
+                if goal
+                    .variables()
+                    .iter()
+                    .all(|variable| bound.contains(variable))
+                {
+                    passed.push(goal.clone());
+                }
+            }
+            rewritten.push(Rule {
+                head: adorned(&rule.head, &adornment),
+                body,
+            });
+        }
+    }
+    (rewritten, adorned(goal, &adornment))
+}
+
+fn key(literal: &Term) -> Key {
+    (
+        literal.name().unwrap_or_default().to_string(),
+        literal.arity(),
+    )
+}
+
+fn adornment_of(arguments: &[Term], bound: &HashSet<String>) -> String {
+    arguments
+        .iter()
+        .map(|argument| match is_bound(argument, bound) {
+            true => 'b',
+            false => 'f',
+        })
+        .collect()
+}
+
+// `name_Adornment`, or just `name` for an atom.
+fn adorned_name(literal: &Term, adornment: &str) -> String {
+    let name = literal.name().unwrap_or_default();
+    match adornment.is_empty() {
+        true => name.to_string(),
+        false => format!("{}_{}", name, adornment),
+    }
+}
+
+fn adorned(literal: &Term, adornment: &str) -> Term {
+    Term::functor(
+        adorned_name(literal, adornment),
+        literal.arguments().iter().cloned(),
+    )
+}
+
+// The magic predicate of an adorned call, with its bound arguments.
+fn magic(literal: &Term, adornment: &str) -> Term {
+    let bound = literal
+        .arguments()
+        .iter()
+        .zip(adornment.chars())
+        .filter(|(_, binding)| *binding == 'b')
+        .map(|(argument, _)| argument.clone());
+    Term::functor(format!("magic_{}", adorned_name(literal, adornment)), bound)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        datalog::Program,
+        parser::{MinplParser, Sentence},
+        unification::{unify, UnifyResult},
+    };
+    use test_case::test_case;
+
+    const FAMILY: &str = "parent(john, mary). parent(mary, sue). parent(bob, tim).
+        ancestor(X, Y) :- parent(X, Y).
+        ancestor(X, Y) :- parent(X, Z), ancestor(Z, Y).";
+
+    fn term(code: &str) -> Term {
+        MinplParser::parse_query(&format!("{}.", code)).unwrap()
+    }
+
+    fn rules(code: &str) -> Vec<Rule> {
+        MinplParser::parse_program(code)
+            .unwrap()
+            .into_iter()
+            .filter_map(|sentence| match sentence {
+                Sentence::Clause { clause, .. } => Some(Rule::from_term(clause)),
+                Sentence::Directive { .. } => None,
+            })
+            .collect()
+    }
+
+    // The facts of the goal's predicate that are instances of it, sorted.
+    fn instances(facts: Vec<Term>, goal: &Term) -> Vec<Term> {
+        let mut instances = facts
+            .into_iter()
+            .map(|fact| Term::functor(goal.name().unwrap(), fact.arguments().iter().cloned()))
+            .filter(|fact| unify(fact.clone(), goal.clone()) != UnifyResult::False)
+            .collect::<Vec<_>>();
+        instances.sort();
+        instances
+    }
+
+    #[test]
+    fn bound_arguments_become_magic_facts() {
+        let (rules, query) = rewrite(&rules(FAMILY), &term("ancestor(john, Y)"));
+
+        assert_eq!(query, term("ancestor_bf(john, Y)"));
+        assert_eq!(
+            Program::new(rules).unwrap().to_string(),
+            "parent(john, mary).
+parent(mary, sue).
+parent(bob, tim).
+magic_ancestor_bf(john).
+ancestor_bf(X, Y) :- magic_ancestor_bf(X), parent(X, Y).
+magic_ancestor_bf(Z) :- magic_ancestor_bf(X), parent(X, Z).
+ancestor_bf(X, Y) :- magic_ancestor_bf(X), parent(X, Z), ancestor_bf(Z, Y).
+"
+        );
+    }
+
+    #[test]
+    fn only_relevant_facts_are_derived() {
+        let (rules, _) = rewrite(&rules(FAMILY), &term("ancestor(john, Y)"));
+
+        let model = Program::new(rules).unwrap().evaluate();
+
+        assert_eq!(
+            model.facts("ancestor_bf", 2),
+            vec![
+                term("ancestor_bf(john, mary)"),
+                term("ancestor_bf(mary, sue)"),
+                term("ancestor_bf(john, sue)"),
+            ]
+        );
+        assert_eq!(
+            model.facts("magic_ancestor_bf", 1),
+            vec![
+                term("magic_ancestor_bf(john)"),
+                term("magic_ancestor_bf(mary)"),
+                term("magic_ancestor_bf(sue)"),
+            ]
+        );
+    }
+
+    #[test_case("ancestor(X, sue)"; "second argument bound")]
+    #[test_case("ancestor(X, Y)"; "all free")]
+    #[test_case("related(mary, Y)"; "calls with other adornments")]
+    #[test_case("parent(X, Y)"; "facts only")]
+    fn same_answers_as_the_full_model(goal: &str) {
+        let code = format!(
+            "{} related(X, Y) :- ancestor(X, Y). related(X, Y) :- ancestor(Y, X), X \\= Y.",
+            FAMILY
+        );
+        let goal = term(goal);
+        let full = Program::new(rules(&code)).unwrap().evaluate();
+        let (rules, query) = rewrite(&rules(&code), &goal);
+        let magic = Program::new(rules).unwrap().evaluate();
+
+        let expected = instances(full.facts(goal.name().unwrap(), goal.arity()), &goal);
+        let found = instances(magic.facts(query.name().unwrap(), query.arity()), &goal);
+        assert_eq!(found, expected);
+    }
+}
@@ -0,0 +1,591 @@
+pub mod magic;
+mod relation;
+
+pub use relation::Relation;
+
+use crate::{
+    database::{Database, Rule},
+    exception::Exception,
+    proof_search::is_builtin,
+    term::Term,
+};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    ops::Range,
+};
+
+type Key = (String, usize);
+
+// The head of the rule `solve` adds for its goal.
+const QUERY: &str = "$query";
+
+// Rules checked to be Datalog: their arguments are atoms, numbers, strings
+// or variables, and every variable of a head or a test is bound by a goal of
+// the body. Their least model is then a finite set of ground facts.
+#[derive(Debug, Clone)]
+pub struct Program {
+    rules: Vec<Rule>,
+    plans: Vec<Plan>,
+}
+
+// A rule with its body in the order it is evaluated.
+#[derive(Debug, Clone)]
+struct Plan {
+    head: Key,
+    arguments: Vec<Term>,
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone)]
+enum Step {
+    // Looks up the tuples of a relation by the columns bound before it.
+    Join {
+        key: Key,
+        arguments: Vec<Term>,
+        columns: Vec<usize>,
+    },
+    Unify(Term, Term),
+    Compare {
+        left: Term,
+        right: Term,
+        equal: bool,
+    },
+}
+
+impl Plan {
+    fn joins(&self) -> usize {
+        self.steps
+            .iter()
+            .filter(|step| matches!(step, Step::Join { .. }))
+            .count()
+    }
+}
+
+impl Program {
+    pub fn new<I: IntoIterator<Item = Rule>>(rules: I) -> Result<Self, Exception> {
+        let rules = rules.into_iter().collect::<Vec<_>>();
+        let plans = rules.iter().map(plan).collect::<Result<_, _>>()?;
+        Ok(Self { rules, plans })
+    }
+
+    // `rules` and the clauses of every predicate they depend on, looked up
+    // from `module` like calls are.
+    pub fn from_database(
+        database: &Database,
+        module: &str,
+        rules: Vec<Rule>,
+    ) -> Result<Self, Exception> {
+        let mut rules = rules
+            .into_iter()
+            .map(|rule| (module.to_string(), rule))
+            .collect::<Vec<_>>();
+        let mut seen = HashSet::new();
+        let mut index = 0;
+        while index < rules.len() {
+            let (module, rule) = rules[index].clone();
+            index += 1;
+            for goal in goals(&rule.body) {
+                let (name, arity) = match goal {
+                    Term::Functor { name, arguments } => (name.as_str(), arguments.len()),
+                    _ => continue,
+                };
+                if is_builtin(name, arity) || !seen.insert((name.to_string(), arity)) {
+                    continue;
+                }
+                if database.foreign(name, arity).is_some() {
+                    return Err(datalog_error("unsupported_goal", goal, &rule));
+                }
+                if let Some((definition, clauses)) = database.lookup(&module, name, arity) {
+                    rules.extend(
+                        clauses
+                            .iter()
+                            .map(|clause| (definition.clone(), clause.rule.as_ref().clone())),
+                    );
+                }
+            }
+        }
+        Self::new(rules.into_iter().map(|(_, rule)| rule))
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    // The least model, by semi-naive iteration: the first round applies the
+    // rules without joins, facts among them, and each later round only the
+    // derivations using a tuple new in the round before.
+    pub fn evaluate(&self) -> Model {
+        let mut relations: HashMap<Key, Relation> = HashMap::new();
+        for plan in &self.plans {
+            relations.entry(plan.head.clone()).or_default();
+            for step in &plan.steps {
+                if let Step::Join { key, columns, .. } = step {
+                    relations.entry(key.clone()).or_default().index(columns);
+                }
+            }
+        }
+        let mut deltas = relations
+            .keys()
+            .map(|key| (key.clone(), 0..0))
+            .collect::<HashMap<_, _>>();
+        let mut first = true;
+        loop {
+            let mut derived = vec![];
+            for plan in &self.plans {
+                let joins = plan.joins();
+                if joins == 0 && first {
+                    let round = Round {
+                        relations: &relations,
+                        deltas: &deltas,
+                        delta: 0,
+                    };
+                    round.derive(plan, 0, 0, &mut vec![], &mut derived);
+                }
+                for delta in 0..joins {
+                    let round = Round {
+                        relations: &relations,
+                        deltas: &deltas,
+                        delta,
+                    };
+                    round.derive(plan, 0, 0, &mut vec![], &mut derived);
+                }
+            }
+            let mut changed = false;
+            for (key, tuple) in derived {
+                changed |= relations.get_mut(&key).unwrap().insert(tuple);
+            }
+            if !changed {
+                return Model { relations };
+            }
+            for (key, relation) in &relations {
+                let delta = deltas.get_mut(key).unwrap();
+                *delta = delta.end..relation.len();
+            }
+            first = false;
+        }
+    }
+}
+
+// One clause per line, as they would be consulted.
+impl Display for Program {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for rule in &self.rules {
+            writeln!(f, "{}.", rule)?;
+        }
+        Ok(())
+    }
+}
+
+// The tuples each relation got in the last round.
+struct Round<'a> {
+    relations: &'a HashMap<Key, Relation>,
+    deltas: &'a HashMap<Key, Range<usize>>,
+    // The join that reads the new tuples. The joins before it read the
+    // older ones and those after it all of them, so that each derivation
+    // is made in one round only.
+    delta: usize,
+}
+
+impl Round<'_> {
+    fn derive(
+        &self,
+        plan: &Plan,
+        step: usize,
+        join: usize,
+        bindings: &mut Vec<(String, Term)>,
+        derived: &mut Vec<(Key, Vec<Term>)>,
+    ) {
+        let mark = bindings.len();
+        match plan.steps.get(step) {
+            None => {
+                let tuple = plan
+                    .arguments
+                    .iter()
+                    .map(|argument| value(argument, bindings).unwrap().clone())
+                    .collect();
+                derived.push((plan.head.clone(), tuple));
+            }
+            Some(Step::Join {
+                key,
+                arguments,
+                columns,
+            }) => {
+                let delta = &self.deltas[key];
+                let rows = match join.cmp(&self.delta) {
+                    Ordering::Less => 0..delta.start,
+                    Ordering::Equal => delta.clone(),
+                    Ordering::Greater => 0..delta.end,
+                };
+                let values = columns
+                    .iter()
+                    .map(|column| value(&arguments[*column], bindings).unwrap().clone())
+                    .collect::<Vec<_>>();
+                for tuple in self.relations[key].select(columns, &values, rows) {
+                    if bind_all(arguments, tuple, bindings) {
+                        self.derive(plan, step + 1, join + 1, bindings, derived);
+                    }
+                    bindings.truncate(mark);
+                }
+            }
+            Some(Step::Unify(left, right)) => {
+                let unified = match (value(left, bindings), value(right, bindings)) {
+                    (Some(left), Some(right)) => left == right,
+                    (Some(value), None) => {
+                        let value = value.clone();
+                        bind_all(std::slice::from_ref(right), &[value], bindings)
+                    }
+                    (None, Some(value)) => {
+                        let value = value.clone();
+                        bind_all(std::slice::from_ref(left), &[value], bindings)
+                    }
+                    (None, None) => unreachable!("planned once a side is bound"),
+                };
+                if unified {
+                    self.derive(plan, step + 1, join, bindings, derived);
+                }
+                bindings.truncate(mark);
+            }
+            Some(Step::Compare { left, right, equal }) => {
+                if (value(left, bindings) == value(right, bindings)) == *equal {
+                    self.derive(plan, step + 1, join, bindings, derived);
+                }
+            }
+        }
+    }
+}
+
+fn value<'a>(term: &'a Term, bindings: &'a [(String, Term)]) -> Option<&'a Term> {
+    match term {
+        Term::Variable { name } => bindings
+            .iter()
+            .find(|(variable, _)| variable == name)
+            .map(|(_, value)| value),
+        constant => Some(constant),
+    }
+}
+
+// Binds the variables of `arguments` to the values in `tuple`, failing if a
+// constant or an already bound variable does not match.
+fn bind_all(arguments: &[Term], tuple: &[Term], bindings: &mut Vec<(String, Term)>) -> bool {
+    arguments
+        .iter()
+        .zip(tuple)
+        .all(|(argument, tuple_value)| match argument {
+            Term::Variable { name } => match value(argument, bindings) {
+                Some(bound) => bound == tuple_value,
+                None => {
+                    bindings.push((name.clone(), tuple_value.clone()));
+                    true
+                }
+            },
+            constant => constant == tuple_value,
+        })
+}
+
+// The facts that hold in a program, by predicate.
+#[derive(Debug, Clone, Default)]
+pub struct Model {
+    relations: HashMap<Key, Relation>,
+}
+
+impl Model {
+    pub fn relation(&self, name: &str, arity: usize) -> Option<&Relation> {
+        self.relations.get(&(name.to_string(), arity))
+    }
+
+    // The facts of `name/arity`, in the order they were derived.
+    pub fn facts(&self, name: &str, arity: usize) -> Vec<Term> {
+        self.relation(name, arity).map_or(vec![], |relation| {
+            relation
+                .tuples()
+                .iter()
+                .map(|tuple| Term::functor(name, tuple.iter().cloned()))
+                .collect()
+        })
+    }
+}
+
+// The instances of `goal`, a conjunction of Datalog goals, that hold in the
+// least model of the predicates it depends on. Only the facts relevant to
+// the goal are derived, by evaluating the program given by `program_for`.
+pub fn solve(database: &Database, module: &str, goal: &Term) -> Result<Vec<Term>, Exception> {
+    let variables = goal.variables();
+    let (program, query) = program_for(database, module, goal)?;
+    let model = program.evaluate();
+    let instances = model
+        .relation(query.name().unwrap(), variables.len())
+        .map_or(vec![], |relation| {
+            relation
+                .tuples()
+                .iter()
+                .map(|tuple| {
+                    variables
+                        .iter()
+                        .zip(tuple)
+                        .fold(goal.clone(), |goal, (variable, value)| {
+                            goal.substitute(variable, value)
+                        })
+                })
+                .collect()
+        });
+    Ok(instances)
+}
+
+// The magic-sets rewriting for `goal` of the predicates it depends on, with
+// a rule for the goal itself, and the query whose facts are its answers:
+// an instance of the head of that rule for each answer, binding the
+// variables of `goal` in order.
+pub fn program_for(
+    database: &Database,
+    module: &str,
+    goal: &Term,
+) -> Result<(Program, Term), Exception> {
+    let query = Term::functor(QUERY, goal.variables().into_iter().map(Term::variable));
+    let rule = Rule {
+        head: query.clone(),
+        body: vec![goal.clone()],
+    };
+    let program = Program::from_database(database, module, vec![rule])?;
+    let (rules, query) = magic::rewrite(program.rules(), &query);
+    Ok((Program::new(rules)?, query))
+}
+
+// Orders the body for evaluation: goals as written, each test as soon as it
+// can run. Rejects the rule if it is not Datalog.
+fn plan(rule: &Rule) -> Result<Plan, Exception> {
+    let (name, arguments) = match &rule.head {
+        Term::Functor { name, arguments } => (name, arguments),
+        Term::Variable { .. } => return Err(Exception::instantiation_error()),
+        head => return Err(Exception::type_error("callable", head.clone())),
+    };
+    let mut joins = vec![];
+    let mut tests = vec![];
+    for goal in goals(&rule.body) {
+        match (goal.name(), goal.arity()) {
+            (Some("true"), 0) => continue,
+            (Some("=" | "==" | "\\=" | "\\=="), 2) => tests.push(goal),
+            (Some(":"), 2) | (None, _) => {
+                return Err(datalog_error("unsupported_goal", goal, rule))
+            }
+            (Some(name), arity) if is_builtin(name, arity) => {
+                return Err(datalog_error("unsupported_goal", goal, rule))
+            }
+            _ => joins.push(goal),
+        }
+    }
+    let all_arguments = arguments
+        .iter()
+        .chain(joins.iter().chain(&tests).flat_map(|goal| goal.arguments()));
+    for argument in all_arguments {
+        if argument.arity() > 0 {
+            return Err(datalog_error("compound_argument", argument, rule));
+        }
+    }
+    let mut bound = HashSet::new();
+    let mut steps = vec![];
+    place_tests(&mut tests, &mut bound, &mut steps);
+    for goal in joins {
+        let arguments = goal.arguments().to_vec();
+        let columns = (0..arguments.len())
+            .filter(|column| is_bound(&arguments[*column], &bound))
+            .collect();
+        bound.extend(goal.variables());
+        steps.push(Step::Join {
+            key: (goal.name().unwrap().to_string(), arguments.len()),
+            arguments,
+            columns,
+        });
+        place_tests(&mut tests, &mut bound, &mut steps);
+    }
+    let unbound = tests
+        .iter()
+        .flat_map(|test| test.variables())
+        .chain(rule.head.variables())
+        .find(|variable| !bound.contains(variable));
+    if let Some(variable) = unbound {
+        return Err(datalog_error(
+            "unsafe_variable",
+            &Term::variable(variable),
+            rule,
+        ));
+    }
+    Ok(Plan {
+        head: (name.clone(), arguments.len()),
+        arguments: arguments.clone(),
+        steps,
+    })
+}
+
+// Adds the tests that can run with the variables bound so far: comparisons
+// once both sides are bound and unifications once one side is.
+fn place_tests(tests: &mut Vec<&Term>, bound: &mut HashSet<String>, steps: &mut Vec<Step>) {
+    let ready = |test: &Term, bound: &HashSet<String>| {
+        let sides = test.arguments();
+        match test.name() {
+            Some("=") => sides.iter().any(|side| is_bound(side, bound)),
+            _ => sides.iter().all(|side| is_bound(side, bound)),
+        }
+    };
+    while let Some(position) = tests.iter().position(|test| ready(test, bound)) {
+        let test = tests.remove(position);
+        let (left, right) = (test.arguments()[0].clone(), test.arguments()[1].clone());
+        bound.extend(test.variables());
+        steps.push(match test.name() {
+            Some("=") => Step::Unify(left, right),
+            name => Step::Compare {
+                left,
+                right,
+                equal: name == Some("=="),
+            },
+        });
+    }
+}
+
+fn is_bound(argument: &Term, bound: &HashSet<String>) -> bool {
+    match argument {
+        Term::Variable { name } => bound.contains(name),
+        _ => true,
+    }
+}
+
+fn goals(body: &[Term]) -> Vec<&Term> {
+    body.iter().flat_map(conjuncts).collect()
+}
+
+fn conjuncts(goal: &Term) -> Vec<&Term> {
+    match goal {
+        Term::Functor { name, arguments } if name == "," && arguments.len() == 2 => {
+            arguments.iter().flat_map(conjuncts).collect()
+        }
+        goal => vec![goal],
+    }
+}
+
+// `datalog_error(Reason, Clause)`, where `Reason` is
+// `unsafe_variable(Variable)` for a variable not bound by any goal of the
+// body, `compound_argument(Argument)` or `unsupported_goal(Goal)`.
+fn datalog_error(reason: &str, culprit: &Term, rule: &Rule) -> Exception {
+    Exception::error(Term::functor(
+        "datalog_error",
+        [Term::functor(reason, [culprit.clone()]), rule.to_term()],
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{consult::consult_str, database::USER, parser::MinplParser};
+    use test_case::test_case;
+
+    fn term(code: &str) -> Term {
+        MinplParser::parse_query(&format!("{}.", code)).unwrap()
+    }
+
+    fn database(code: &str) -> Database {
+        let mut database = Database::empty();
+        consult_str(&mut database, code).unwrap();
+        database
+    }
+
+    fn solutions(code: &str, goal: &str) -> Result<Vec<Term>, Exception> {
+        solve(&database(code), USER, &term(goal))
+    }
+
+    #[test]
+    fn left_recursion_terminates() {
+        let code = "edge(a, b). edge(b, c). edge(c, d). edge(d, b).
+            path(X, Y) :- path(X, Z), edge(Z, Y).
+            path(X, Y) :- edge(X, Y).";
+
+        let mut found = solutions(code, "path(a, X)").unwrap();
+        found.sort();
+
+        assert_eq!(
+            found,
+            vec![term("path(a, b)"), term("path(a, c)"), term("path(a, d)")]
+        );
+    }
+
+    #[test]
+    fn each_fact_is_derived_once() {
+        let mut program = vec![Rule::from_term(term("path(X, Y) :- edge(X, Y)"))];
+        program.push(Rule::from_term(term(
+            "path(X, Y) :- path(X, Z), path(Z, Y)",
+        )));
+        for node in 0..60 {
+            let edge = Term::functor("edge", [Term::number(node), Term::number(node + 1)]);
+            program.push(Rule::from_term(edge));
+        }
+
+        let model = Program::new(program).unwrap().evaluate();
+
+        assert_eq!(model.relation("path", 2).unwrap().len(), 60 * 61 / 2);
+        assert!(model
+            .relation("path", 2)
+            .unwrap()
+            .contains(&[Term::number(0), Term::number(60)]));
+    }
+
+    #[test]
+    fn mutual_recursion() {
+        let code = "next(0, 1). next(1, 2). next(2, 3). next(3, 4).
+            even(0).
+            even(Y) :- odd(X), next(X, Y).
+            odd(Y) :- even(X), next(X, Y).";
+
+        let database = database(code);
+        let program =
+            Program::from_database(&database, USER, vec![Rule::from_term(term("q :- even(_)"))])
+                .unwrap();
+        let model = program.evaluate();
+
+        assert_eq!(
+            model.facts("even", 1),
+            vec![term("even(0)"), term("even(2)"), term("even(4)")]
+        );
+        assert_eq!(model.facts("odd", 1), vec![term("odd(1)"), term("odd(3)")]);
+    }
+
+    #[test_case("sibling(X, Y)" => vec![term("sibling(bob, liz)"), term("sibling(liz, bob)")]; "disequality")]
+    #[test_case("same(X)" => vec![term("same(tom)")]; "unification binds")]
+    #[test_case("parent(tom, X), X == liz" => vec![term("(parent(tom, liz), liz == liz)")]; "conjunction")]
+    #[test_case("parent(ann, X)" => Vec::<Term>::new(); "no solutions")]
+    fn tests_and_queries(goal: &str) -> Vec<Term> {
+        let code = "parent(tom, bob). parent(tom, liz).
+            sibling(X, Y) :- parent(P, X), parent(P, Y), X \\= Y.
+            same(X) :- X = Y, parent(Y, bob).";
+
+        solutions(code, goal).unwrap()
+    }
+
+    #[test_case("p(X) :- q(Y)." => term("datalog_error(unsafe_variable(X), (p(X) :- q(Y)))"); "head variable")]
+    #[test_case("p(X)." => term("datalog_error(unsafe_variable(X), p(X))"); "fact with a variable")]
+    #[test_case("p(X) :- q(X), X \\= Y." => term("datalog_error(unsafe_variable(Y), (p(X) :- q(X), X \\= Y))"); "test variable")]
+    #[test_case("p(X) :- q(f(X))." => term("datalog_error(compound_argument(f(X)), (p(X) :- q(f(X))))"); "compound argument")]
+    #[test_case("p(X) :- q(X), \\+ r(X)." => term("datalog_error(unsupported_goal(\\+ r(X)), (p(X) :- q(X), \\+ r(X)))"); "negation")]
+    fn rules_that_are_not_datalog(code: &str) -> Term {
+        let code = format!("q(a). r(b). {}", code);
+
+        let error = solutions(&code, "p(_)").unwrap_err();
+        error.ball().arguments()[0].clone()
+    }
+
+    #[test]
+    fn foreign_predicates_are_not_datalog() {
+        let mut database = database("p(X) :- q(X).");
+        database.register_foreign(
+            "q",
+            1,
+            crate::database::deterministic(|_: &[Term]| Ok(None)),
+        );
+
+        let error = solve(&database, USER, &term("p(_)")).unwrap_err();
+
+        assert_eq!(
+            error.ball().arguments()[0],
+            term("datalog_error(unsupported_goal(q(X)), (p(X) :- q(X)))")
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use crate::term::Term;
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+
+// The tuples of a predicate in the order they were derived, with hash
+// indexes on the sets of columns that joins look up. Tuples are only ever
+// appended, so a range of rows is the set of tuples derived in some rounds.
+#[derive(Debug, Clone, Default)]
+pub struct Relation {
+    tuples: Vec<Vec<Term>>,
+    known: HashSet<Vec<Term>>,
+    indexes: HashMap<Vec<usize>, HashMap<Vec<Term>, Vec<usize>>>,
+}
+
+impl Relation {
+    pub fn len(&self) -> usize {
+        self.tuples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tuples.is_empty()
+    }
+
+    pub fn tuples(&self) -> &[Vec<Term>] {
+        &self.tuples
+    }
+
+    pub fn contains(&self, tuple: &[Term]) -> bool {
+        self.known.contains(tuple)
+    }
+
+    // Adds an index on `columns`, kept up to date by `insert`.
+    pub fn index(&mut self, columns: &[usize]) {
+        if columns.is_empty() || self.indexes.contains_key(columns) {
+            return;
+        }
+        let mut index: HashMap<Vec<Term>, Vec<usize>> = HashMap::new();
+        for (row, tuple) in self.tuples.iter().enumerate() {
+            index.entry(key(tuple, columns)).or_default().push(row);
+        }
+        self.indexes.insert(columns.to_vec(), index);
+    }
+
+    // Returns whether the tuple is new.
+    pub fn insert(&mut self, tuple: Vec<Term>) -> bool {
+        if self.known.contains(&tuple) {
+            return false;
+        }
+        let row = self.tuples.len();
+        for (columns, index) in &mut self.indexes {
+            index.entry(key(&tuple, columns)).or_default().push(row);
+        }
+        self.known.insert(tuple.clone());
+        self.tuples.push(tuple);
+        true
+    }
+
+    // The tuples among `rows` whose `columns` hold `values`, found through an
+    // index when there is one on those columns.
+    pub fn select(&self, columns: &[usize], values: &[Term], rows: Range<usize>) -> Vec<&[Term]> {
+        match self.indexes.get(columns) {
+            Some(index) => {
+                let matching = index.get(values).map_or(&[][..], Vec::as_slice);
+                let start = matching.partition_point(|row| *row < rows.start);
+                let end = matching.partition_point(|row| *row < rows.end);
+                matching[start..end]
+                    .iter()
+                    .map(|row| self.tuples[*row].as_slice())
+                    .collect()
+            }
+            None => self.tuples[rows]
+                .iter()
+                .filter(|tuple| columns.iter().zip(values).all(|(c, v)| &tuple[*c] == v))
+                .map(Vec::as_slice)
+                .collect(),
+        }
+    }
+}
+
+fn key(tuple: &[Term], columns: &[usize]) -> Vec<Term> {
+    columns
+        .iter()
+        .map(|column| tuple[*column].clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tuple(values: &[&str]) -> Vec<Term> {
+        values.iter().map(Term::atom).collect()
+    }
+
+    fn relation() -> Relation {
+        let mut relation = Relation::default();
+        relation.insert(tuple(&["a", "b"]));
+        relation.index(&[0]);
+        relation.insert(tuple(&["b", "c"]));
+        relation.insert(tuple(&["a", "c"]));
+        relation
+    }
+
+    #[test]
+    fn insert_keeps_tuples_unique() {
+        let mut relation = relation();
+
+        assert!(!relation.insert(tuple(&["a", "b"])));
+        assert_eq!(relation.len(), 3);
+        assert!(relation.contains(&tuple(&["b", "c"])));
+    }
+
+    #[test]
+    fn select_with_and_without_an_index() {
+        let relation = relation();
+        let a = [Term::atom("a")];
+        let c = [Term::atom("c")];
+
+        assert_eq!(
+            relation.select(&[0], &a, 0..3),
+            vec![&tuple(&["a", "b"])[..], &tuple(&["a", "c"])[..]]
+        );
+        assert_eq!(
+            relation.select(&[0], &a, 1..3),
+            vec![&tuple(&["a", "c"])[..]]
+        );
+        assert_eq!(
+            relation.select(&[1], &c, 0..2),
+            vec![&tuple(&["b", "c"])[..]]
+        );
+        assert_eq!(relation.select(&[], &[], 2..3).len(), 1);
+    }
+}
@@ -0,0 +1,84 @@
+use crate::term::Term;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exception {
+    ball: Term,
+}
+
+impl Exception {
+    pub fn new(ball: Term) -> Self {
+        Self { ball }
+    }
+
+    pub fn error(formal: Term) -> Self {
+        Self::new(Term::functor("error", [formal, Term::variable("_")]))
+    }
+
+    pub fn instantiation_error() -> Self {
+        Self::error(Term::atom("instantiation_error"))
+    }
+
+    pub fn uninstantiation_error(culprit: Term) -> Self {
+        Self::error(Term::functor("uninstantiation_error", [culprit]))
+    }
+
+    pub fn type_error<S: ToString>(kind: S, culprit: Term) -> Self {
+        Self::error(Term::functor("type_error", [Term::atom(kind), culprit]))
+    }
+
+    pub fn domain_error<S: ToString>(domain: S, culprit: Term) -> Self {
+        Self::error(Term::functor("domain_error", [Term::atom(domain), culprit]))
+    }
+
+    pub fn permission_error<S: ToString, T: ToString>(action: S, kind: T, culprit: Term) -> Self {
+        Self::error(Term::functor(
+            "permission_error",
+            [Term::atom(action), Term::atom(kind), culprit],
+        ))
+    }
+
+    pub fn existence_error<S: ToString>(kind: S, culprit: Term) -> Self {
+        Self::error(Term::functor(
+            "existence_error",
+            [Term::atom(kind), culprit],
+        ))
+    }
+
+    pub fn representation_error<S: ToString>(flag: S) -> Self {
+        Self::error(Term::functor("representation_error", [Term::atom(flag)]))
+    }
+
+    pub fn syntax_error<S: ToString>(message: S) -> Self {
+        Self::error(Term::functor("syntax_error", [Term::atom(message)]))
+    }
+
+    pub fn evaluation_error<S: ToString>(error: S) -> Self {
+        Self::error(Term::functor("evaluation_error", [Term::atom(error)]))
+    }
+
+    pub fn ball(&self) -> &Term {
+        &self.ball
+    }
+}
+
+impl Display for Exception {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.ball {
+            Term::Functor { name, arguments } if name == "error" && arguments.len() == 2 => {
+                match (arguments[0].name(), arguments[0].arguments()) {
+                    // Parser messages span several lines, so they are not quoted.
+                    (Some("syntax_error"), [Term::Functor { name, arguments }])
+                        if arguments.is_empty() =>
+                    {
+                        write!(f, "syntax error: {}", name)
+                    }
+                    _ => write!(f, "{}", arguments[0]),
+                }
+            }
+            ball => write!(f, "unhandled exception: {}", ball),
+        }
+    }
+}
+
+impl std::error::Error for Exception {}
@@ -0,0 +1,199 @@
+use crate::{exception::Exception, term::Term};
+use serde_json::{Map, Value};
+
+// JSON objects are `json([Key=Value, ...])` with atom keys, arrays are lists,
+// `true`, `false` and `null` are `@(true)`, `@(false)` and `@(null)`, and
+// numbers are integers: other numbers raise `representation_error(integer)`.
+// Strings are atoms or strings, as chosen in the options, except that "[]" is
+// always a string so that it doesn't read as the empty array.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct JsonOptions {
+    pub strings: JsonStrings,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JsonStrings {
+    #[default]
+    Atom,
+    String,
+}
+
+pub fn json_to_term(value: &Value, options: JsonOptions) -> Result<Term, Exception> {
+    Ok(match value {
+        Value::Null => constant("null"),
+        Value::Bool(value) => constant(&value.to_string()),
+        Value::Number(number) => match number.as_i64() {
+            Some(value) => Term::number(value),
+            None => return Err(Exception::representation_error("integer")),
+        },
+        Value::String(text) => match options.strings {
+            JsonStrings::Atom if text != "[]" => Term::atom(text),
+            JsonStrings::Atom => Term::string(text),
+            JsonStrings::String => Term::string(text),
+        },
+        Value::Array(elements) => Term::list(
+            elements
+                .iter()
+                .map(|element| json_to_term(element, options))
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        Value::Object(members) => {
+            let members = members
+                .iter()
+                .map(|(key, value)| {
+                    Ok(Term::functor(
+                        "=",
+                        [Term::atom(key), json_to_term(value, options)?],
+                    ))
+                })
+                .collect::<Result<Vec<_>, Exception>>()?;
+            Term::functor("json", [Term::list(members)])
+        }
+    })
+}
+
+// Members of `json/1` may also be written `Key-Value` or `Key(Value)`, and
+// other atoms and strings are JSON strings.
+pub fn term_to_json(term: &Term) -> Result<Value, Exception> {
+    match term {
+        Term::Variable { .. } => Err(Exception::instantiation_error()),
+        Term::Number { value } => Ok(Value::from(*value)),
+        Term::Str { value } => Ok(Value::String(value.clone())),
+        Term::Functor { name, arguments } => match (name.as_str(), arguments.as_slice()) {
+            ("@", [Term::Functor { name, arguments }]) if arguments.is_empty() => {
+                match name.as_str() {
+                    "true" => Ok(Value::Bool(true)),
+                    "false" => Ok(Value::Bool(false)),
+                    "null" => Ok(Value::Null),
+                    _ => Err(Exception::type_error("json_term", term.clone())),
+                }
+            }
+            ("[]", []) | (".", [_, _]) => term
+                .list_elements()?
+                .iter()
+                .map(term_to_json)
+                .collect::<Result<_, _>>()
+                .map(Value::Array),
+            (name, []) => Ok(Value::String(name.to_string())),
+            ("json", [members]) => {
+                let mut object = Map::new();
+                for member in members.list_elements()? {
+                    let (key, value) = match (member.name(), member.arguments()) {
+                        (Some("=" | "-"), [key, value]) => (key_text(key)?, value),
+                        (Some(key), [value]) => (key.to_string(), value),
+                        _ if matches!(member, Term::Variable { .. }) => {
+                            return Err(Exception::instantiation_error())
+                        }
+                        _ => return Err(Exception::type_error("json_member", member.clone())),
+                    };
+                    object.insert(key, term_to_json(value)?);
+                }
+                Ok(Value::Object(object))
+            }
+            _ => Err(Exception::type_error("json_term", term.clone())),
+        },
+    }
+}
+
+fn constant(name: &str) -> Term {
+    Term::functor("@", [Term::atom(name)])
+}
+
+fn key_text(key: &Term) -> Result<String, Exception> {
+    match key {
+        Term::Functor { name, arguments } if arguments.is_empty() => Ok(name.clone()),
+        Term::Str { value } => Ok(value.clone()),
+        Term::Variable { .. } => Err(Exception::instantiation_error()),
+        key => Err(Exception::type_error("json_key", key.clone())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::MinplParser;
+    use serde_json::json;
+    use test_case::test_case;
+
+    fn term(code: &str) -> Term {
+        MinplParser::parse_query(&format!("{}.", code)).unwrap()
+    }
+
+    #[test]
+    fn values_become_terms() {
+        let value = json!({"name": "ada", "age": 36, "tags": ["x", true, null], "empty": {}});
+
+        assert_eq!(
+            json_to_term(&value, JsonOptions::default()),
+            Ok(term(
+                "json([name=ada, age=36, tags=[x, @(true), @(null)], empty=json([])])"
+            ))
+        );
+        assert_eq!(
+            json_to_term(
+                &json!(["text"]),
+                JsonOptions {
+                    strings: JsonStrings::String
+                }
+            ),
+            Ok(term("[\"text\"]"))
+        );
+    }
+
+    #[test]
+    fn terms_become_values() {
+        assert_eq!(
+            term_to_json(&term(
+                "json([name=ada, \"age\"-36, tags([x, \"y\"]), ok= @(false), none= @(null)])"
+            )),
+            Ok(json!({"name": "ada", "age": 36, "tags": ["x", "y"], "ok": false, "none": null}))
+        );
+        assert_eq!(term_to_json(&term("[]")), Ok(json!([])));
+    }
+
+    #[test_case(JsonStrings::Atom ; "atoms")]
+    #[test_case(JsonStrings::String ; "strings")]
+    fn constants_and_strings_round_trip(strings: JsonStrings) {
+        let value = json!([true, false, null, [], "true", "false", "null", "[]"]);
+
+        let term = json_to_term(&value, JsonOptions { strings }).unwrap();
+
+        assert_eq!(term_to_json(&term), Ok(value));
+    }
+
+    #[test]
+    fn round_trip_keeps_member_order() {
+        let value: Value = serde_json::from_str(r#"{"b": 1, "a": [2, {"c": "d"}]}"#).unwrap();
+
+        let term = json_to_term(&value, JsonOptions::default()).unwrap();
+
+        assert_eq!(
+            serde_json::to_string(&term_to_json(&term).unwrap()).unwrap(),
+            r#"{"b":1,"a":[2,{"c":"d"}]}"#
+        );
+    }
+
+    #[test]
+    fn conversion_errors() {
+        assert_eq!(
+            json_to_term(&json!(1.5), JsonOptions::default()),
+            Err(Exception::representation_error("integer"))
+        );
+        assert_eq!(
+            term_to_json(&term("f(x)")),
+            Err(Exception::type_error("json_term", term("f(x)")))
+        );
+        assert_eq!(
+            term_to_json(&term("@(yes)")),
+            Err(Exception::type_error("json_term", term("@(yes)")))
+        );
+        assert_eq!(
+            term_to_json(&term("json([1])")),
+            Err(Exception::type_error("json_member", Term::number(1)))
+        );
+        assert_eq!(
+            term_to_json(&term("[a|_]")),
+            Err(Exception::instantiation_error())
+        );
+    }
+}
@@ -0,0 +1,19 @@
+pub mod assignments;
+pub mod consult;
+pub mod convert;
+pub mod database;
+pub mod datalog;
+pub mod exception;
+pub mod json;
+pub mod library;
+pub mod machine;
+pub mod operators;
+pub mod parser;
+pub mod proof_search;
+pub mod term;
+pub mod unification;
+
+pub use machine::{Answer, Answers, Machine};
+
+// Lets the code generated by the derive macros name this crate from inside it.
+extern crate self as minpl;
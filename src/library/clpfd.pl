@@ -0,0 +1,123 @@
+:- module(clpfd, [
+    (#=)/2, (#\=)/2, (#<)/2, (#>)/2, (#=<)/2, (#>=)/2,
+    (in)/2, (ins)/2, all_different/1, all_distinct/1, sum/3,
+    label/1, labeling/2
+]).
+
+% Constraints are kept in the `clpfd` attribute of their variables and
+% propagated by the interpreter.
+X #= Y :- '$fd_post'(X #= Y).
+X #\= Y :- '$fd_post'(X #\= Y).
+X #< Y :- '$fd_post'(X #< Y).
+X #> Y :- '$fd_post'(X #> Y).
+X #=< Y :- '$fd_post'(X #=< Y).
+X #>= Y :- '$fd_post'(X #>= Y).
+
+X in Domain :- '$fd_post'(X in Domain).
+Xs ins Domain :- '$fd_post'(Xs ins Domain).
+
+all_different(Xs) :- '$fd_post'(all_different(Xs)).
+all_distinct(Xs) :- '$fd_post'(all_distinct(Xs)).
+
+sum(Vars, Relation, Value) :-
+    (   var(Relation)
+    ->  throw(error(instantiation_error, _))
+    ;   relation(Relation)
+    ->  sum_expression(Vars, Sum),
+        Constraint =.. [Relation, Sum, Value],
+        '$fd_post'(Constraint)
+    ;   throw(error(domain_error(clpfd_relation, Relation), _))
+    ).
+
+relation(#=).
+relation(#\=).
+relation(#<).
+relation(#>).
+relation(#=<).
+relation(#>=).
+
+sum_expression([], 0).
+sum_expression([V|Vs], V + Sum) :-
+    sum_expression(Vs, Sum).
+
+label(Vars) :-
+    labeling([], Vars).
+
+% Options select the variable (leftmost, ff, ffc, min, max), the order of
+% values (up, down) and how to branch (step, enum, bisect). With min(Expr) or
+% max(Expr), all solutions are found first and given by increasing or
+% decreasing value of Expr, then of the next such option.
+labeling(Options, Vars) :-
+    '$fd_labeling_options'(Options, Vars, Selection, Order, Choice, Objectives),
+    (   Objectives == []
+    ->  label(Vars, Selection, Order, Choice)
+    ;   findall(Keys-Vars,
+                (   label(Vars, Selection, Order, Choice),
+                    objective_keys(Objectives, Keys)
+                ),
+                Solutions),
+        keysort(Solutions, Sorted),
+        solution(Vars, Sorted)
+    ).
+
+label(Vars, Selection, Order, Choice) :-
+    (   '$fd_select'(Vars, Selection, Var)
+    ->  choice(Choice, Order, Var),
+        label(Vars, Selection, Order, Choice)
+    ;   true
+    ).
+
+choice(step, Order, Var) :-
+    '$fd_bound'(Var, Order, Value),
+    (   Var = Value
+    ;   Var #\= Value
+    ).
+choice(enum, Order, Var) :-
+    '$fd_values'(Var, Order, Values),
+    value(Var, Values).
+choice(bisect, Order, Var) :-
+    '$fd_middle'(Var, Middle),
+    (   Order == up
+    ->  (   Var #=< Middle
+        ;   Var #> Middle
+        )
+    ;   (   Var #> Middle
+        ;   Var #=< Middle
+        )
+    ).
+
+value(Var, [Value|Values]) :-
+    (   Var = Value
+    ;   value(Var, Values)
+    ).
+
+% The value of each objective, negated for max(Expr) so that sorting puts the
+% largest first.
+objective_keys([], []).
+objective_keys([Objective|Objectives], [Key|Keys]) :-
+    objective_key(Objective, Key),
+    (   integer(Key)
+    ->  objective_keys(Objectives, Keys)
+    ;   throw(error(instantiation_error, _))
+    ).
+
+objective_key(min(Expr), Key) :-
+    Key #= Expr.
+objective_key(max(Expr), Key) :-
+    Key #= -Expr.
+
+solution(Vars, [_-Solution|Solutions]) :-
+    (   Vars = Solution
+    ;   solution(Vars, Solutions)
+    ).
+
+attr_unify_hook(Attribute, Other) :-
+    '$fd_unify'(Attribute, Other).
+
+attribute_goals(X, Goals, Tail) :-
+    '$fd_residual'(X, Residual),
+    append(Residual, Tail, Goals).
+
+append([], Tail, Tail).
+append([X|Xs], Tail, [X|Rest]) :-
+    append(Xs, Tail, Rest).
@@ -0,0 +1,96 @@
+:- module(basics, [blank//0, blanks//0, white//0, whites//0, nonblank//1,
+                   nonblanks//1, digit//1, digits//1, string//1,
+                   string_without//2, remainder//1, eos//0]).
+
+% Codes are compared with the standard order of terms, where numbers are
+% ordered by value.
+
+blank -->
+    [C],
+    { blank_code(C) }.
+
+blanks -->
+    blank,
+    !,
+    blanks.
+blanks -->
+    [].
+
+white -->
+    [C],
+    { white_code(C) }.
+
+whites -->
+    white,
+    !,
+    whites.
+whites -->
+    [].
+
+nonblank(C) -->
+    [C],
+    { \+ blank_code(C) }.
+
+nonblanks([C|Cs]) -->
+    nonblank(C),
+    !,
+    nonblanks(Cs).
+nonblanks([]) -->
+    [].
+
+digit(C) -->
+    [C],
+    { digit_code(C) }.
+
+digits([C|Cs]) -->
+    digit(C),
+    !,
+    digits(Cs).
+digits([]) -->
+    [].
+
+% The shortest list of codes first, then longer ones on backtracking.
+string([]) -->
+    [].
+string([C|Cs]) -->
+    [C],
+    string(Cs).
+
+% The longest list of codes not in `End`, a string or a list of codes.
+string_without(End, Codes) -->
+    { format(codes(EndCodes), "~s", [End]) },
+    codes_without(EndCodes, Codes).
+
+codes_without(End, [C|Cs]) -->
+    [C],
+    { \+ member_code(C, End) },
+    !,
+    codes_without(End, Cs).
+codes_without(_, []) -->
+    [].
+
+remainder(Rest, Rest, []).
+
+eos([], []).
+
+blank_code(C) :-
+    integer(C),
+    (   C == 32
+    ;   C @>= 9,
+        C @=< 13
+    ),
+    !.
+
+white_code(9).
+white_code(32).
+
+digit_code(C) :-
+    integer(C),
+    C @>= 0'0,
+    C @=< 0'9.
+
+member_code(C, [D|Ds]) :-
+    (   C == D
+    ->  true
+    ;   member_code(C, Ds)
+    ).
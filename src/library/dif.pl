@@ -0,0 +1,56 @@
+:- module(dif, [dif/2]).
+
+% dif(X, Y) suspends on the variables of the most general unifier of X and Y
+% and is checked again whenever one of them is bound.
+dif(X, Y) :-
+    X \== Y,
+    (   X \= Y
+    ->  true
+    ;   unifiable(X, Y, Unifier),
+        suspend(Unifier, X-Y)
+    ).
+
+suspend([], _).
+suspend([V = T|Unifier], Pair) :-
+    add_pair(V, Pair),
+    (   var(T)
+    ->  add_pair(T, Pair)
+    ;   true
+    ),
+    suspend(Unifier, Pair).
+
+add_pair(V, Pair) :-
+    (   get_attr(V, dif, Pairs)
+    ->  true
+    ;   Pairs = []
+    ),
+    (   contains(Pairs, Pair)
+    ->  true
+    ;   put_attr(V, dif, [Pair|Pairs])
+    ).
+
+contains([P|Ps], Pair) :-
+    (   P == Pair
+    ->  true
+    ;   contains(Ps, Pair)
+    ).
+
+attr_unify_hook(Pairs, _) :-
+    recheck(Pairs).
+
+recheck([]).
+recheck([X-Y|Pairs]) :-
+    dif(X, Y),
+    recheck(Pairs).
+
+attribute_goals(V, Goals, Tail) :-
+    get_attr(V, dif, Pairs),
+    pending(Pairs, Goals, Tail).
+
+pending([], Tail, Tail).
+pending([X-Y|Pairs], Goals, Tail) :-
+    (   X \= Y
+    ->  Goals = Goals1
+    ;   Goals = [dif(X, Y)|Goals1]
+    ),
+    pending(Pairs, Goals1, Tail).
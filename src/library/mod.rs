@@ -0,0 +1,121 @@
+use std::path::{Path, PathBuf};
+
+// Libraries bundled with the interpreter, loaded with `use_module(library(Name))`.
+const LIBRARIES: &[(&str, &str)] = &[
+    ("clpfd", include_str!("clpfd.pl")),
+    ("dcg/basics", include_str!("dcg/basics.pl")),
+    ("dif", include_str!("dif.pl")),
+    ("when", include_str!("when.pl")),
+];
+
+// Predicates that load their library the first time they are called.
+const AUTOLOAD: &[(&str, usize, &str)] = &[
+    ("dif", 2, "dif"),
+    ("freeze", 2, "when"),
+    ("frozen", 2, "when"),
+    ("when", 2, "when"),
+];
+
+pub fn path(name: &str) -> Option<PathBuf> {
+    LIBRARIES
+        .iter()
+        .any(|(library, _)| *library == name)
+        .then(|| PathBuf::from(format!("library({})", name)))
+}
+
+pub fn source(path: &Path) -> Option<&'static str> {
+    let name = path.to_str()?.strip_prefix("library(")?.strip_suffix(')')?;
+    LIBRARIES
+        .iter()
+        .find(|(library, _)| *library == name)
+        .map(|(_, source)| *source)
+}
+
+pub fn autoload(name: &str, arity: usize) -> Option<&'static str> {
+    AUTOLOAD
+        .iter()
+        .find(|(predicate, predicate_arity, _)| *predicate == name && *predicate_arity == arity)
+        .map(|(_, _, library)| *library)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{database::Database, parser::MinplParser, proof_search::solve, term::Term};
+    use test_case::test_case;
+
+    fn residual_goals(code: &str) -> Vec<Vec<Term>> {
+        let mut database = Database::empty();
+        let mut solver = solve(&mut database, MinplParser::parse_query(code).unwrap());
+        let mut answers = vec![];
+        while let Some(result) = solver.next() {
+            result.unwrap();
+            answers.push(solver.residual_goals().unwrap());
+        }
+        answers
+    }
+
+    #[test]
+    fn library_paths_resolve_to_bundled_sources() {
+        let path = path("dif").unwrap();
+
+        assert!(source(&path).unwrap().contains(":- module(dif"));
+        assert_eq!(super::path("nope"), None);
+    }
+
+    #[test_case("dif(a, b)." => 1)]
+    #[test_case("dif(a, a)." => 0)]
+    #[test_case("dif(X, a), X = a." => 0)]
+    #[test_case("dif(X, a), X = b." => 1)]
+    #[test_case("X = a, dif(X, a)." => 0)]
+    #[test_case("dif(f(X, Y), f(a, b)), X = a, Y = b." => 0)]
+    #[test_case("dif(f(X, Y), f(a, b)), X = a, Y = c." => 1)]
+    #[test_case("dif(X, Y), X = Z, Y = Z." => 0)]
+    #[test_case("(dif(X, a) ; true), X = a." => 1)]
+    fn dif_solutions(code: &str) -> usize {
+        residual_goals(code).len()
+    }
+
+    #[test]
+    fn pending_dif_is_a_residual_goal() {
+        assert_eq!(
+            residual_goals("dif(X, a)."),
+            vec![vec![Term::functor(
+                "dif",
+                [Term::variable("X"), Term::atom("a")]
+            )]]
+        );
+        assert_eq!(residual_goals("dif(X, a), X = b."), vec![vec![]]);
+    }
+
+    #[test_case("freeze(X, Y = 1), X = a, Y == 1." => 1)]
+    #[test_case("freeze(X, fail), X = a." => 0)]
+    #[test_case("freeze(a, Y = 1), Y == 1." => 1)]
+    #[test_case("freeze(X, Y = 1), X = Z, Z = b, Y == 1." => 1)]
+    #[test_case("(freeze(X, fail) ; true), X = 1." => 1)]
+    #[test_case("when(nonvar(X), Y = 1), X = f(_), Y == 1." => 1)]
+    #[test_case("when(ground(f(X, Y)), Z = 1), X = a, var(Z), Y = b, Z == 1." => 1)]
+    #[test_case("when(?=(X, Y), Z = 1), X = a, Y = b, Z == 1." => 1)]
+    #[test_case("when(?=(X, Y), Z = 1), X = Y, Z == 1." => 1)]
+    #[test_case("when((nonvar(X) ; nonvar(Y)), Z = 1), Y = b, Z == 1." => 1)]
+    #[test_case("catch(when(foo, true), error(domain_error(when_condition, foo), _), true)." => 1)]
+    #[test_case("freeze(X, true), frozen(X, freeze(X, true))." => 1)]
+    #[test_case("frozen(a, true)." => 1)]
+    fn coroutine_solutions(code: &str) -> usize {
+        residual_goals(code).len()
+    }
+
+    #[test]
+    fn suspended_goals_are_residual_goals() {
+        assert_eq!(
+            residual_goals("freeze(X, f(Y))."),
+            vec![vec![MinplParser::parse_query("freeze(X, f(Y)).").unwrap()]]
+        );
+        assert_eq!(
+            residual_goals("when(ground(X-Y), f), X = a."),
+            vec![vec![
+                MinplParser::parse_query("when(ground(a-Y), f).").unwrap()
+            ]]
+        );
+    }
+}
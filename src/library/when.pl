@@ -0,0 +1,168 @@
+:- module(when, [freeze/2, frozen/2, when/2]).
+
+:- meta_predicate freeze(?, 0), when(+, 0).
+
+% The `when` attribute of a variable holds the goals waiting for it, as
+% `frozen(Goal)` and `when(Condition, Goal, Done)`. `Done` is shared by all
+% the variables a `when/2` waits on, so its goal runs only once.
+freeze(X, Goal) :-
+    (   var(X)
+    ->  suspend(X, frozen(Goal))
+    ;   call(Goal)
+    ).
+
+frozen(X, Goal) :-
+    (   var(X),
+        attribute_goals(X, Goals, [])
+    ->  conjunction(Goals, Goal)
+    ;   Goal = true
+    ).
+
+% Conditions are `nonvar(X)`, `ground(X)`, `?=(X, Y)` and their conjunctions
+% and disjunctions.
+when(Condition, Goal) :-
+    check_condition(Condition),
+    trigger(Condition, Goal, _).
+
+check_condition(Condition) :-
+    (   var(Condition)
+    ->  throw(error(instantiation_error, _))
+    ;   Condition = nonvar(_)
+    ->  true
+    ;   Condition = ground(_)
+    ->  true
+    ;   Condition = ?=(_, _)
+    ->  true
+    ;   Condition = (C1, C2)
+    ->  check_condition(C1),
+        check_condition(C2)
+    ;   Condition = (C1 ; C2)
+    ->  check_condition(C1),
+        check_condition(C2)
+    ;   throw(error(domain_error(when_condition, Condition), _))
+    ).
+
+trigger(Condition, Goal, Done) :-
+    (   Done == true
+    ->  true
+    ;   holds(Condition)
+    ->  Done = true,
+        call(Goal)
+    ;   waiting_variables(Condition, Vars),
+        suspend_all(Vars, when(Condition, Goal, Done))
+    ).
+
+holds(nonvar(X)) :-
+    nonvar(X).
+holds(ground(X)) :-
+    ground(X).
+holds(?=(X, Y)) :-
+    (   X == Y
+    ->  true
+    ;   X \= Y
+    ).
+holds((C1, C2)) :-
+    holds(C1),
+    holds(C2).
+holds((C1 ; C2)) :-
+    (   holds(C1)
+    ->  true
+    ;   holds(C2)
+    ).
+
+% Variables whose binding may make the condition hold.
+waiting_variables(nonvar(X), [X]).
+waiting_variables(ground(X), [V]) :-
+    term_variables(X, [V|_]).
+waiting_variables(?=(X, Y), Vars) :-
+    unifiable(X, Y, Unifier),
+    term_variables(Unifier, Vars).
+waiting_variables((C1, C2), Vars) :-
+    (   holds(C1)
+    ->  waiting_variables(C2, Vars)
+    ;   waiting_variables(C1, Vars)
+    ).
+waiting_variables((C1 ; C2), Vars) :-
+    waiting_variables(C1, Vars1),
+    waiting_variables(C2, Vars2),
+    append(Vars1, Vars2, Vars).
+
+suspend_all([], _).
+suspend_all([V|Vs], Waiting) :-
+    suspend(V, Waiting),
+    suspend_all(Vs, Waiting).
+
+suspend(V, Waiting) :-
+    (   get_attr(V, when, Waitings)
+    ->  (   contains(Waitings, Waiting)
+        ->  true
+        ;   append(Waitings, [Waiting], NewWaitings),
+            put_attr(V, when, NewWaitings)
+        )
+    ;   put_attr(V, when, [Waiting])
+    ).
+
+contains([W|Ws], Waiting) :-
+    (   W == Waiting
+    ->  true
+    ;   contains(Ws, Waiting)
+    ).
+
+% Binding two variables moves the frozen goals to the remaining one and
+% checks the `when/2` conditions again, since `?=/2` may hold now.
+attr_unify_hook(Waitings, Other) :-
+    (   var(Other)
+    ->  merge(Waitings, Other)
+    ;   wake(Waitings)
+    ).
+
+merge([], _).
+merge([Waiting|Waitings], Other) :-
+    (   Waiting = frozen(_)
+    ->  suspend(Other, Waiting)
+    ;   run(Waiting)
+    ),
+    merge(Waitings, Other).
+
+wake([]).
+wake([Waiting|Waitings]) :-
+    run(Waiting),
+    wake(Waitings).
+
+run(frozen(Goal)) :-
+    call(Goal).
+run(when(Condition, Goal, Done)) :-
+    trigger(Condition, Goal, Done).
+
+attribute_goals(X, Goals, Tail) :-
+    get_attr(X, when, Waitings),
+    residual(Waitings, X, Goals, Tail).
+
+residual([], _, Tail, Tail).
+residual([Waiting|Waitings], X, Goals, Tail) :-
+    (   Waiting = frozen(Goal)
+    ->  unqualified(Goal, G),
+        Goals = [freeze(X, G)|Goals1]
+    ;   Waiting = when(Condition, Goal, Done),
+        Done \== true
+    ->  unqualified(Goal, G),
+        Goals = [when(Condition, G)|Goals1]
+    ;   Goals = Goals1
+    ),
+    residual(Waitings, X, Goals1, Tail).
+
+unqualified(Goal, G) :-
+    (   Goal = user:G
+    ->  true
+    ;   G = Goal
+    ).
+
+conjunction([], true).
+conjunction([Goal], Goal) :-
+    !.
+conjunction([Goal|Goals], (Goal, Conjunction)) :-
+    conjunction(Goals, Conjunction).
+
+append([], Tail, Tail).
+append([X|Xs], Tail, [X|Rest]) :-
+    append(Xs, Tail, Rest).
@@ -0,0 +1,312 @@
+use crate::{
+    assignments::Assignments,
+    consult,
+    convert::FromTerm,
+    database::{Database, ForeignPredicate, USER},
+    exception::Exception,
+    parser::MinplParser,
+    proof_search::{solve, Solver},
+    term::Term,
+};
+use std::{fmt::Display, path::Path};
+
+// An interpreter with its database, for programs embedding minpl.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Machine {
+    database: Database,
+}
+
+impl Default for Machine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Machine {
+    pub fn new() -> Self {
+        Self::with_database(Database::empty())
+    }
+
+    pub fn with_database(database: Database) -> Self {
+        Self { database }
+    }
+
+    pub fn database(&self) -> &Database {
+        &self.database
+    }
+
+    pub fn database_mut(&mut self) -> &mut Database {
+        &mut self.database
+    }
+
+    pub fn consult_str(&mut self, code: &str) -> Result<(), Exception> {
+        consult::consult_str(&mut self.database, code)
+    }
+
+    pub fn consult_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Exception> {
+        let file = Term::atom(path.as_ref().display());
+        consult::consult(&mut self.database, &file, USER)
+    }
+
+    pub fn register_foreign<P: ForeignPredicate + 'static>(
+        &mut self,
+        name: &str,
+        arity: usize,
+        predicate: P,
+    ) {
+        self.database.register_foreign(name, arity, predicate);
+    }
+
+    // The goal of a loaded `initialization(Goal, main)` directive.
+    pub fn take_main(&mut self) -> Option<Term> {
+        self.database.take_main()
+    }
+
+    // Answers of a query written in Prolog syntax, ending with a dot. A
+    // syntax error is the only item.
+    pub fn query(&mut self, code: &str) -> Answers<'_> {
+        match MinplParser::parse_query(code) {
+            Ok(goal) => self.query_term(goal),
+            Err(error) => Answers {
+                solver: None,
+                error: Some(Exception::syntax_error(error)),
+            },
+        }
+    }
+
+    pub fn query_term(&mut self, goal: Term) -> Answers<'_> {
+        Answers {
+            solver: Some(solve(&mut self.database, goal)),
+            error: None,
+        }
+    }
+}
+
+pub struct Answers<'a> {
+    solver: Option<Solver<'a>>,
+    error: Option<Exception>,
+}
+
+impl Iterator for Answers<'_> {
+    type Item = Result<Answer, Exception>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(error) = self.error.take() {
+            return Some(Err(error));
+        }
+        let solver = self.solver.as_mut()?;
+        let answer = solver.next()?.and_then(|bindings| {
+            Ok(Answer {
+                bindings,
+                residual_goals: solver.residual_goals()?,
+            })
+        });
+        Some(answer)
+    }
+}
+
+// The bindings of the variables of a query in one of its solutions, and the
+// constraints left on them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Answer {
+    bindings: Assignments,
+    residual_goals: Vec<Term>,
+}
+
+impl Answer {
+    pub fn bindings(&self) -> &Assignments {
+        &self.bindings
+    }
+
+    pub fn residual_goals(&self) -> &[Term] {
+        &self.residual_goals
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty() && self.residual_goals.is_empty()
+    }
+
+    // The value of a variable, `None` when it is left unbound.
+    pub fn get(&self, variable: &str) -> Option<&Term> {
+        self.bindings.get(variable)
+    }
+
+    // The value of a variable converted to a Rust type.
+    pub fn value<T: FromTerm>(&self, variable: &str) -> Result<T, Exception> {
+        T::from_term(self.bound(variable)?)
+    }
+
+    pub fn atom(&self, variable: &str) -> Result<&str, Exception> {
+        match self.bound(variable)? {
+            Term::Functor { name, arguments } if arguments.is_empty() => Ok(name),
+            value => Err(Exception::type_error("atom", value.clone())),
+        }
+    }
+
+    pub fn integer(&self, variable: &str) -> Result<i64, Exception> {
+        match self.bound(variable)? {
+            Term::Number { value } => Ok(*value),
+            value => Err(Exception::type_error("integer", value.clone())),
+        }
+    }
+
+    pub fn string(&self, variable: &str) -> Result<&str, Exception> {
+        match self.bound(variable)? {
+            Term::Str { value } => Ok(value),
+            value => Err(Exception::type_error("string", value.clone())),
+        }
+    }
+
+    pub fn list(&self, variable: &str) -> Result<Vec<Term>, Exception> {
+        self.bound(variable)?.list_elements()
+    }
+
+    fn bound(&self, variable: &str) -> Result<&Term, Exception> {
+        match self.get(variable) {
+            Some(Term::Variable { .. }) | None => Err(Exception::instantiation_error()),
+            Some(value) => Ok(value),
+        }
+    }
+}
+
+// Written as the REPL shows it: the bindings, then each residual goal.
+impl Display for Answer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut lines = vec![];
+        if !self.bindings.is_empty() {
+            lines.push(self.bindings.to_string());
+        }
+        lines.extend(self.residual_goals.iter().map(Term::to_string));
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::database::deterministic;
+    use std::{env, fs};
+
+    fn machine() -> Machine {
+        let mut machine = Machine::new();
+        machine
+            .consult_str(
+                "parent(tom, bob).
+                parent(bob, ann).
+                ancestor(X, Y) :- parent(X, Y).
+                ancestor(X, Z) :- parent(X, Y), ancestor(Y, Z).
+                info(\"text\", 42, [a, b]).",
+            )
+            .unwrap();
+        machine
+    }
+
+    #[test]
+    fn query_gives_each_answer() {
+        let mut machine = machine();
+
+        let names = machine
+            .query("ancestor(tom, X).")
+            .map(|answer| answer.unwrap().atom("X").unwrap().to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, vec!["bob", "ann"]);
+        assert_eq!(machine.query("parent(ann, _).").count(), 0);
+    }
+
+    #[test]
+    fn deep_terms_do_not_overflow_the_stack() {
+        let mut machine = Machine::new();
+        machine
+            .consult_str(
+                "app([], L, L).
+                app([H|T], L, [H|R]) :- app(T, L, R).
+                double(0, L, L).
+                double(s(N), L0, L) :- app(L0, L0, L1), double(N, L1, L).
+                walk([]).
+                walk([_|T]) :- walk(T), true.",
+            )
+            .unwrap();
+        let fifteen = (0..15).fold(String::from("0"), |n, _| format!("s({n})"));
+
+        let answers = machine.query(&format!("double({fifteen}, [a], L), walk(L)."));
+
+        assert_eq!(answers.count(), 1);
+    }
+
+    #[test]
+    fn typed_accessors() {
+        let mut machine = machine();
+        let answer = machine.query("info(S, N, L).").next().unwrap().unwrap();
+
+        assert_eq!(answer.string("S"), Ok("text"));
+        assert_eq!(answer.integer("N"), Ok(42));
+        assert_eq!(answer.list("L"), Ok(vec![Term::atom("a"), Term::atom("b")]));
+        assert_eq!(
+            answer.atom("N"),
+            Err(Exception::type_error("atom", Term::number(42)))
+        );
+        assert_eq!(answer.atom("Other"), Err(Exception::instantiation_error()));
+        assert_eq!(
+            answer.value::<Vec<String>>("L"),
+            Ok(vec![String::from("a"), String::from("b")])
+        );
+    }
+
+    #[test]
+    fn errors_are_answers() {
+        let mut machine = machine();
+
+        assert!(matches!(
+            machine.query("parent(.").collect::<Vec<_>>().as_slice(),
+            [Err(_)]
+        ));
+        assert_eq!(
+            machine.query("throw(oops).").collect::<Vec<_>>(),
+            vec![Err(Exception::new(Term::atom("oops")))]
+        );
+    }
+
+    #[test]
+    fn residual_goals_are_part_of_the_answer() {
+        let mut machine = Machine::new();
+        let answer = machine.query("dif(X, a).").next().unwrap().unwrap();
+
+        assert!(answer.bindings().is_empty());
+        assert_eq!(
+            answer.residual_goals(),
+            [Term::functor("dif", [Term::variable("X"), Term::atom("a")])]
+        );
+        assert_eq!(answer.to_string(), "dif(X, a)");
+    }
+
+    #[test]
+    fn consult_file_and_foreign_predicates() {
+        let path = env::temp_dir().join(format!("minpl-machine-{}.pl", std::process::id()));
+        fs::write(
+            &path,
+            "greet(Name, Greeting) :- greeting(G), join(G, Name, Greeting).\ngreeting(hello).",
+        )
+        .unwrap();
+        let mut machine = Machine::new();
+        machine.register_foreign(
+            "join",
+            3,
+            deterministic(|arguments: &[Term]| {
+                let text = format!("{} {}", arguments[0], arguments[1]);
+                Ok(Some(vec![
+                    arguments[0].clone(),
+                    arguments[1].clone(),
+                    Term::atom(text),
+                ]))
+            }),
+        );
+
+        let consulted = machine.consult_file(&path);
+        let answer = machine.query("greet(world, G).").next();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(consulted, Ok(()));
+        assert_eq!(answer.unwrap().unwrap().atom("G"), Ok("hello world"));
+    }
+}
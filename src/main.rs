@@ -1,13 +1,8 @@
-pub mod assignments;
-pub mod database;
-pub mod parser;
-pub mod proof_search;
-pub mod repl;
-pub mod term;
-pub mod unification;
+mod repl;
 
 use repl::MinplRepl;
+use std::env;
 
 fn main() {
-    MinplRepl::new().run();
+    MinplRepl::new().run(env::args().skip(1));
 }
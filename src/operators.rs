@@ -0,0 +1,119 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperatorType {
+    Xfx,
+    Xfy,
+    Yfx,
+    Fy,
+    Fx,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Operator {
+    pub priority: u32,
+    pub kind: OperatorType,
+}
+
+impl Operator {
+    const fn new(priority: u32, kind: OperatorType) -> Self {
+        Self { priority, kind }
+    }
+
+    pub fn left_priority(&self) -> u32 {
+        match self.kind {
+            OperatorType::Yfx => self.priority,
+            _ => self.priority - 1,
+        }
+    }
+
+    pub fn right_priority(&self) -> u32 {
+        match self.kind {
+            OperatorType::Xfy | OperatorType::Fy => self.priority,
+            _ => self.priority - 1,
+        }
+    }
+}
+
+use OperatorType::*;
+
+const INFIX_OPERATORS: &[(&str, Operator)] = &[
+    (":-", Operator::new(1200, Xfx)),
+    ("-->", Operator::new(1200, Xfx)),
+    (";", Operator::new(1100, Xfy)),
+    ("|", Operator::new(1100, Xfy)),
+    ("->", Operator::new(1050, Xfy)),
+    ("*->", Operator::new(1050, Xfy)),
+    (",", Operator::new(1000, Xfy)),
+    ("=", Operator::new(700, Xfx)),
+    ("\\=", Operator::new(700, Xfx)),
+    ("==", Operator::new(700, Xfx)),
+    ("\\==", Operator::new(700, Xfx)),
+    ("@<", Operator::new(700, Xfx)),
+    ("@>", Operator::new(700, Xfx)),
+    ("@=<", Operator::new(700, Xfx)),
+    ("@>=", Operator::new(700, Xfx)),
+    ("=..", Operator::new(700, Xfx)),
+    ("is", Operator::new(700, Xfx)),
+    ("=:=", Operator::new(700, Xfx)),
+    ("=\\=", Operator::new(700, Xfx)),
+    ("<", Operator::new(700, Xfx)),
+    (">", Operator::new(700, Xfx)),
+    ("=<", Operator::new(700, Xfx)),
+    (">=", Operator::new(700, Xfx)),
+    ("#=", Operator::new(700, Xfx)),
+    ("#\\=", Operator::new(700, Xfx)),
+    ("#<", Operator::new(700, Xfx)),
+    ("#>", Operator::new(700, Xfx)),
+    ("#=<", Operator::new(700, Xfx)),
+    ("#>=", Operator::new(700, Xfx)),
+    ("in", Operator::new(700, Xfx)),
+    ("ins", Operator::new(700, Xfx)),
+    ("+", Operator::new(500, Yfx)),
+    ("-", Operator::new(500, Yfx)),
+    ("/\\", Operator::new(500, Yfx)),
+    ("\\/", Operator::new(500, Yfx)),
+    ("xor", Operator::new(500, Yfx)),
+    ("..", Operator::new(450, Xfx)),
+    ("*", Operator::new(400, Yfx)),
+    ("/", Operator::new(400, Yfx)),
+    ("//", Operator::new(400, Yfx)),
+    ("rem", Operator::new(400, Yfx)),
+    ("mod", Operator::new(400, Yfx)),
+    ("div", Operator::new(400, Yfx)),
+    ("<<", Operator::new(400, Yfx)),
+    (">>", Operator::new(400, Yfx)),
+    ("**", Operator::new(200, Xfx)),
+    ("^", Operator::new(200, Xfy)),
+    (":", Operator::new(200, Xfy)),
+];
+
+const PREFIX_OPERATORS: &[(&str, Operator)] = &[
+    (":-", Operator::new(1200, Fx)),
+    ("?-", Operator::new(1200, Fx)),
+    ("dynamic", Operator::new(1150, Fx)),
+    ("discontiguous", Operator::new(1150, Fx)),
+    ("meta_predicate", Operator::new(1150, Fx)),
+    ("\\+", Operator::new(900, Fy)),
+    ("-", Operator::new(200, Fy)),
+    ("+", Operator::new(200, Fy)),
+    ("\\", Operator::new(200, Fy)),
+];
+
+pub fn infix(name: &str) -> Option<Operator> {
+    lookup(INFIX_OPERATORS, name)
+}
+
+pub fn prefix(name: &str) -> Option<Operator> {
+    lookup(PREFIX_OPERATORS, name)
+}
+
+// The characters that symbol atoms such as `=..` and `\+` are made of.
+pub fn is_symbol_char(c: char) -> bool {
+    "+-*/\\^<>=~:.?@#&$".contains(c)
+}
+
+fn lookup(table: &[(&str, Operator)], name: &str) -> Option<Operator> {
+    table
+        .iter()
+        .find(|(op_name, _)| *op_name == name)
+        .map(|(_, op)| *op)
+}
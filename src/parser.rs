@@ -1,66 +1,347 @@
-use crate::{database::Database, term::Term};
-use pest::{error::Error, iterators::Pair, Parser};
+use crate::{
+    operators::{self, is_symbol_char},
+    term::Term,
+};
+use pest::{
+    error::{Error, ErrorVariant},
+    iterators::{Pair, Pairs},
+    Parser,
+};
 use pest_derive::Parser;
+use std::{
+    collections::HashMap,
+    iter::Peekable,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 #[derive(Parser)]
 #[grammar = "grammar.pest"]
 pub struct MinplParser;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Sentence {
+    Clause { clause: Term, line: usize },
+    Directive { goal: Term, line: usize },
+}
+
+type ParseResult<T> = Result<T, Box<Error<Rule>>>;
+
+static ANONYMOUS_VARIABLES: AtomicUsize = AtomicUsize::new(0);
+
 impl MinplParser {
-    pub fn parse_query(code: &str) -> Result<Term, Error<Rule>> {
-        Ok(Self::build_query(
-            Self::parse(Rule::query, code)?.next().unwrap(),
-        ))
+    pub fn parse_query(code: &str) -> ParseResult<Term> {
+        Self::build_query(Self::parse(Rule::query, code)?.next().unwrap())
     }
 
-    pub fn parse_database(code: &str) -> Result<Database, Error<Rule>> {
-        Ok(Self::build_database(
-            Self::parse(Rule::database, code)?.next().unwrap(),
-        ))
+    pub fn parse_program(code: &str) -> ParseResult<Vec<Sentence>> {
+        Self::build_program(Self::parse(Rule::database, code)?.next().unwrap())
     }
 
+    #[cfg(test)]
+    pub fn parse_database(
+        code: &str,
+    ) -> Result<crate::database::Database, crate::exception::Exception> {
+        let mut database = crate::database::Database::empty();
+        crate::consult::consult_str(&mut database, code)?;
+        Ok(database)
+    }
+
+    #[cfg(test)]
     fn parse_term(code: &str) -> Term {
-        Self::build_term(Self::parse(Rule::term, code).unwrap().next().unwrap())
+        let term = Self::build_term(Self::parse(Rule::term, code).unwrap().next().unwrap());
+        rename_apart(term.unwrap(), &mut HashMap::new())
     }
 
-    fn build_term(pair: Pair<Rule>) -> Term {
-        match pair.as_rule() {
+    fn build_term(pair: Pair<Rule>) -> ParseResult<Term> {
+        let term = match pair.as_rule() {
+            Rule::term => Self::build_operators(pair, 1200)?,
+            Rule::argument => Self::build_operators(pair, 999)?,
             Rule::variable => Term::variable(pair.as_str()),
-            Rule::atom => Term::atom(pair.as_str()),
+            Rule::atom => Term::atom(Self::build_name(pair.into_inner().next().unwrap())),
             Rule::functor => {
                 let mut inner = pair.into_inner();
-                let name = inner.next().unwrap().as_str();
-                let args = inner.map(|pair| Self::build_term(pair));
+                let name = Self::build_name(inner.next().unwrap());
+                let args = inner
+                    .next()
+                    .unwrap()
+                    .into_inner()
+                    .map(Self::build_term)
+                    .collect::<ParseResult<Vec<_>>>()?;
                 Term::functor(name, args)
             }
+            Rule::list => {
+                let mut elements = vec![];
+                let mut tail = Term::atom("[]");
+                for pair in pair.into_inner() {
+                    match pair.as_rule() {
+                        Rule::list_tail => {
+                            tail = Self::build_term(pair.into_inner().next().unwrap())?
+                        }
+                        _ => elements.push(Self::build_term(pair)?),
+                    }
+                }
+                Term::list_with_tail(elements, tail)
+            }
+            Rule::curly => {
+                Term::functor("{}", [Self::build_term(pair.into_inner().next().unwrap())?])
+            }
+            Rule::number => Term::number(Self::build_number(pair)?),
+            Rule::string => Term::string(Self::unquote(pair.as_str(), '"')),
             _ => unreachable!(),
+        };
+        Ok(term)
+    }
+
+    fn build_operators(pair: Pair<Rule>, max_priority: u32) -> ParseResult<Term> {
+        let mut tokens = pair.into_inner().peekable();
+        let (term, _) = Self::build_operator_term(&mut tokens, max_priority)?;
+        if let Some(token) = tokens.next() {
+            return Err(Self::error(&token, "operator priority clash"));
+        }
+        Ok(term)
+    }
+
+    fn build_operator_term(
+        tokens: &mut Peekable<Pairs<Rule>>,
+        max_priority: u32,
+    ) -> ParseResult<(Term, u32)> {
+        let first = tokens.next().unwrap();
+        let (mut left, mut left_priority) = if first.as_rule() == Rule::prefix_operator {
+            let name = first.as_str();
+            let operator = operators::prefix(name).unwrap();
+            match tokens.peek() {
+                Some(next)
+                    if name == "-"
+                        && next.as_rule() == Rule::number
+                        && next.as_span().start() == first.as_span().end() =>
+                {
+                    let number = Self::build_number(tokens.next().unwrap())?;
+                    (Term::number(-number), 0)
+                }
+                _ => {
+                    if operator.priority > max_priority {
+                        return Err(Self::error(&first, "operator priority clash"));
+                    }
+                    let (argument, _) =
+                        Self::build_operator_term(tokens, operator.right_priority())?;
+                    (Term::functor(name, [argument]), operator.priority)
+                }
+            }
+        } else {
+            (Self::build_term(first)?, 0)
+        };
+        while let Some(token) = tokens.peek() {
+            let name = token.as_str();
+            let operator = operators::infix(name)
+                .ok_or_else(|| Self::error(token, &format!("unknown operator `{}`", name)))?;
+            if operator.priority > max_priority || left_priority > operator.left_priority() {
+                break;
+            }
+            tokens.next();
+            let (right, _) = Self::build_operator_term(tokens, operator.right_priority())?;
+            let name = if name == "|" { ";" } else { name };
+            left = Term::functor(name, [left, right]);
+            left_priority = operator.priority;
         }
+        Ok((left, left_priority))
     }
 
-    fn build_database(pair: Pair<Rule>) -> Database {
-        let mut database = Database::empty();
+    fn build_name(pair: Pair<Rule>) -> String {
+        match pair.as_rule() {
+            Rule::quoted_atom => Self::unquote(pair.as_str(), '\''),
+            Rule::empty_list => String::from("[]"),
+            Rule::empty_curly => String::from("{}"),
+            _ => String::from(pair.as_str()),
+        }
+    }
+
+    fn build_number(pair: Pair<Rule>) -> ParseResult<i64> {
+        let text = pair.as_str();
+        if let Some(character) = text.strip_prefix("0'") {
+            let character = match character {
+                "''" => String::from("'"),
+                _ => Self::unescape(character),
+            };
+            Ok(character.chars().next().unwrap() as i64)
+        } else {
+            text.parse()
+                .map_err(|_| Self::error(&pair, "integer out of range"))
+        }
+    }
+
+    fn unquote(text: &str, quote: char) -> String {
+        let doubled = format!("{}{}", quote, quote);
+        Self::unescape(&text[1..text.len() - 1].replace(&doubled, &quote.to_string()))
+    }
+
+    fn unescape(text: &str) -> String {
+        let mut result = String::new();
+        let mut chars = text.chars();
+        while let Some(character) = chars.next() {
+            if character != '\\' {
+                result.push(character);
+                continue;
+            }
+            match chars.next() {
+                Some('n') => result.push('\n'),
+                Some('t') => result.push('\t'),
+                Some('r') => result.push('\r'),
+                Some('0') => result.push('\0'),
+                Some('\n') | None => {}
+                Some(other) => result.push(other),
+            }
+        }
+        result
+    }
+
+    fn error(pair: &Pair<Rule>, message: &str) -> Box<Error<Rule>> {
+        Box::new(Error::new_from_span(
+            ErrorVariant::CustomError {
+                message: String::from(message),
+            },
+            pair.as_span(),
+        ))
+    }
+
+    fn build_program(pair: Pair<Rule>) -> ParseResult<Vec<Sentence>> {
         let pair = pair.into_inner().next().unwrap();
-        for rule in pair.into_inner() {
-            database.add(Self::build_rule(rule));
+        pair.into_inner()
+            .map(|sentence| {
+                let (line, _) = sentence.as_span().start_pos().line_col();
+                let directive = sentence.as_rule() == Rule::directive;
+                let term = Self::build_term(sentence.into_inner().next().unwrap())?;
+                let term = rename_apart(term, &mut HashMap::new());
+                Ok(if directive {
+                    Sentence::Directive { goal: term, line }
+                } else {
+                    Sentence::Clause { clause: term, line }
+                })
+            })
+            .collect()
+    }
+
+    fn build_query(pair: Pair<Rule>) -> ParseResult<Term> {
+        let term = Self::build_term(pair.into_inner().next().unwrap())?;
+        Ok(rename_apart(term, &mut HashMap::new()))
+    }
+
+    // Takes characters up to the end of the next clause, so that it can be
+    // parsed with `parse_query`. Quoted text, character codes and comments are
+    // skipped over when looking for the end. Gives `None` when only layout is
+    // left before the end of input.
+    pub fn read_sentence<I: Iterator<Item = char>>(chars: &mut Peekable<I>) -> Option<String> {
+        let mut text = String::new();
+        while let Some(c) = chars.next() {
+            match c {
+                '%' => {
+                    chars.find(|&c| c == '\n');
+                    text.push('\n');
+                }
+                '/' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    let mut last = ' ';
+                    for c in chars.by_ref() {
+                        if last == '*' && c == '/' {
+                            break;
+                        }
+                        last = c;
+                    }
+                    text.push(' ');
+                }
+                '\'' if is_character_code(&text) => {
+                    text.push('\'');
+                    match chars.next() {
+                        Some('\\') => {
+                            text.push('\\');
+                            text.extend(chars.next());
+                        }
+                        Some('\'') => {
+                            text.push('\'');
+                            text.extend(chars.next_if_eq(&'\''));
+                        }
+                        other => text.extend(other),
+                    }
+                }
+                '\'' | '"' | '`' => {
+                    text.push(c);
+                    Self::read_quoted(chars, c, &mut text);
+                }
+                '.' if !text.ends_with(is_symbol_char)
+                    && chars.peek().is_none_or(|c| c.is_whitespace() || *c == '%') =>
+                {
+                    text.push('.');
+                    chars.next_if(|c| c.is_whitespace());
+                    return Some(text);
+                }
+                c => text.push(c),
+            }
         }
-        database
+        (!text.trim().is_empty()).then_some(text)
     }
 
-    fn build_rule(pair: Pair<Rule>) -> crate::database::Rule {
-        let mut inner = pair.into_inner();
-        let head = Self::build_term(inner.next().unwrap());
-        let body = inner.map(|term| Self::build_term(term)).collect();
-        crate::database::Rule { head, body }
+    fn read_quoted<I: Iterator<Item = char>>(
+        chars: &mut Peekable<I>,
+        quote: char,
+        text: &mut String,
+    ) {
+        while let Some(c) = chars.next() {
+            text.push(c);
+            if c == '\\' {
+                text.extend(chars.next());
+            } else if c == quote {
+                match chars.next_if_eq(&quote) {
+                    Some(c) => text.push(c),
+                    None => return,
+                }
+            }
+        }
+    }
+}
+
+// Each `_` becomes a fresh `_G{n}` variable, and so does each variable named
+// like the ones the solver makes, `_{n}` or `_G{n}`, so that source variables
+// can't clash with those.
+fn rename_apart(mut term: Term, names: &mut HashMap<String, String>) -> Term {
+    match term {
+        Term::Variable { ref name } if name == "_" || is_internal_name(name) => {
+            let fresh = || format!("_G{}", ANONYMOUS_VARIABLES.fetch_add(1, Ordering::Relaxed));
+            let name = match name.as_str() {
+                "_" => fresh(),
+                _ => names.entry(name.clone()).or_insert_with(fresh).clone(),
+            };
+            Term::variable(name)
+        }
+        Term::Functor {
+            ref name,
+            ref mut arguments,
+        } => Term::functor(
+            name,
+            std::mem::take(arguments)
+                .into_iter()
+                .map(|argument| rename_apart(argument, names)),
+        ),
+        term => term,
     }
+}
+
+fn is_internal_name(name: &str) -> bool {
+    let number = name.strip_prefix('_').unwrap_or_default();
+    let number = number.strip_prefix('G').unwrap_or(number);
+    !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+}
 
-    fn build_query(pair: Pair<Rule>) -> Term {
-        Self::build_term(pair.into_inner().next().unwrap())
+// Whether a quote after `text` starts a `0'c` character code.
+fn is_character_code(text: &str) -> bool {
+    match text.strip_suffix('0') {
+        Some(before) => !before.ends_with(|c: char| c.is_alphanumeric() || c == '_'),
+        None => false,
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::database::{Database, USER};
 
     #[test]
     fn parse_variable() {
@@ -170,4 +451,207 @@ mod test {
         let expected_query = Term::functor("brother", [Term::atom("john"), Term::variable("X")]);
         assert_eq!(expected_query, query);
     }
+
+    #[test]
+    fn parse_numbers_strings_and_quoted_atoms() {
+        let code = "test(42, -7, \"some text\", 'Quoted atom', 0'a)";
+
+        let term = MinplParser::parse_term(code);
+
+        assert_eq!(
+            term,
+            Term::functor(
+                "test",
+                [
+                    Term::number(42),
+                    Term::number(-7),
+                    Term::string("some text"),
+                    Term::atom("Quoted atom"),
+                    Term::number(97),
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn parse_lists() {
+        let code = "test([], [a, b], [H|T])";
+
+        let term = MinplParser::parse_term(code);
+
+        assert_eq!(
+            term,
+            Term::functor(
+                "test",
+                [
+                    Term::list([]),
+                    Term::list([Term::atom("a"), Term::atom("b")]),
+                    Term::list_with_tail([Term::variable("H")], Term::variable("T")),
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn parse_infix_operators_by_priority() {
+        let code = "X == a - b - c, Y @< Z";
+
+        let term = MinplParser::parse_term(code);
+
+        assert_eq!(
+            term,
+            Term::functor(
+                ",",
+                [
+                    Term::functor(
+                        "==",
+                        [
+                            Term::variable("X"),
+                            Term::functor(
+                                "-",
+                                [
+                                    Term::functor("-", [Term::atom("a"), Term::atom("b")]),
+                                    Term::atom("c"),
+                                ]
+                            ),
+                        ]
+                    ),
+                    Term::functor("@<", [Term::variable("Y"), Term::variable("Z")]),
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn parse_prefix_operators() {
+        let code = "\\+ a = b";
+
+        let term = MinplParser::parse_term(code);
+
+        assert_eq!(
+            term,
+            Term::functor(
+                "\\+",
+                [Term::functor("=", [Term::atom("a"), Term::atom("b")])]
+            )
+        );
+    }
+
+    #[test]
+    fn reject_operator_priority_clash() {
+        let code = "X = a = b.";
+
+        assert!(MinplParser::parse_query(code).is_err());
+    }
+
+    #[test]
+    fn parse_rule_with_comments() {
+        let code = "% a fact\nfather(peter, john). /* a rule */ parent(X, Y) :- father(X, Y).";
+
+        let database = MinplParser::parse_database(code).unwrap();
+
+        let expected_database = Database::empty()
+            .with_fact(Term::functor(
+                "father",
+                [Term::atom("peter"), Term::atom("john")],
+            ))
+            .with_rule(
+                Term::functor("parent", [Term::variable("X"), Term::variable("Y")]),
+                [Term::functor(
+                    "father",
+                    [Term::variable("X"), Term::variable("Y")],
+                )],
+            );
+        assert_eq!(expected_database, database);
+    }
+
+    #[test]
+    fn parse_dynamic_directive() {
+        let code = ":- dynamic counter/1, (seen/2, [log/0]).";
+
+        let database = MinplParser::parse_database(code).unwrap();
+
+        assert!(database.is_dynamic(USER, "counter", 1));
+        assert!(database.is_dynamic(USER, "seen", 2));
+        assert!(database.is_dynamic(USER, "log", 0));
+    }
+
+    #[test]
+    fn skip_clause_with_number_head() {
+        let code = "1 :- true. f.";
+
+        let database = MinplParser::parse_database(code).unwrap();
+
+        assert!(database.is_defined(USER, "f", 0));
+    }
+
+    #[test]
+    fn parse_program_with_directives() {
+        let code = ":- initialization(main).\n\nmain :- true.\n:- dynamic(f/1).";
+
+        let sentences = MinplParser::parse_program(code).unwrap();
+
+        assert_eq!(
+            sentences,
+            vec![
+                Sentence::Directive {
+                    goal: Term::functor("initialization", [Term::atom("main")]),
+                    line: 1
+                },
+                Sentence::Clause {
+                    clause: Term::functor(":-", [Term::atom("main"), Term::atom("true")]),
+                    line: 3
+                },
+                Sentence::Directive {
+                    goal: Term::functor(
+                        "dynamic",
+                        [Term::functor("/", [Term::atom("f"), Term::number(1)])]
+                    ),
+                    line: 4
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rename_variables_named_like_internal_ones() {
+        let term = MinplParser::parse_term("f(_1, _G0, _1, _, _, _X)");
+
+        let Term::Functor { arguments, .. } = &term else {
+            panic!("expected a compound, got {term}");
+        };
+        assert_ne!(arguments[0], Term::variable("_1"));
+        assert_ne!(arguments[1], Term::variable("_G0"));
+        assert_ne!(arguments[0], arguments[1]);
+        assert_eq!(arguments[0], arguments[2]);
+        assert_ne!(arguments[3], arguments[4]);
+        assert_eq!(arguments[5], Term::variable("_X"));
+    }
+
+    fn sentences(text: &str) -> Vec<String> {
+        let mut chars = text.chars().peekable();
+        std::iter::from_fn(|| MinplParser::read_sentence(&mut chars)).collect()
+    }
+
+    #[test]
+    fn read_sentences_one_at_a_time() {
+        assert_eq!(
+            sentences("f(a).\ng(X, '.') :- X =.. [h]. % done\n  "),
+            vec!["f(a).", "g(X, '.') :- X =.. [h]."]
+        );
+    }
+
+    #[test]
+    fn read_sentence_skips_quotes_codes_and_comments() {
+        assert_eq!(
+            sentences("a(\"x. y\", 0'., 0''', 'it''s. ') /* a. b */ . b."),
+            vec!["a(\"x. y\", 0'., 0''', 'it''s. ')   .", "b."]
+        );
+    }
+
+    #[test]
+    fn read_sentence_without_end() {
+        assert_eq!(sentences("f(a)"), vec!["f(a)"]);
+        assert_eq!(sentences("  % nothing\n"), Vec::<String>::new());
+    }
 }
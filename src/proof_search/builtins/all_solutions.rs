@@ -0,0 +1,328 @@
+use super::Solver;
+use crate::{exception::Exception, term::Term};
+use std::collections::HashSet;
+
+pub fn findall(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let solutions = solver.solve_all(args[1].clone(), &args[0])?;
+    Ok(solver.unify(&args[2], &Term::list(solutions)))
+}
+
+pub fn findall_with_tail(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let solutions = solver.solve_all(args[1].clone(), &args[0])?;
+    Ok(solver.unify(&args[2], &Term::list_with_tail(solutions, args[3].clone())))
+}
+
+pub fn bagof(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    collect_groups(solver, args, false)
+}
+
+pub fn setof(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    collect_groups(solver, args, true)
+}
+
+pub fn existential(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    solver.push_goal(args[1].clone());
+    Ok(true)
+}
+
+pub fn aggregate_all(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let specification = solver.resolve(&args[0]);
+    let result = match &specification {
+        Term::Variable { .. } => return Err(Exception::instantiation_error()),
+        Term::Functor { name, arguments } => match (name.as_str(), arguments.as_slice()) {
+            ("count", []) => {
+                let solutions = solver.solve_all(args[1].clone(), &Term::atom("x"))?;
+                Term::number(solutions.len() as i64)
+            }
+            ("sum", [expression]) => {
+                let solutions = solver.solve_all(args[1].clone(), expression)?;
+                let mut sum: i64 = 0;
+                for solution in solutions {
+                    sum = sum
+                        .checked_add(number(solution)?)
+                        .ok_or_else(|| Exception::evaluation_error("int_overflow"))?;
+                }
+                Term::number(sum)
+            }
+            ("max", [expression]) => match solver
+                .solve_all(args[1].clone(), expression)?
+                .into_iter()
+                .max()
+            {
+                Some(max) => max,
+                None => return Ok(false),
+            },
+            ("min", [expression]) => match solver
+                .solve_all(args[1].clone(), expression)?
+                .into_iter()
+                .min()
+            {
+                Some(min) => min,
+                None => return Ok(false),
+            },
+            ("max", [expression, witness]) | ("min", [expression, witness]) => {
+                let template = Term::functor(name, [expression.clone(), witness.clone()]);
+                let solutions = solver.solve_all(args[1].clone(), &template)?.into_iter();
+                let key = |solution: &Term| solution.arguments()[0].clone();
+                let best = if name == "max" {
+                    solutions.rev().max_by_key(key)
+                } else {
+                    solutions.min_by_key(key)
+                };
+                match best {
+                    Some(best) => best,
+                    None => return Ok(false),
+                }
+            }
+            ("bag", [template]) => Term::list(solver.solve_all(args[1].clone(), template)?),
+            ("set", [template]) => {
+                let mut solutions = solver.solve_all(args[1].clone(), template)?;
+                solutions.sort();
+                solutions.dedup();
+                Term::list(solutions)
+            }
+            _ => return Err(Exception::domain_error("aggregate_spec", specification)),
+        },
+        _ => return Err(Exception::domain_error("aggregate_spec", specification)),
+    };
+    Ok(solver.unify(&args[2], &result))
+}
+
+fn collect_groups(solver: &mut Solver, args: &[Term], sorted: bool) -> Result<bool, Exception> {
+    let template = solver.resolve(&args[0]);
+    let mut goal = solver.resolve(&args[1]);
+    let mut bound: HashSet<_> = template.variables().into_iter().collect();
+    while let Term::Functor {
+        ref name,
+        ref mut arguments,
+    } = goal
+    {
+        if name != "^" || arguments.len() != 2 {
+            break;
+        }
+        let inner = arguments.pop().unwrap();
+        bound.extend(arguments[0].variables());
+        goal = inner;
+    }
+    let witness = Term::list(
+        goal.variables()
+            .into_iter()
+            .filter(|variable| !bound.contains(variable))
+            .map(Term::variable),
+    );
+    let pair = Term::functor("-", [witness.clone(), template]);
+    let mut solutions = solver
+        .solve_all(goal, &pair)?
+        .into_iter()
+        .map(|mut solution| match solution {
+            Term::Functor {
+                ref mut arguments, ..
+            } => {
+                let template = arguments.pop().unwrap();
+                (arguments.pop().unwrap(), template)
+            }
+            _ => unreachable!(),
+        })
+        .collect::<Vec<_>>();
+    solutions.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut groups: Vec<(Vec<Term>, Vec<Term>)> = vec![];
+    for (instance, template) in solutions {
+        match groups.last_mut() {
+            Some((witnesses, templates)) if witnesses[0].is_variant(&instance) => {
+                witnesses.push(instance);
+                templates.push(template);
+            }
+            _ => groups.push((vec![instance], vec![template])),
+        }
+    }
+    let alternatives = groups
+        .into_iter()
+        .map(|(witnesses, mut templates)| {
+            if sorted {
+                templates.sort();
+                templates.dedup();
+            }
+            witnesses
+                .into_iter()
+                .map(|instance| Term::functor("=", [witness.clone(), instance]))
+                .chain([Term::functor("=", [args[2].clone(), Term::list(templates)])])
+                .reduce(|conjunction, goal| Term::functor(",", [conjunction, goal]))
+                .unwrap()
+        })
+        .collect();
+    Ok(solver.alternatives(alternatives))
+}
+
+fn number(term: Term) -> Result<i64, Exception> {
+    match term {
+        Term::Number { value } => Ok(value),
+        Term::Variable { .. } => Err(Exception::instantiation_error()),
+        term => Err(Exception::type_error("number", term)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::query;
+    use crate::{
+        assignments::Assignments, database::Database, exception::Exception, parser::MinplParser,
+        term::Term,
+    };
+
+    fn database() -> Database {
+        MinplParser::parse_database(
+            "member(X, [X|_]).
+            member(X, [_|T]) :- member(X, T).
+            class(a, peter). class(b, ann). class(a, pat). class(b, tom).
+            age(peter, 7). age(ann, 11). age(pat, 8). age(tom, 5).",
+        )
+        .unwrap()
+    }
+
+    fn atoms(names: &[&str]) -> Term {
+        Term::list(names.iter().map(Term::atom))
+    }
+
+    #[test]
+    fn findall_collects_solutions_in_order() {
+        let result = query(&database(), "findall(X, member(X, [c, a, b]), L).");
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with("L", atoms(&["c", "a", "b"]))]
+        );
+    }
+
+    #[test]
+    fn findall_with_tail() {
+        let result = query(&database(), "findall(X, member(X, [a]), L, [z]).");
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with("L", atoms(&["a", "z"]))]
+        );
+    }
+
+    #[test]
+    fn findall_does_not_bind_goal_variables() {
+        let result = query(&database(), "findall(N, class(C, N), _), var(C).");
+
+        assert_eq!(result.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn bagof_groups_by_free_variables() {
+        let result = query(&database(), "bagof(N, class(C, N), L).");
+
+        assert_eq!(
+            result.unwrap(),
+            vec![
+                Assignments::empty()
+                    .with("C", Term::atom("a"))
+                    .with("L", atoms(&["peter", "pat"])),
+                Assignments::empty()
+                    .with("C", Term::atom("b"))
+                    .with("L", atoms(&["ann", "tom"])),
+            ]
+        );
+    }
+
+    #[test]
+    fn bagof_with_existential_variable() {
+        let result = query(&database(), "bagof(N, C^class(C, N), L).");
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with("L", atoms(&["peter", "ann", "pat", "tom"]))]
+        );
+    }
+
+    #[test]
+    fn bagof_fails_without_solutions() {
+        let result = query(&database(), "bagof(X, member(X, []), L).");
+
+        assert_eq!(result.unwrap(), vec![]);
+    }
+
+    #[test]
+    fn setof_sorts_each_group() {
+        let result = query(&database(), "setof(N, class(b, N), L).");
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with("L", atoms(&["ann", "tom"]))]
+        );
+    }
+
+    #[test]
+    fn setof_with_conjunction() {
+        let result = query(
+            &database(),
+            "setof(A-N, C^(class(C, N), age(N, A)), [First|_]).",
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with(
+                "First",
+                Term::functor("-", [Term::number(5), Term::atom("tom")])
+            )]
+        );
+    }
+
+    #[test]
+    fn aggregate_all_count_sum_max_and_min() {
+        let result = query(
+            &database(),
+            "aggregate_all(count, age(_, _), C),
+            aggregate_all(sum(A), age(_, A), S),
+            aggregate_all(max(A), age(_, A), Max),
+            aggregate_all(min(A, N), age(N, A), Min).",
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty()
+                .with("C", Term::number(4))
+                .with("S", Term::number(31))
+                .with("Max", Term::number(11))
+                .with(
+                    "Min",
+                    Term::functor("min", [Term::number(5), Term::atom("tom")])
+                )]
+        );
+    }
+
+    #[test]
+    fn aggregate_all_bag_and_set() {
+        let result = query(
+            &database(),
+            "aggregate_all(bag(C), class(C, _), B), aggregate_all(set(C), class(C, _), S).",
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty()
+                .with("B", atoms(&["a", "b", "a", "b"]))
+                .with("S", atoms(&["a", "b"]))]
+        );
+    }
+
+    #[test]
+    fn aggregate_all_max_fails_without_solutions() {
+        let result = query(&database(), "aggregate_all(max(X), member(X, []), M).");
+
+        assert_eq!(result.unwrap(), vec![]);
+    }
+
+    #[test]
+    fn aggregate_all_sum_of_non_numbers_is_a_type_error() {
+        let result = query(&database(), "aggregate_all(sum(N), class(_, N), S).");
+
+        assert_eq!(
+            result,
+            Err(Exception::type_error("number", Term::atom("peter")))
+        );
+    }
+}
@@ -0,0 +1,124 @@
+use super::Solver;
+use crate::{exception::Exception, term::Term};
+
+pub fn put_attr(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let module = attribute_module(solver, &args[1])?;
+    match solver.dereference(&args[0]) {
+        Term::Variable { name } => {
+            let name = name.clone();
+            solver.set_attribute(&name, &module, Some(args[2].clone()));
+            Ok(true)
+        }
+        term => Err(Exception::uninstantiation_error(term.clone())),
+    }
+}
+
+pub fn get_attr(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let module = attribute_module(solver, &args[1])?;
+    let value = match solver.dereference(&args[0]) {
+        Term::Variable { name } => solver.attribute(name, &module).cloned(),
+        _ => None,
+    };
+    match value {
+        Some(value) => Ok(solver.unify(&args[2], &value)),
+        None => Ok(false),
+    }
+}
+
+pub fn del_attr(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let module = attribute_module(solver, &args[1])?;
+    if let Term::Variable { name } = solver.dereference(&args[0]) {
+        let name = name.clone();
+        solver.set_attribute(&name, &module, None);
+    }
+    Ok(true)
+}
+
+fn attribute_module(solver: &Solver, module: &Term) -> Result<String, Exception> {
+    match solver.dereference(module) {
+        Term::Functor { name, arguments } if arguments.is_empty() => Ok(name.clone()),
+        Term::Variable { .. } => Err(Exception::instantiation_error()),
+        module => Err(Exception::type_error("atom", module.clone())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::query;
+    use crate::{
+        assignments::Assignments, consult::consult_str, database::Database, exception::Exception,
+        term::Term,
+    };
+
+    fn database() -> Database {
+        let mut database = Database::empty();
+        consult_str(
+            &mut database,
+            ":- module(m, []).
+            attr_unify_hook(Value, Other) :- Value == Other.",
+        )
+        .unwrap();
+        database
+    }
+
+    #[test]
+    fn get_attr_returns_the_value_put() {
+        let result = query(&database(), "put_attr(X, m, 1), get_attr(X, m, V).");
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with("V", Term::number(1))]
+        );
+    }
+
+    #[test]
+    fn attributes_are_undone_on_backtracking() {
+        let result = query(
+            &database(),
+            "(put_attr(X, m, 1), fail ; get_attr(X, m, V)).",
+        );
+
+        assert_eq!(result.unwrap(), vec![]);
+    }
+
+    #[test]
+    fn del_attr_removes_the_attribute() {
+        let result = query(
+            &database(),
+            "put_attr(X, m, 1), del_attr(X, m), get_attr(X, m, V).",
+        );
+
+        assert_eq!(result.unwrap(), vec![]);
+    }
+
+    #[test]
+    fn binding_runs_the_unify_hook() {
+        let database = database();
+
+        assert_eq!(
+            query(&database, "put_attr(X, m, a), X = a.").unwrap().len(),
+            1
+        );
+        assert_eq!(
+            query(&database, "put_attr(X, m, a), X = b.").unwrap(),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn plain_variables_take_the_attributes() {
+        let result = query(&database(), "put_attr(X, m, a), X = Y, get_attr(Y, m, V).");
+
+        assert_eq!(result.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn put_attr_on_bound_term_is_an_error() {
+        let result = query(&database(), "put_attr(a, m, 1).");
+
+        assert_eq!(
+            result,
+            Err(Exception::uninstantiation_error(Term::atom("a")))
+        );
+    }
+}
@@ -0,0 +1,214 @@
+use crate::{exception::Exception, term::Term};
+
+pub const INF: i64 = i64::MIN;
+pub const SUP: i64 = i64::MAX;
+
+// A set of integers as sorted, disjoint and non adjacent intervals. `INF` and
+// `SUP` stand for the infinite bounds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Domain {
+    intervals: Vec<(i64, i64)>,
+}
+
+impl Domain {
+    pub fn full() -> Self {
+        Self::interval(INF, SUP)
+    }
+
+    pub fn interval(min: i64, max: i64) -> Self {
+        let intervals = if min <= max { vec![(min, max)] } else { vec![] };
+        Self { intervals }
+    }
+
+    pub fn singleton(value: i64) -> Self {
+        Self::interval(value, value)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    pub fn min(&self) -> i64 {
+        self.intervals.first().map_or(SUP, |interval| interval.0)
+    }
+
+    pub fn max(&self) -> i64 {
+        self.intervals.last().map_or(INF, |interval| interval.1)
+    }
+
+    pub fn is_finite(&self) -> bool {
+        !self.is_empty() && self.min() != INF && self.max() != SUP
+    }
+
+    pub fn size(&self) -> Option<u64> {
+        self.is_finite().then(|| {
+            self.intervals
+                .iter()
+                .map(|(min, max)| (*max as i128 - *min as i128 + 1) as u64)
+                .sum()
+        })
+    }
+
+    pub fn value(&self) -> Option<i64> {
+        match self.intervals.as_slice() {
+            [(min, max)] if min == max => Some(*min),
+            _ => None,
+        }
+    }
+
+    pub fn contains(&self, value: i64) -> bool {
+        self.intervals
+            .iter()
+            .any(|(min, max)| *min <= value && value <= *max)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = i64> + '_ {
+        self.intervals.iter().flat_map(|(min, max)| *min..=*max)
+    }
+
+    pub fn intersect(&self, other: &Domain) -> Domain {
+        let mut intervals = vec![];
+        let (mut i, mut j) = (0, 0);
+        while i < self.intervals.len() && j < other.intervals.len() {
+            let (a_min, a_max) = self.intervals[i];
+            let (b_min, b_max) = other.intervals[j];
+            let (min, max) = (a_min.max(b_min), a_max.min(b_max));
+            if min <= max {
+                intervals.push((min, max));
+            }
+            if a_max < b_max {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        Self { intervals }
+    }
+
+    pub fn union(&self, other: &Domain) -> Domain {
+        let mut all: Vec<_> = self.intervals.iter().chain(&other.intervals).collect();
+        all.sort();
+        let mut intervals: Vec<(i64, i64)> = vec![];
+        for &(min, max) in all {
+            match intervals.last_mut() {
+                Some(last) if min as i128 <= last.1 as i128 + 1 => last.1 = last.1.max(max),
+                _ => intervals.push((min, max)),
+            }
+        }
+        Self { intervals }
+    }
+
+    pub fn remove(&self, value: i64) -> Domain {
+        let mut intervals = vec![];
+        for &(min, max) in &self.intervals {
+            if value < min || max < value {
+                intervals.push((min, max));
+                continue;
+            }
+            if min < value {
+                intervals.push((min, value - 1));
+            }
+            if value < max {
+                intervals.push((value + 1, max));
+            }
+        }
+        Self { intervals }
+    }
+
+    // Parses `Min..Max`, `D1 \/ D2` and integers, with `inf` and `sup` as bounds.
+    pub fn from_term(term: &Term) -> Result<Domain, Exception> {
+        match term {
+            Term::Number { value } => Ok(Self::singleton(*value)),
+            Term::Functor { name, arguments } if arguments.len() == 2 && name == ".." => {
+                let min = bound(&arguments[0], INF, "inf")?;
+                let max = bound(&arguments[1], SUP, "sup")?;
+                Ok(Self::interval(min, max))
+            }
+            Term::Functor { name, arguments } if arguments.len() == 2 && name == "\\/" => {
+                Ok(Self::from_term(&arguments[0])?.union(&Self::from_term(&arguments[1])?))
+            }
+            Term::Variable { .. } => Err(Exception::instantiation_error()),
+            term => Err(Exception::type_error("clpfd_domain", term.clone())),
+        }
+    }
+
+    pub fn to_term(&self) -> Term {
+        let mut intervals = self.intervals.iter().map(|&(min, max)| match (min, max) {
+            (min, max) if min == max => Term::number(min),
+            (min, max) => {
+                let min = match min {
+                    INF => Term::atom("inf"),
+                    min => Term::number(min),
+                };
+                let max = match max {
+                    SUP => Term::atom("sup"),
+                    max => Term::number(max),
+                };
+                Term::functor("..", [min, max])
+            }
+        });
+        let first = intervals
+            .next()
+            .unwrap_or_else(|| Term::functor("..", [Term::number(1), Term::number(0)]));
+        intervals.fold(first, |domain, interval| {
+            Term::functor("\\/", [domain, interval])
+        })
+    }
+}
+
+fn bound(term: &Term, infinite: i64, name: &str) -> Result<i64, Exception> {
+    match term {
+        Term::Number { value } => Ok(*value),
+        Term::Functor {
+            name: atom,
+            arguments,
+        } if arguments.is_empty() && atom == name => Ok(infinite),
+        Term::Variable { .. } => Err(Exception::instantiation_error()),
+        term => Err(Exception::type_error("integer", term.clone())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::MinplParser;
+    use test_case::test_case;
+
+    fn term(code: &str) -> Term {
+        MinplParser::parse_query(&format!("{}.", code)).unwrap()
+    }
+
+    fn domain(code: &str) -> Domain {
+        Domain::from_term(&term(code)).unwrap()
+    }
+
+    #[test_case("1..5", "3..9", "3..5")]
+    #[test_case("1..5 \\/ 8..9", "4..8", "4..5 \\/ 8")]
+    #[test_case("inf..sup", "1..2", "1..2")]
+    #[test_case("1..2", "5..6", "1..0")]
+    fn intersection(a: &str, b: &str, expected: &str) {
+        assert_eq!(domain(a).intersect(&domain(b)), domain(expected));
+    }
+
+    #[test_case("1..3", "4..5", "1..5")]
+    #[test_case("1..3", "7", "1..3 \\/ 7")]
+    #[test_case("1..8", "2..3", "1..8")]
+    fn union(a: &str, b: &str, expected: &str) {
+        assert_eq!(domain(a).union(&domain(b)), domain(expected));
+    }
+
+    #[test]
+    fn remove_splits_intervals() {
+        let domain = domain("1..5").remove(3);
+
+        assert_eq!(domain.to_term(), term("1..2 \\/ 4..5"));
+        assert_eq!(domain.size(), Some(4));
+        assert_eq!(domain.values().collect::<Vec<_>>(), vec![1, 2, 4, 5]);
+    }
+
+    #[test]
+    fn infinite_domains_have_no_size() {
+        assert_eq!(domain("0..sup").size(), None);
+        assert_eq!(domain("inf..sup").to_term(), term("inf..sup"));
+    }
+}
@@ -0,0 +1,597 @@
+mod domain;
+mod propagation;
+
+use super::Solver;
+use crate::{exception::Exception, term::Term};
+use domain::{Domain, INF, SUP};
+use propagation::{
+    domain, integer, linear_terms, partial_sum, propagators, scaled, set_attribute, Propagation,
+};
+
+// The attribute of a constrained variable is `clpfd(Domain, Propagators)`.
+const MODULE: &str = "clpfd";
+
+// Posts the constraints of `library(clpfd)`: `in/2`, `ins/2`, the arithmetic
+// comparisons and `all_different/1`, `all_distinct/1`.
+pub fn post(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let constraint = solver.resolve(&args[0]);
+    match (constraint.name(), constraint.arguments()) {
+        (Some("in"), [variable, domain]) => {
+            let domain = Domain::from_term(domain)?;
+            constrain(solver, std::slice::from_ref(variable), &domain)
+        }
+        (Some("ins"), [variables, domain]) => {
+            let domain = Domain::from_term(domain)?;
+            constrain(solver, &variables.list_elements()?, &domain)
+        }
+        (Some("all_different" | "all_distinct"), [list]) => {
+            for element in list.list_elements()? {
+                check_integer(&element)?;
+            }
+            attach(solver, vec![constraint.clone()])
+        }
+        (Some(relation), [left, right]) if relation.starts_with('#') => {
+            post_comparison(solver, relation, left, right)
+        }
+        _ => Err(Exception::domain_error(
+            "clpfd_constraint",
+            constraint.clone(),
+        )),
+    }
+}
+
+// `attr_unify_hook/2`: checks the value against the domain, or merges the
+// constraints of two variables, and propagates again.
+pub fn unify_hook(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let attribute = solver.resolve(&args[0]);
+    let (domain, mut constraints) = match attribute.arguments() {
+        [domain, constraints] => (Domain::from_term(domain)?, constraints.list_elements()?),
+        _ => return Err(Exception::type_error("clpfd_attribute", attribute.clone())),
+    };
+    match solver.dereference(&args[1]).clone() {
+        Term::Number { value } if domain.contains(value) => {
+            Propagation::new(solver).run(constraints)
+        }
+        Term::Number { .. } => Ok(false),
+        Term::Variable { ref name } => {
+            let other = Term::variable(name);
+            let merged = propagation::domain(solver, &other)?.intersect(&domain);
+            if merged.is_empty() {
+                return Ok(false);
+            }
+            for constraint in propagators(solver, name) {
+                if !constraints.contains(&constraint) {
+                    constraints.push(constraint);
+                }
+            }
+            set_attribute(solver, name, &merged, constraints.clone());
+            if let Some(value) = merged.value() {
+                if !solver.unify(&other, &Term::number(value)) {
+                    return Ok(false);
+                }
+            }
+            Propagation::new(solver).run(constraints)
+        }
+        other => Err(Exception::type_error("integer", other)),
+    }
+}
+
+// Checks the options and variables of `labeling/2`, giving the variable
+// selection (`leftmost`, `ff`, `ffc`, `min` or `max`), value order (`up` or
+// `down`), branching strategy (`step`, `enum` or `bisect`) and the list of
+// `min(Expr)` and `max(Expr)` options, whose values order the solutions.
+pub fn labeling_options(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    // Variable selection, value order and branching, with their defaults first.
+    let groups: [&[&str]; 3] = [
+        &["leftmost", "ff", "ffc", "min", "max"],
+        &["up", "down"],
+        &["step", "enum", "bisect"],
+    ];
+    let mut settings = groups.map(|group| group[0]);
+    let mut objectives = vec![];
+    for option in solver.resolve(&args[0]).list_elements()? {
+        if let Term::Variable { .. } = option {
+            return Err(Exception::instantiation_error());
+        }
+        if let (Some("min" | "max"), [_]) = (option.name(), option.arguments()) {
+            objectives.push(option);
+            continue;
+        }
+        let setting = groups.iter().enumerate().find_map(|(index, group)| {
+            let name = group.iter().find(|name| Term::atom(name) == option)?;
+            Some((index, *name))
+        });
+        match setting {
+            Some((index, name)) => settings[index] = name,
+            None => return Err(Exception::domain_error("labeling_option", option)),
+        }
+    }
+    for variable in solver.resolve(&args[1]).list_elements()? {
+        if !domain(solver, &variable)?.is_finite() {
+            return Err(Exception::instantiation_error());
+        }
+    }
+    Ok(args[2..5]
+        .iter()
+        .zip(settings)
+        .all(|(argument, setting)| solver.unify(argument, &Term::atom(setting)))
+        && solver.unify(&args[5], &Term::list(objectives)))
+}
+
+// Picks the next variable to label, failing when all of them are bound.
+pub fn select(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let mut candidates = vec![];
+    for variable in solver.resolve(&args[0]).list_elements()? {
+        if let Term::Variable { name } = &variable {
+            let constraints = propagators(solver, name).len();
+            candidates.push((variable.clone(), domain(solver, &variable)?, constraints));
+        }
+    }
+    let key = |(_, domain, constraints): &(Term, Domain, usize)| -> (i128, i128) {
+        let size = domain.size().unwrap_or(u64::MAX) as i128;
+        match solver.dereference(&args[1]).name() {
+            Some("ff") => (size, 0),
+            Some("ffc") => (size, -(*constraints as i128)),
+            Some("min") => (domain.min() as i128, 0),
+            Some("max") => (-(domain.max() as i128), 0),
+            _ => (0, 0),
+        }
+    };
+    let selected = candidates
+        .iter()
+        .enumerate()
+        .min_by_key(|(index, candidate)| (key(candidate), *index))
+        .map(|(_, (variable, _, _))| variable.clone());
+    match selected {
+        Some(variable) => Ok(solver.unify(&args[2], &variable)),
+        None => Ok(false),
+    }
+}
+
+// The smallest value in the domain, or the largest one for `down`.
+pub fn bound(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let domain = domain(solver, &args[0])?;
+    let value = match solver.dereference(&args[1]).name() {
+        Some("down") => domain.max(),
+        _ => domain.min(),
+    };
+    Ok(solver.unify(&args[2], &Term::number(value)))
+}
+
+pub fn values(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let domain = domain(solver, &args[0])?;
+    if !domain.is_finite() {
+        return Err(Exception::instantiation_error());
+    }
+    let mut values: Vec<_> = domain.values().map(Term::number).collect();
+    if solver.dereference(&args[1]).name() == Some("down") {
+        values.reverse();
+    }
+    Ok(solver.unify(&args[2], &Term::list(values)))
+}
+
+pub fn middle(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let domain = domain(solver, &args[0])?;
+    let middle = (domain.min() as i128 + domain.max() as i128).div_euclid(2);
+    Ok(solver.unify(&args[1], &Term::number(middle as i64)))
+}
+
+// The constraints on a variable that are not yet entailed, for the residual
+// goals of an answer.
+pub fn residual(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let variable = solver.resolve(&args[0]);
+    let name = match &variable {
+        Term::Variable { name } => name.clone(),
+        _ => return Ok(solver.unify(&args[1], &Term::atom("[]"))),
+    };
+    let mut goals = vec![];
+    let domain = domain(solver, &variable)?;
+    if domain != Domain::full() {
+        goals.push(Term::functor("in", [variable.clone(), domain.to_term()]));
+    }
+    for constraint in propagators(solver, &name) {
+        if let Some(goal) = residual_goal(solver, &solver.resolve(&constraint))? {
+            goals.push(goal);
+        }
+    }
+    Ok(solver.unify(&args[1], &Term::list(goals)))
+}
+
+fn constrain(solver: &mut Solver, variables: &[Term], domain: &Domain) -> Result<bool, Exception> {
+    let mut propagation = Propagation::new(solver);
+    for variable in variables {
+        check_integer(variable)?;
+        if !propagation.narrow(variable, domain)? {
+            return Ok(false);
+        }
+    }
+    propagation.run(vec![])
+}
+
+fn post_comparison(
+    solver: &mut Solver,
+    relation: &str,
+    left: &Term,
+    right: &Term,
+) -> Result<bool, Exception> {
+    let mut expression = Expression::default();
+    let (sign, relation, offset) = match relation {
+        "#=" => (1, "=", 0),
+        "#\\=" => (1, "\\=", 0),
+        "#=<" => (1, "=<", 0),
+        "#<" => (1, "=<", 1),
+        "#>=" => (-1, "=<", 0),
+        "#>" => (-1, "=<", 1),
+        _ => {
+            let constraint = Term::functor(relation, [left.clone(), right.clone()]);
+            return Err(Exception::domain_error("clpfd_constraint", constraint));
+        }
+    };
+    expression.add(solver, left, sign)?;
+    expression.add(solver, right, -sign)?;
+    expression.constant = checked(expression.constant.checked_add(offset))?;
+    let mut constraints = expression.auxiliary;
+    constraints.push(linear(expression.terms, relation, expression.constant));
+    attach(solver, constraints)
+}
+
+// Adds the constraints to the attributes of their variables and propagates.
+fn attach(solver: &mut Solver, constraints: Vec<Term>) -> Result<bool, Exception> {
+    for constraint in &constraints {
+        for name in constraint.variables() {
+            let variable = Term::variable(&name);
+            let domain = domain(solver, &variable)?;
+            let mut propagators = propagators(solver, &name);
+            propagators.push(constraint.clone());
+            set_attribute(solver, &name, &domain, propagators);
+        }
+    }
+    Propagation::new(solver).run(constraints)
+}
+
+fn linear(terms: Vec<(i64, Term)>, relation: &str, constant: i64) -> Term {
+    let terms = terms
+        .into_iter()
+        .map(|(coefficient, variable)| Term::functor("*", [Term::number(coefficient), variable]));
+    Term::functor(
+        "linear",
+        [
+            Term::list(terms),
+            Term::atom(relation),
+            Term::number(constant),
+        ],
+    )
+}
+
+// An arithmetic expression as `Sum(Coefficient * Variable) + Constant`, with
+// products of variables replaced by auxiliary variables.
+#[derive(Default)]
+struct Expression {
+    terms: Vec<(i64, Term)>,
+    constant: i64,
+    auxiliary: Vec<Term>,
+}
+
+impl Expression {
+    fn add(&mut self, solver: &mut Solver, term: &Term, coefficient: i64) -> Result<(), Exception> {
+        let term = solver.resolve(term);
+        match (&term, term.name(), term.arguments()) {
+            (Term::Number { value }, _, _) => {
+                let value = checked(value.checked_mul(coefficient))?;
+                self.constant = checked(self.constant.checked_add(value))?;
+            }
+            (Term::Variable { .. }, _, _) => self.add_variable(coefficient, term.clone()),
+            (_, Some("+"), [a, b]) => {
+                self.add(solver, a, coefficient)?;
+                self.add(solver, b, coefficient)?;
+            }
+            (_, Some("-"), [a, b]) => {
+                self.add(solver, a, coefficient)?;
+                self.add(solver, b, checked(coefficient.checked_neg())?)?;
+            }
+            (_, Some("-"), [a]) => self.add(solver, a, checked(coefficient.checked_neg())?)?,
+            (_, Some("*"), [a, b]) => match (evaluate(a), evaluate(b)) {
+                (Some(factor), _) => {
+                    self.add(solver, b, checked(coefficient.checked_mul(factor))?)?
+                }
+                (_, Some(factor)) => {
+                    self.add(solver, a, checked(coefficient.checked_mul(factor))?)?
+                }
+                _ => {
+                    let x = self.variable(solver, a)?;
+                    let y = self.variable(solver, b)?;
+                    let product = solver.fresh_variable();
+                    self.auxiliary
+                        .push(Term::functor("times", [x, y, product.clone()]));
+                    self.add_variable(coefficient, product);
+                }
+            },
+            (_, Some(name), arguments) => {
+                let indicator = Term::functor(
+                    "/",
+                    [Term::atom(name), Term::number(arguments.len() as i64)],
+                );
+                return Err(Exception::type_error("evaluable", indicator));
+            }
+            _ => return Err(Exception::type_error("evaluable", term.clone())),
+        }
+        Ok(())
+    }
+
+    fn add_variable(&mut self, coefficient: i64, variable: Term) {
+        match self.terms.iter_mut().find(|(_, other)| *other == variable) {
+            Some(term) => term.0 += coefficient,
+            None => self.terms.push((coefficient, variable)),
+        }
+        self.terms.retain(|(coefficient, _)| *coefficient != 0);
+    }
+
+    // A variable standing for a subexpression of a product.
+    fn variable(&mut self, solver: &mut Solver, term: &Term) -> Result<Term, Exception> {
+        if let Term::Variable { .. } = term {
+            return Ok(term.clone());
+        }
+        let mut expression = Expression::default();
+        expression.add(solver, term, 1)?;
+        let variable = solver.fresh_variable();
+        expression.add_variable(-1, variable.clone());
+        self.auxiliary.extend(expression.auxiliary);
+        self.auxiliary
+            .push(linear(expression.terms, "=", expression.constant));
+        Ok(variable)
+    }
+}
+
+// The value of a ground integer expression.
+fn evaluate(term: &Term) -> Option<i64> {
+    match (term, term.name(), term.arguments()) {
+        (Term::Number { value }, _, _) => Some(*value),
+        (_, Some("+"), [a, b]) => evaluate(a)?.checked_add(evaluate(b)?),
+        (_, Some("-"), [a, b]) => evaluate(a)?.checked_sub(evaluate(b)?),
+        (_, Some("*"), [a, b]) => evaluate(a)?.checked_mul(evaluate(b)?),
+        (_, Some("-"), [a]) => evaluate(a)?.checked_neg(),
+        _ => None,
+    }
+}
+
+fn checked(value: Option<i64>) -> Result<i64, Exception> {
+    value.ok_or_else(|| Exception::evaluation_error("int_overflow"))
+}
+
+fn check_integer(term: &Term) -> Result<(), Exception> {
+    match term {
+        Term::Number { .. } | Term::Variable { .. } => Ok(()),
+        term => Err(Exception::type_error("integer", term.clone())),
+    }
+}
+
+fn residual_goal(solver: &Solver, constraint: &Term) -> Result<Option<Term>, Exception> {
+    let unbound = constraint.variables();
+    if unbound.is_empty() {
+        return Ok(None);
+    }
+    match (constraint.name(), constraint.arguments()) {
+        (Some("linear"), [terms, relation, constant]) => {
+            let mut constant = integer(constant)? as i128;
+            let mut variables = vec![];
+            let (mut left, mut right) = (vec![], vec![]);
+            for (coefficient, term) in linear_terms(terms)? {
+                match &term {
+                    Term::Number { value } => constant += coefficient as i128 * *value as i128,
+                    term => {
+                        match coefficient > 0 {
+                            true => left.push(product(coefficient, term)),
+                            false => right.push(product(-coefficient, term)),
+                        }
+                        variables.push((coefficient, domain(solver, term)?));
+                    }
+                }
+            }
+            if entailed(relation.name(), &variables, constant) {
+                return Ok(None);
+            }
+            match constant {
+                constant if constant > 0 => left.push(Term::number(constant as i64)),
+                constant if constant < 0 => right.push(Term::number(-constant as i64)),
+                _ => {}
+            }
+            let operator = match relation.name() {
+                Some("=<") => "#=<",
+                Some("\\=") => "#\\=",
+                _ => "#=",
+            };
+            Ok(Some(Term::functor(operator, [sum(left), sum(right)])))
+        }
+        (Some("times"), [x, y, z]) => Ok(Some(Term::functor(
+            "#=",
+            [Term::functor("*", [x.clone(), y.clone()]), z.clone()],
+        ))),
+        _ => Ok(Some(constraint.clone())),
+    }
+}
+
+// Whether the bounds of `Sum + Constant` already satisfy the relation.
+fn entailed(relation: Option<&str>, variables: &[(i64, Domain)], constant: i128) -> bool {
+    let bounds: Vec<_> = variables
+        .iter()
+        .map(|(coefficient, domain)| scaled(*coefficient, domain))
+        .collect();
+    let low = match partial_sum(bounds.iter().map(|bound| bound.0)) {
+        (sum, 0) => Some(sum + constant),
+        _ => None,
+    };
+    let high = match partial_sum(bounds.iter().map(|bound| bound.1)) {
+        (sum, 0) => Some(sum + constant),
+        _ => None,
+    };
+    match (relation, variables) {
+        (Some("=<"), _) => high.is_some_and(|high| high <= 0),
+        (Some("\\="), [(coefficient, domain)]) => {
+            let coefficient = *coefficient as i128;
+            -constant % coefficient != 0
+                || !domain
+                    .contains((-constant / coefficient).clamp(INF as i128, SUP as i128) as i64)
+        }
+        (Some("\\="), _) => low.is_some_and(|low| low > 0) || high.is_some_and(|high| high < 0),
+        _ => false,
+    }
+}
+
+fn product(coefficient: i64, term: &Term) -> Term {
+    match coefficient {
+        1 => term.clone(),
+        coefficient => Term::functor("*", [Term::number(coefficient), term.clone()]),
+    }
+}
+
+fn sum(terms: Vec<Term>) -> Term {
+    terms
+        .into_iter()
+        .reduce(|sum, term| Term::functor("+", [sum, term]))
+        .unwrap_or_else(|| Term::number(0))
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::query;
+    use crate::{
+        assignments::Assignments, consult::consult_str, database::Database, exception::Exception,
+        parser::MinplParser, proof_search::solve, term::Term,
+    };
+    use test_case::test_case;
+
+    fn database() -> Database {
+        let mut database = Database::empty();
+        consult_str(&mut database, ":- use_module(library(clpfd)).").unwrap();
+        database
+    }
+
+    fn values(code: &str, variable: &str) -> Vec<Term> {
+        query(&database(), code)
+            .unwrap()
+            .into_iter()
+            .map(|answer| answer.get(variable).unwrap().clone())
+            .collect()
+    }
+
+    fn residual_goals(code: &str) -> Vec<Term> {
+        let mut database = database();
+        let mut solver = solve(&mut database, MinplParser::parse_query(code).unwrap());
+        solver.next().unwrap().unwrap();
+        solver.residual_goals().unwrap()
+    }
+
+    fn term(code: &str) -> Term {
+        MinplParser::parse_query(&format!("{}.", code)).unwrap()
+    }
+
+    #[test_case("X #= 3 + 4." => vec![Term::number(7)])]
+    #[test_case("2 * X + 1 #= 7." => vec![Term::number(3)])]
+    #[test_case("X in 1..3, X #\\= 2, label([X])." => vec![Term::number(1), Term::number(3)])]
+    #[test_case("X in 0..10, X #> 3, X #< 6, label([X])." => vec![Term::number(4), Term::number(5)])]
+    #[test_case("X in 1..3, X #>= 3." => vec![Term::number(3)])]
+    #[test_case("X * X #= 9, X in 0..10, label([X])." => vec![Term::number(3)])]
+    fn propagation(code: &str) -> Vec<Term> {
+        values(code, "X")
+    }
+
+    #[test_case("X in 1..3, X = 5.")]
+    #[test_case("[X, Y] ins 1..2, all_distinct([X, Y, Z]), Z in 1..2.")]
+    #[test_case("X in 1..3, Y in 5..6, X #= Y.")]
+    #[test_case("[X, Y] ins 0..1, all_different([X, Y]), X = Y.")]
+    fn inconsistent_constraints_fail(code: &str) {
+        assert_eq!(query(&database(), code).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn all_different_with_labeling() {
+        let result = query(
+            &database(),
+            "Vs = [X, Y, Z], Vs ins 1..3, all_different(Vs), X #< Y, Y #< Z.",
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty()
+                .with("Vs", Term::list([1, 2, 3].map(Term::number)))
+                .with("X", Term::number(1))
+                .with("Y", Term::number(2))
+                .with("Z", Term::number(3))]
+        );
+    }
+
+    #[test]
+    fn sum_and_labeling_options() {
+        assert_eq!(
+            values(
+                "Vs = [X, Y], Vs ins 0..3, sum(Vs, #=, 5), labeling([down], Vs).",
+                "X"
+            ),
+            vec![Term::number(3), Term::number(2)]
+        );
+        assert_eq!(
+            values("X in 1..4, labeling([bisect, down], [X]).", "X"),
+            [4, 3, 2, 1].map(Term::number).to_vec()
+        );
+        assert_eq!(
+            values(
+                "Vs = [X, Y], X in 1..5, Y in 1..2, labeling([ff], Vs), !.",
+                "Y"
+            ),
+            vec![Term::number(1)]
+        );
+    }
+
+    #[test]
+    fn labeling_orders_solutions_by_objectives() {
+        assert_eq!(
+            values(
+                "[X, Y] ins 1..3, X #\\= Y, labeling([max(X - Y)], [X, Y]).",
+                "X"
+            ),
+            [3, 2, 3, 1, 2, 1].map(Term::number).to_vec()
+        );
+        assert_eq!(
+            values(
+                "[X, Y] ins 1..2, labeling([min(X + Y), max(Y)], [X, Y]).",
+                "Y"
+            ),
+            [1, 2, 1, 2].map(Term::number).to_vec()
+        );
+    }
+
+    #[test]
+    fn labeling_errors() {
+        assert_eq!(
+            query(&database(), "X in 1..3, labeling([fast], [X])."),
+            Err(Exception::domain_error(
+                "labeling_option",
+                Term::atom("fast")
+            ))
+        );
+        assert_eq!(
+            query(&database(), "X #> 0, label([X])."),
+            Err(Exception::instantiation_error())
+        );
+    }
+
+    #[test]
+    fn non_linear_products_use_auxiliary_variables() {
+        let result = values("X * Y #= 6, [X, Y] ins 1..6, label([X, Y]).", "X");
+
+        assert_eq!(result, [1, 2, 3, 6].map(Term::number).to_vec());
+    }
+
+    #[test]
+    fn residual_constraints() {
+        assert_eq!(residual_goals("X #> 3."), vec![term("X in 4..sup")]);
+        assert_eq!(
+            residual_goals("X in 1..5, X #\\= 3."),
+            vec![term("X in 1..2 \\/ 4..5")]
+        );
+        assert_eq!(
+            residual_goals("X #= Y + 1, Y in 0..2."),
+            vec![term("X in 1..3"), term("X #= Y + 1"), term("Y in 0..2")]
+        );
+    }
+}
@@ -0,0 +1,324 @@
+use super::{
+    domain::{Domain, INF, SUP},
+    MODULE,
+};
+use crate::{exception::Exception, proof_search::Solver, term::Term};
+use std::collections::VecDeque;
+
+// Narrows domains until no propagator changes anything, then binds the
+// variables left with a single value.
+pub struct Propagation<'s, 'a> {
+    solver: &'s mut Solver<'a>,
+    changed: Vec<String>,
+}
+
+impl<'s, 'a> Propagation<'s, 'a> {
+    pub fn new(solver: &'s mut Solver<'a>) -> Self {
+        Self {
+            solver,
+            changed: vec![],
+        }
+    }
+
+    pub fn run(mut self, initial: Vec<Term>) -> Result<bool, Exception> {
+        let mut queue = VecDeque::from(initial);
+        let mut mark = 0;
+        loop {
+            for name in &self.changed[mark..] {
+                for propagator in propagators(self.solver, name) {
+                    if !queue.contains(&propagator) {
+                        queue.push_back(propagator);
+                    }
+                }
+            }
+            mark = self.changed.len();
+            match queue.pop_front() {
+                Some(propagator) if !self.propagate(&propagator)? => return Ok(false),
+                Some(_) => {}
+                None => break,
+            }
+        }
+        for name in std::mem::take(&mut self.changed) {
+            let variable = Term::variable(&name);
+            if let Some(value) = self.domain(&variable)?.value() {
+                if !self.solver.unify(&variable, &Term::number(value)) {
+                    return Ok(false);
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    pub fn domain(&self, term: &Term) -> Result<Domain, Exception> {
+        domain(self.solver, term)
+    }
+
+    // Intersects the domain of `term` with `domain`, failing when nothing is
+    // left.
+    pub fn narrow(&mut self, term: &Term, domain: &Domain) -> Result<bool, Exception> {
+        let current = self.domain(term)?;
+        let narrowed = current.intersect(domain);
+        if narrowed.is_empty() {
+            return Ok(false);
+        }
+        if let Term::Variable { name } = self.solver.dereference(term) {
+            if narrowed != current {
+                let name = name.clone();
+                let propagators = propagators(self.solver, &name);
+                set_attribute(self.solver, &name, &narrowed, propagators);
+                self.changed.push(name);
+            }
+        }
+        Ok(true)
+    }
+
+    fn restrict(
+        &mut self,
+        term: &Term,
+        min: Option<i128>,
+        max: Option<i128>,
+    ) -> Result<bool, Exception> {
+        let min = min.map_or(INF, |min| min.clamp(INF as i128, SUP as i128) as i64);
+        let max = max.map_or(SUP, |max| max.clamp(INF as i128, SUP as i128) as i64);
+        self.narrow(term, &Domain::interval(min, max))
+    }
+
+    fn propagate(&mut self, propagator: &Term) -> Result<bool, Exception> {
+        let propagator = self.solver.resolve(propagator);
+        let arguments = propagator.arguments();
+        match (propagator.name(), arguments) {
+            (Some("linear"), [terms, relation, constant]) => {
+                let terms = linear_terms(terms)?;
+                let constant = integer(constant)?;
+                match relation.name() {
+                    Some("\\=") => self.not_equal(&terms, constant),
+                    Some(relation) => self.linear(&terms, relation == "=", constant),
+                    None => Err(Exception::type_error("atom", relation.clone())),
+                }
+            }
+            (Some("times"), [x, y, z]) => self.times(x, y, z),
+            (Some("all_different"), [list]) => self.all_different(list, false),
+            (Some("all_distinct"), [list]) => self.all_different(list, true),
+            _ => Err(Exception::domain_error(
+                "clpfd_propagator",
+                propagator.clone(),
+            )),
+        }
+    }
+
+    // Bounds consistency for `Sum + Constant = 0`, or `=< 0` when `equal` is
+    // false.
+    fn linear(
+        &mut self,
+        terms: &[(i64, Term)],
+        equal: bool,
+        constant: i64,
+    ) -> Result<bool, Exception> {
+        if terms.is_empty() {
+            return Ok(if equal { constant == 0 } else { constant <= 0 });
+        }
+        let mut bounds = vec![];
+        for (coefficient, term) in terms {
+            bounds.push(scaled(*coefficient, &self.domain(term)?));
+        }
+        let (low, low_infinite) = partial_sum(bounds.iter().map(|bound| bound.0));
+        let (high, high_infinite) = partial_sum(bounds.iter().map(|bound| bound.1));
+        for ((coefficient, term), (own_low, own_high)) in terms.iter().zip(&bounds) {
+            let rest_low =
+                (low_infinite == own_low.is_none() as usize).then(|| low - own_low.unwrap_or(0));
+            let rest_high = (high_infinite == own_high.is_none() as usize)
+                .then(|| high - own_high.unwrap_or(0));
+            let max = rest_low.map(|rest| -(constant as i128) - rest);
+            let min = match equal {
+                true => rest_high.map(|rest| -(constant as i128) - rest),
+                false => None,
+            };
+            let coefficient = *coefficient as i128;
+            let (min, max) = match coefficient > 0 {
+                true => (
+                    min.map(|min| div_ceil(min, coefficient)),
+                    max.map(|max| div_floor(max, coefficient)),
+                ),
+                false => (
+                    max.map(|max| div_ceil(max, coefficient)),
+                    min.map(|min| div_floor(min, coefficient)),
+                ),
+            };
+            if !self.restrict(term, min, max)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn not_equal(&mut self, terms: &[(i64, Term)], constant: i64) -> Result<bool, Exception> {
+        let mut sum = constant as i128;
+        let mut unfixed = vec![];
+        for (coefficient, term) in terms {
+            match self.domain(term)?.value() {
+                Some(value) => sum += *coefficient as i128 * value as i128,
+                None => unfixed.push((*coefficient as i128, term)),
+            }
+        }
+        match unfixed.as_slice() {
+            [] => Ok(sum != 0),
+            [(coefficient, term)] if -sum % coefficient == 0 => {
+                let value = (-sum / coefficient).clamp(INF as i128, SUP as i128) as i64;
+                let domain = self.domain(term)?.remove(value);
+                self.narrow(term, &domain)
+            }
+            _ => Ok(true),
+        }
+    }
+
+    fn times(&mut self, x: &Term, y: &Term, z: &Term) -> Result<bool, Exception> {
+        let (x_domain, y_domain) = (self.domain(x)?, self.domain(y)?);
+        if x_domain.is_finite() && y_domain.is_finite() {
+            let corners = [
+                x_domain.min() as i128 * y_domain.min() as i128,
+                x_domain.min() as i128 * y_domain.max() as i128,
+                x_domain.max() as i128 * y_domain.min() as i128,
+                x_domain.max() as i128 * y_domain.max() as i128,
+            ];
+            let (min, max) = (corners.iter().min(), corners.iter().max());
+            if !self.restrict(z, min.copied(), max.copied())? {
+                return Ok(false);
+            }
+        }
+        Ok(self.quotient(x, y, z)? && self.quotient(y, x, z)?)
+    }
+
+    // Narrows `factor` from `product` when `other * factor = product` and
+    // `other` is known.
+    fn quotient(&mut self, factor: &Term, other: &Term, product: &Term) -> Result<bool, Exception> {
+        let other = match self.domain(other)?.value() {
+            Some(0) => return self.narrow(product, &Domain::singleton(0)),
+            Some(value) => value as i128,
+            None => return Ok(true),
+        };
+        let product = self.domain(product)?;
+        let low = (product.min() != INF).then(|| product.min() as i128);
+        let high = (product.max() != SUP).then(|| product.max() as i128);
+        let (min, max) = match other > 0 {
+            true => (
+                low.map(|low| div_ceil(low, other)),
+                high.map(|high| div_floor(high, other)),
+            ),
+            false => (
+                high.map(|high| div_ceil(high, other)),
+                low.map(|low| div_floor(low, other)),
+            ),
+        };
+        self.restrict(factor, min, max)
+    }
+
+    // Removes the values taken from the other elements. With `pigeonhole`, the
+    // remaining elements must also have enough values between them.
+    fn all_different(&mut self, list: &Term, pigeonhole: bool) -> Result<bool, Exception> {
+        let elements = list.list_elements()?;
+        let mut values = vec![];
+        for element in &elements {
+            values.push(self.domain(element)?.value());
+        }
+        let fixed: Vec<i64> = values.iter().flatten().copied().collect();
+        for (index, value) in fixed.iter().enumerate() {
+            if fixed[index + 1..].contains(value) {
+                return Ok(false);
+            }
+        }
+        for (index, element) in elements.iter().enumerate() {
+            if element.arity() == 0 && elements[index + 1..].contains(element) {
+                return Ok(false);
+            }
+        }
+        let mut union = Domain::interval(1, 0);
+        let mut unfixed = 0;
+        for (element, value) in elements.iter().zip(&values) {
+            if value.is_some() {
+                continue;
+            }
+            let domain = fixed
+                .iter()
+                .fold(self.domain(element)?, |domain, value| domain.remove(*value));
+            if !self.narrow(element, &domain)? {
+                return Ok(false);
+            }
+            union = union.union(&domain);
+            unfixed += 1;
+        }
+        Ok(!pigeonhole || union.size().is_none_or(|size| size >= unfixed))
+    }
+}
+
+pub fn domain(solver: &Solver, term: &Term) -> Result<Domain, Exception> {
+    match solver.dereference(term) {
+        Term::Number { value } => Ok(Domain::singleton(*value)),
+        Term::Variable { name } => match solver.attribute(name, MODULE) {
+            Some(attribute) => Domain::from_term(&solver.resolve(&attribute.arguments()[0])),
+            None => Ok(Domain::full()),
+        },
+        term => Err(Exception::type_error("integer", term.clone())),
+    }
+}
+
+pub fn propagators(solver: &Solver, name: &str) -> Vec<Term> {
+    match solver.attribute(name, MODULE) {
+        Some(attribute) => attribute.arguments()[1].list_elements().unwrap_or_default(),
+        None => vec![],
+    }
+}
+
+pub fn set_attribute(solver: &mut Solver, name: &str, domain: &Domain, propagators: Vec<Term>) {
+    let attribute = Term::functor(MODULE, [domain.to_term(), Term::list(propagators)]);
+    solver.set_attribute(name, MODULE, Some(attribute));
+}
+
+pub fn linear_terms(terms: &Term) -> Result<Vec<(i64, Term)>, Exception> {
+    terms
+        .list_elements()?
+        .into_iter()
+        .map(|term| match (term.name(), term.arguments()) {
+            (Some("*"), [coefficient, variable]) => Ok((integer(coefficient)?, variable.clone())),
+            _ => Err(Exception::type_error("clpfd_term", term.clone())),
+        })
+        .collect()
+}
+
+pub fn integer(term: &Term) -> Result<i64, Exception> {
+    match term {
+        Term::Number { value } => Ok(*value),
+        Term::Variable { .. } => Err(Exception::instantiation_error()),
+        term => Err(Exception::type_error("integer", term.clone())),
+    }
+}
+
+// The bounds of `coefficient * X`, with `None` for an infinite bound.
+pub fn scaled(coefficient: i64, domain: &Domain) -> (Option<i128>, Option<i128>) {
+    let min = (domain.min() != INF).then(|| domain.min() as i128 * coefficient as i128);
+    let max = (domain.max() != SUP).then(|| domain.max() as i128 * coefficient as i128);
+    match coefficient > 0 {
+        true => (min, max),
+        false => (max, min),
+    }
+}
+
+// The sum of the finite bounds and how many were infinite.
+pub fn partial_sum<I: Iterator<Item = Option<i128>>>(bounds: I) -> (i128, usize) {
+    bounds.fold((0, 0), |(sum, infinite), bound| match bound {
+        Some(bound) => (sum + bound, infinite),
+        None => (sum, infinite + 1),
+    })
+}
+
+fn div_floor(a: i128, b: i128) -> i128 {
+    let quotient = a / b;
+    if a % b != 0 && (a < 0) != (b < 0) {
+        quotient - 1
+    } else {
+        quotient
+    }
+}
+
+fn div_ceil(a: i128, b: i128) -> i128 {
+    -div_floor(-a, b)
+}
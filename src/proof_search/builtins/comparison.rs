@@ -0,0 +1,351 @@
+use super::{check_partial_list, control::add_arguments, Solver};
+use crate::{exception::Exception, term::Term};
+use std::{cmp::Ordering, collections::VecDeque};
+
+pub fn compare(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    match solver.resolve(&args[0]) {
+        Term::Variable { .. } => {}
+        Term::Functor {
+            ref name,
+            ref arguments,
+        } if arguments.is_empty() => {
+            if order_from_atom(name).is_none() {
+                return Err(Exception::domain_error("order", Term::atom(name)));
+            }
+        }
+        order => return Err(Exception::type_error("atom", order)),
+    }
+    let order = order_atom(compare_arguments(solver, args));
+    Ok(solver.unify(&args[0], &order))
+}
+
+pub fn identical(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    Ok(compare_arguments(solver, args) == Ordering::Equal)
+}
+
+pub fn not_identical(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    Ok(compare_arguments(solver, args) != Ordering::Equal)
+}
+
+pub fn less(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    Ok(compare_arguments(solver, args) == Ordering::Less)
+}
+
+pub fn greater(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    Ok(compare_arguments(solver, args) == Ordering::Greater)
+}
+
+pub fn less_or_equal(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    Ok(compare_arguments(solver, args) != Ordering::Greater)
+}
+
+pub fn greater_or_equal(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    Ok(compare_arguments(solver, args) != Ordering::Less)
+}
+
+pub fn sort(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let mut elements = sortable_elements(solver, args)?;
+    elements.sort();
+    elements.dedup();
+    Ok(solver.unify(&args[1], &Term::list(elements)))
+}
+
+pub fn msort(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let mut elements = sortable_elements(solver, args)?;
+    elements.sort();
+    Ok(solver.unify(&args[1], &Term::list(elements)))
+}
+
+pub fn sort_by_key(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let key = match solver.resolve(&args[0]) {
+        Term::Number { value } if value >= 0 => value as usize,
+        Term::Number { value } => {
+            return Err(Exception::domain_error(
+                "not_less_than_zero",
+                Term::number(value),
+            ))
+        }
+        Term::Variable { .. } => return Err(Exception::instantiation_error()),
+        key => return Err(Exception::type_error("integer", key)),
+    };
+    let (descending, deduplicate) = match solver.resolve(&args[1]) {
+        Term::Functor {
+            ref name,
+            ref arguments,
+        } if arguments.is_empty() => match name.as_str() {
+            "@<" => (false, true),
+            "@=<" => (false, false),
+            "@>" => (true, true),
+            "@>=" => (true, false),
+            _ => return Err(Exception::domain_error("order", Term::atom(name))),
+        },
+        Term::Variable { .. } => return Err(Exception::instantiation_error()),
+        order => return Err(Exception::type_error("atom", order)),
+    };
+    let elements = sortable_elements(solver, &args[2..])?;
+    let mut keyed = elements
+        .into_iter()
+        .map(|element| Ok((sort_key(&element, key)?, element)))
+        .collect::<Result<Vec<_>, Exception>>()?;
+    if descending {
+        keyed.sort_by(|(a, _), (b, _)| b.cmp(a));
+    } else {
+        keyed.sort_by(|(a, _), (b, _)| a.cmp(b));
+    }
+    if deduplicate {
+        keyed.dedup_by(|(a, _), (b, _)| a == b);
+    }
+    let sorted = keyed.into_iter().map(|(_, element)| element);
+    Ok(solver.unify(&args[3], &Term::list(sorted)))
+}
+
+pub fn predsort(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let predicate = solver.resolve(&args[0]);
+    let elements = sortable_elements(solver, &args[1..])?;
+    match merge_sort(solver, &predicate, elements)? {
+        Some(sorted) => Ok(solver.unify(&args[2], &Term::list(sorted))),
+        None => Ok(false),
+    }
+}
+
+pub fn keysort(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let elements = sortable_elements(solver, args)?;
+    let mut pairs = elements
+        .into_iter()
+        .map(|element| match &element {
+            Term::Functor { name, arguments } if name == "-" && arguments.len() == 2 => {
+                Ok((arguments[0].clone(), element))
+            }
+            Term::Variable { .. } => Err(Exception::instantiation_error()),
+            _ => Err(Exception::type_error("pair", element)),
+        })
+        .collect::<Result<Vec<_>, Exception>>()?;
+    pairs.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let sorted = pairs.into_iter().map(|(_, element)| element);
+    Ok(solver.unify(&args[1], &Term::list(sorted)))
+}
+
+fn compare_arguments(solver: &Solver, args: &[Term]) -> Ordering {
+    let a = solver.resolve(&args[args.len() - 2]);
+    let b = solver.resolve(&args[args.len() - 1]);
+    a.cmp(&b)
+}
+
+fn order_atom(ordering: Ordering) -> Term {
+    Term::atom(match ordering {
+        Ordering::Less => "<",
+        Ordering::Equal => "=",
+        Ordering::Greater => ">",
+    })
+}
+
+fn order_from_atom(name: &str) -> Option<Ordering> {
+    match name {
+        "<" => Some(Ordering::Less),
+        "=" => Some(Ordering::Equal),
+        ">" => Some(Ordering::Greater),
+        _ => None,
+    }
+}
+
+fn sortable_elements(solver: &Solver, args: &[Term]) -> Result<Vec<Term>, Exception> {
+    let elements = solver.resolve(&args[0]).list_elements()?;
+    check_partial_list(&solver.resolve(&args[1]))?;
+    Ok(elements)
+}
+
+fn sort_key(element: &Term, key: usize) -> Result<Term, Exception> {
+    if key == 0 {
+        return Ok(element.clone());
+    }
+    match element {
+        Term::Functor { arguments, .. } if arguments.len() >= key => Ok(arguments[key - 1].clone()),
+        Term::Variable { .. } => Err(Exception::instantiation_error()),
+        _ => Err(Exception::type_error("compound", element.clone())),
+    }
+}
+
+fn merge_sort(
+    solver: &mut Solver,
+    predicate: &Term,
+    mut elements: Vec<Term>,
+) -> Result<Option<Vec<Term>>, Exception> {
+    if elements.len() <= 1 {
+        return Ok(Some(elements));
+    }
+    let right = elements.split_off(elements.len() / 2);
+    let (mut left, mut right): (VecDeque<_>, VecDeque<_>) = match (
+        merge_sort(solver, predicate, elements)?,
+        merge_sort(solver, predicate, right)?,
+    ) {
+        (Some(left), Some(right)) => (left.into(), right.into()),
+        _ => return Ok(None),
+    };
+    let mut merged = vec![];
+    while let (Some(a), Some(b)) = (left.front(), right.front()) {
+        match call_order(solver, predicate, a, b)? {
+            Some(Ordering::Less) => merged.push(left.pop_front().unwrap()),
+            Some(Ordering::Greater) => merged.push(right.pop_front().unwrap()),
+            Some(Ordering::Equal) => {
+                right.pop_front();
+            }
+            None => return Ok(None),
+        }
+    }
+    merged.extend(left);
+    merged.extend(right);
+    Ok(Some(merged))
+}
+
+fn call_order(
+    solver: &mut Solver,
+    predicate: &Term,
+    a: &Term,
+    b: &Term,
+) -> Result<Option<Ordering>, Exception> {
+    let order = solver.fresh_variable();
+    let goal = add_arguments(predicate.clone(), &[order.clone(), a.clone(), b.clone()])?;
+    if !solver.solve_once(goal)? {
+        return Ok(None);
+    }
+    match solver.resolve(&order) {
+        Term::Functor {
+            ref name,
+            ref arguments,
+        } if arguments.is_empty() => order_from_atom(name)
+            .map(Some)
+            .ok_or_else(|| Exception::domain_error("order", Term::atom(name))),
+        Term::Variable { .. } => Err(Exception::instantiation_error()),
+        order => Err(Exception::type_error("atom", order)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::query;
+    use crate::{
+        assignments::Assignments, database::Database, exception::Exception, parser::MinplParser,
+        term::Term,
+    };
+
+    #[test]
+    fn compare_terms_in_standard_order() {
+        let result = query(&Database::empty(), "compare(O, 1, a).");
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with("O", Term::atom("<"))]
+        );
+    }
+
+    #[test]
+    fn compare_with_invalid_order() {
+        let result = query(&Database::empty(), "compare(bigger, 1, a).");
+
+        assert_eq!(
+            result,
+            Err(Exception::domain_error("order", Term::atom("bigger")))
+        );
+    }
+
+    #[test]
+    fn compare_bound_variables_by_value() {
+        let result = query(&Database::empty(), "X = a, Y = a, X == Y, X @=< Y, a @< b.");
+
+        assert_eq!(result.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn sort_removes_duplicates() {
+        let result = query(&Database::empty(), "sort([c, a, b, a], L).");
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with(
+                "L",
+                Term::list([Term::atom("a"), Term::atom("b"), Term::atom("c")])
+            )]
+        );
+    }
+
+    #[test]
+    fn msort_keeps_duplicates() {
+        let result = query(&Database::empty(), "msort([b, 1, a, 1], L).");
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with(
+                "L",
+                Term::list([
+                    Term::number(1),
+                    Term::number(1),
+                    Term::atom("a"),
+                    Term::atom("b")
+                ])
+            )]
+        );
+    }
+
+    #[test]
+    fn sort_partial_list_is_an_instantiation_error() {
+        let result = query(&Database::empty(), "sort([a|T], L).");
+
+        assert_eq!(result, Err(Exception::instantiation_error()));
+    }
+
+    #[test]
+    fn sort_on_key_descending() {
+        let result = query(
+            &Database::empty(),
+            "sort(1, @>=, [f(1, a), f(2, b), f(1, c)], L).",
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with(
+                "L",
+                Term::list([
+                    Term::functor("f", [Term::number(2), Term::atom("b")]),
+                    Term::functor("f", [Term::number(1), Term::atom("a")]),
+                    Term::functor("f", [Term::number(1), Term::atom("c")]),
+                ])
+            )]
+        );
+    }
+
+    #[test]
+    fn keysort_is_stable() {
+        let result = query(&Database::empty(), "keysort([b-1, a-2, b-0], L).");
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with(
+                "L",
+                Term::list([
+                    Term::functor("-", [Term::atom("a"), Term::number(2)]),
+                    Term::functor("-", [Term::atom("b"), Term::number(1)]),
+                    Term::functor("-", [Term::atom("b"), Term::number(0)]),
+                ])
+            )]
+        );
+    }
+
+    #[test]
+    fn predsort_with_user_predicate_drops_equal_elements() {
+        let database =
+            MinplParser::parse_database("by_key(O, A-_, B-_) :- compare(O, A, B).").unwrap();
+
+        let result = query(&database, "predsort(by_key, [2-a, 1-b, 2-c], L).");
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with(
+                "L",
+                Term::list([
+                    Term::functor("-", [Term::number(1), Term::atom("b")]),
+                    Term::functor("-", [Term::number(2), Term::atom("a")]),
+                ])
+            )]
+        );
+    }
+}
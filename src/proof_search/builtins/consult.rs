@@ -0,0 +1,58 @@
+use super::Solver;
+use crate::{consult, exception::Exception, term::Term};
+
+pub fn consult(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let files = solver.resolve(&args[0]);
+    let context = solver.module().to_string();
+    consult::consult(solver.database(), &files, &context)?;
+    Ok(true)
+}
+
+pub fn consult_list(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let files = solver.resolve(&Term::functor(".", args.to_vec()));
+    let context = solver.module().to_string();
+    consult::consult(solver.database(), &files, &context)?;
+    Ok(true)
+}
+
+pub fn ensure_loaded(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let files = solver.resolve(&args[0]);
+    let context = solver.module().to_string();
+    consult::ensure_loaded(solver.database(), &files, &context)?;
+    Ok(true)
+}
+
+pub fn use_module(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let files = solver.resolve(&args[0]);
+    let imports = args.get(1).map(|imports| solver.resolve(imports));
+    let context = solver.module().to_string();
+    consult::use_module(solver.database(), &files, imports.as_ref(), &context)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        assignments::Assignments, database::Database, parser::MinplParser, proof_search::prove,
+        term::Term,
+    };
+    use std::{env, fs};
+
+    #[test]
+    fn consult_from_a_query() {
+        let path = env::temp_dir().join(format!("minpl-query-{}.pl", std::process::id()));
+        fs::write(&path, "f(a).").unwrap();
+        let file = path.with_extension("").display().to_string();
+        let mut database = Database::empty();
+
+        let query = MinplParser::parse_query(&format!("['{}'], consult('{}').", file, file));
+        prove(&mut database, query.unwrap()).unwrap();
+        let result = prove(&mut database, MinplParser::parse_query("f(X).").unwrap());
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with("X", Term::atom("a"))]
+        );
+    }
+}
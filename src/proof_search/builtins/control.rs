@@ -0,0 +1,301 @@
+use super::{check_partial_list, Solver};
+use crate::{database::dcg_body, exception::Exception, term::Term};
+
+pub fn call(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let goal = add_arguments(solver.dereference(&args[0]).clone(), &args[1..])?;
+    check_body(solver, &goal)?;
+    solver.push_goal(goal);
+    Ok(true)
+}
+
+// A goal made of control constructs is checked as a whole before any of it
+// runs, so that `call((fail, 1))` is a type error rather than a failure.
+pub fn check_body(solver: &Solver, goal: &Term) -> Result<(), Exception> {
+    let mut pending = vec![goal];
+    while let Some(term) = pending.pop() {
+        match solver.dereference(term) {
+            Term::Functor { name, arguments }
+                if arguments.len() == 2 && matches!(name.as_str(), "," | ";" | "->") =>
+            {
+                pending.extend(arguments)
+            }
+            Term::Functor { .. } | Term::Variable { .. } => {}
+            _ => return Err(Exception::type_error("callable", solver.resolve(goal))),
+        }
+    }
+    Ok(())
+}
+
+// `phrase(Body, List, Rest)` parses `List` with a grammar rule body.
+pub fn phrase(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let body = solver.resolve(&args[0]);
+    let rest = args.get(2).cloned().unwrap_or_else(|| Term::list([]));
+    if let Term::Variable { .. } = body {
+        return Err(Exception::instantiation_error());
+    }
+    check_partial_list(&solver.resolve(&args[1]))?;
+    check_partial_list(&solver.resolve(&rest))?;
+    let goal = dcg_body(&body, args[1].clone(), rest, &mut || {
+        solver.fresh_variable()
+    })?;
+    solver.push_goal(goal);
+    Ok(true)
+}
+
+pub fn not(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    solver.push_goal(if_then_else(args[0].clone(), "fail", "true"));
+    Ok(true)
+}
+
+pub fn once(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    solver.push_goal(if_then_else(args[0].clone(), "true", "fail"));
+    Ok(true)
+}
+
+pub fn ignore(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    solver.push_goal(if_then_else(args[0].clone(), "true", "true"));
+    Ok(true)
+}
+
+pub fn forall(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let counterexample = Term::functor(
+        ",",
+        [args[0].clone(), Term::functor("\\+", [args[1].clone()])],
+    );
+    solver.push_goal(if_then_else(counterexample, "fail", "true"));
+    Ok(true)
+}
+
+pub fn throw(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    match solver.resolve(&args[0]) {
+        Term::Variable { .. } => Err(Exception::instantiation_error()),
+        ball => Err(Exception::new(ball)),
+    }
+}
+
+pub fn catch(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    solver.catch(args[0].clone(), args[1].clone(), args[2].clone());
+    Ok(true)
+}
+
+pub fn add_arguments(mut goal: Term, extra: &[Term]) -> Result<Term, Exception> {
+    match goal {
+        Term::Functor {
+            ref name,
+            ref mut arguments,
+        } if name == ":" && arguments.len() == 2 => {
+            let goal = add_arguments(arguments.pop().unwrap(), extra)?;
+            Ok(Term::functor(name, [arguments.pop().unwrap(), goal]))
+        }
+        Term::Functor {
+            ref name,
+            ref mut arguments,
+        } => Ok(Term::functor(
+            name,
+            std::mem::take(arguments)
+                .into_iter()
+                .chain(extra.iter().cloned()),
+        )),
+        Term::Variable { .. } => Err(Exception::instantiation_error()),
+        goal => Err(Exception::type_error("callable", goal)),
+    }
+}
+
+fn if_then_else(condition: Term, then: &str, otherwise: &str) -> Term {
+    Term::functor(
+        ";",
+        [
+            Term::functor("->", [condition, Term::atom(then)]),
+            Term::atom(otherwise),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::query;
+    use crate::{
+        assignments::Assignments, database::Database, exception::Exception, parser::MinplParser,
+        term::Term,
+    };
+    use test_case::test_case;
+
+    fn database() -> Database {
+        MinplParser::parse_database(
+            "member(X, [X|_]).
+            member(X, [_|T]) :- member(X, T).
+            maplist(_, []).
+            maplist(G, [X|Xs]) :- call(G, X), maplist(G, Xs).
+            first(X, L) :- member(X, L), !.",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn call_appends_extra_arguments() {
+        let result = query(&database(), "G = member(X), call(G, [a, b]).");
+
+        assert_eq!(
+            result
+                .unwrap()
+                .into_iter()
+                .map(|solution| solution.get("X").cloned())
+                .collect::<Vec<_>>(),
+            vec![Some(Term::atom("a")), Some(Term::atom("b"))]
+        );
+    }
+
+    #[test]
+    fn goals_passed_as_data() {
+        let result = query(&database(), "maplist(atom, [a, b]).");
+
+        assert_eq!(result.unwrap(), vec![Assignments::empty()]);
+    }
+
+    #[test]
+    fn calling_unbound_variable_is_an_instantiation_error() {
+        let result = query(&database(), "call(G).");
+
+        assert_eq!(result, Err(Exception::instantiation_error()));
+    }
+
+    #[test]
+    fn calling_a_number_is_a_type_error() {
+        let result = query(&database(), "call(1, a).");
+
+        assert_eq!(
+            result,
+            Err(Exception::type_error("callable", Term::number(1)))
+        );
+    }
+
+    #[test_case("call((fail, 1)).", "(fail, 1)." ; "call")]
+    #[test_case("G = (fail ; true -> 1), G.", "(fail ; true -> 1)." ; "variable goal")]
+    fn control_constructs_are_checked_before_running(code: &str, culprit: &str) {
+        let result = query(&database(), code);
+
+        let culprit = MinplParser::parse_query(culprit).unwrap();
+        assert_eq!(result, Err(Exception::type_error("callable", culprit)));
+    }
+
+    #[test]
+    fn cut_commits_to_first_clause_solution() {
+        let result = query(&database(), "first(X, [a, b]).");
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with("X", Term::atom("a"))]
+        );
+    }
+
+    #[test]
+    fn cut_inside_call_is_local() {
+        let result = query(
+            &database(),
+            "member(Y, [1, 2]), call((member(X, [a, b]), !)).",
+        );
+
+        assert_eq!(result.unwrap().len(), 2);
+    }
+
+    #[test]
+    fn once_keeps_first_solution() {
+        let result = query(&database(), "once(member(X, [a, b])).");
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with("X", Term::atom("a"))]
+        );
+    }
+
+    #[test]
+    fn ignore_succeeds_when_goal_fails() {
+        let result = query(&database(), "ignore(member(c, [])).");
+
+        assert_eq!(result.unwrap(), vec![Assignments::empty()]);
+    }
+
+    #[test]
+    fn forall_checks_every_solution() {
+        assert_eq!(
+            query(&database(), "forall(member(X, [a, b]), atom(X)).").unwrap(),
+            vec![Assignments::empty()]
+        );
+        assert_eq!(
+            query(&database(), "forall(member(X, [a, 1]), atom(X)).").unwrap(),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn if_then_else_and_negation() {
+        let result = query(
+            &database(),
+            "(member(b, [a, b]) -> X = yes ; X = no), \\+ member(c, [a, b]).",
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with("X", Term::atom("yes"))]
+        );
+    }
+
+    #[test]
+    fn catch_recovers_from_errors() {
+        let result = query(&database(), "catch(call(G), error(E, _), true).");
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with("E", Term::atom("instantiation_error"))]
+        );
+    }
+
+    #[test]
+    fn catch_is_not_active_after_goal_exits() {
+        let result = query(
+            &database(),
+            "catch(member(X, [a, b]), _, true), throw(oops).",
+        );
+
+        assert_eq!(result, Err(Exception::new(Term::atom("oops"))));
+    }
+
+    #[test]
+    fn phrase_parses_with_a_grammar_body() {
+        assert_eq!(
+            query(&database(), "phrase(([a], ([b] ; [c])), [a, X]).").unwrap(),
+            vec![
+                Assignments::empty().with("X", Term::atom("b")),
+                Assignments::empty().with("X", Term::atom("c"))
+            ]
+        );
+        assert_eq!(
+            query(&database(), "phrase([a], [a, b], R).").unwrap(),
+            vec![Assignments::empty().with("R", MinplParser::parse_query("[b].").unwrap())]
+        );
+    }
+
+    #[test]
+    fn phrase_checks_its_body() {
+        assert_eq!(
+            query(&database(), "phrase(G, [])."),
+            Err(Exception::instantiation_error())
+        );
+        assert_eq!(
+            query(&database(), "phrase(1, [])."),
+            Err(Exception::type_error("callable", Term::number(1)))
+        );
+    }
+
+    #[test]
+    fn phrase_checks_its_lists() {
+        assert_eq!(
+            query(&database(), "phrase([a], \"123\", R)."),
+            Err(Exception::type_error("list", Term::string("123")))
+        );
+        assert_eq!(
+            query(&database(), "phrase([a], [a|_], b)."),
+            Err(Exception::type_error("list", Term::atom("b")))
+        );
+    }
+}
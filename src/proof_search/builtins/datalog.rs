@@ -0,0 +1,120 @@
+use super::{strip_module, Solver};
+use crate::{datalog, exception::Exception, term::Term};
+
+// Solves the goal bottom-up, in the least model of the predicates it depends
+// on, and gives its instances there as alternatives.
+pub fn datalog(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let module = solver.module().to_string();
+    let (module, goal) = strip_module(solver.resolve(&args[0]), &module)?;
+    if let Term::Variable { .. } = goal {
+        return Err(Exception::instantiation_error());
+    }
+    let instances = datalog::solve(solver.database(), &module, &goal)?;
+    let alternatives = instances
+        .into_iter()
+        .map(|instance| Term::functor("=", [goal.clone(), instance]))
+        .collect();
+    Ok(solver.alternatives(alternatives))
+}
+
+// The clauses `datalog/1` evaluates for the goal after magic-sets rewriting.
+pub fn datalog_rewrite(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let module = solver.module().to_string();
+    let (module, goal) = strip_module(solver.resolve(&args[0]), &module)?;
+    if let Term::Variable { .. } = goal {
+        return Err(Exception::instantiation_error());
+    }
+    let (program, _) = datalog::program_for(solver.database(), &module, &goal)?;
+    // Each clause gets its own variables, apart from those of the query.
+    let clauses = program
+        .rules()
+        .iter()
+        .map(|rule| solver.rename(&rule.to_term()))
+        .collect::<Vec<_>>();
+    Ok(solver.unify(&args[1], &Term::list(clauses)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::query;
+    use crate::{
+        assignments::Assignments, consult::consult_str, database::Database, exception::Exception,
+        term::Term,
+    };
+
+    fn database() -> Database {
+        let mut database = Database::empty();
+        consult_str(
+            &mut database,
+            "edge(a, b). edge(b, c). edge(c, a).
+            path(X, Y) :- path(X, Z), edge(Z, Y).
+            path(X, Y) :- edge(X, Y).",
+        )
+        .unwrap();
+        database
+    }
+
+    fn answers(values: &[&str]) -> Vec<Assignments> {
+        values
+            .iter()
+            .map(|value| Assignments::empty().with("X", Term::atom(value)))
+            .collect()
+    }
+
+    #[test]
+    fn per_query() {
+        let result = query(&database(), "datalog(path(a, X)).");
+
+        assert_eq!(result.unwrap(), answers(&["b", "c", "a"]));
+    }
+
+    #[test]
+    fn per_program() {
+        let result = query(
+            &database(),
+            "set_prolog_flag(evaluation, bottom_up), path(b, X).",
+        );
+
+        assert_eq!(result.unwrap(), answers(&["c", "a", "b"]));
+    }
+
+    #[test]
+    fn rewritten_program() {
+        let result = query(&database(), "datalog_rewrite(path(a, X), Clauses).");
+
+        let clauses = result.unwrap()[0]
+            .get("Clauses")
+            .unwrap()
+            .list_elements()
+            .unwrap();
+        assert_eq!(clauses.len(), 8);
+        assert_eq!(clauses[3], Term::atom("magic_$query_f"));
+        assert!(clauses.contains(&Term::functor(
+            ":-",
+            [
+                Term::functor("magic_path_bf", [Term::atom("a")]),
+                Term::atom("magic_$query_f")
+            ]
+        )));
+    }
+
+    #[test]
+    fn errors_can_be_caught() {
+        let mut database = database();
+        consult_str(&mut database, "bad(X) :- edge(a, _).").unwrap();
+
+        let result = query(
+            &database,
+            "catch(datalog(bad(_)), error(datalog_error(unsafe_variable(_), _), _), R = caught).",
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with("R", Term::atom("caught"))]
+        );
+        assert_eq!(
+            query(&Database::empty(), "datalog(_)."),
+            Err(Exception::instantiation_error())
+        );
+    }
+}
@@ -0,0 +1,345 @@
+use super::{is_builtin, strip_module, Solver};
+use crate::{
+    database::{predicate_indicator, predicate_indicators, Rule},
+    exception::Exception,
+    term::Term,
+    unification::{unify, UnifyResult},
+};
+
+pub fn asserta(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let (module, rule) = new_clause(solver, &args[0])?;
+    solver.database().asserta(&module, rule)?;
+    Ok(true)
+}
+
+pub fn assertz(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let (module, rule) = new_clause(solver, &args[0])?;
+    solver.database().assertz(&module, rule)?;
+    Ok(true)
+}
+
+pub fn retract(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let (module, clause) = strip_module(solver.resolve(&args[0]), solver.module())?;
+    let rule = Rule::from_term(clause);
+    let body = rule.body_term();
+    let (module, head) = strip_module(rule.head, &module)?;
+    let (module, name, arity) = modifiable_predicate(solver, &module, &head)?;
+    let pattern = Term::functor(":-", [head, body]);
+    let indicator = Term::functor(
+        ":",
+        [
+            Term::atom(&module),
+            Term::functor("/", [Term::atom(&name), Term::number(arity as i64)]),
+        ],
+    );
+    let clauses = solver.database().clauses(&module, &name, arity);
+    let alternatives = clauses
+        .iter()
+        .map(|clause| {
+            let stored = Term::functor(":-", [clause.rule.head.clone(), clause.rule.body_term()]);
+            let erase = Term::functor(
+                "$erase",
+                [indicator.clone(), Term::number(clause.id as i64)],
+            );
+            Term::functor(
+                ",",
+                [
+                    Term::functor("=", [solver.rename(&stored), pattern.clone()]),
+                    erase,
+                ],
+            )
+        })
+        .collect();
+    Ok(solver.alternatives(alternatives))
+}
+
+pub fn erase(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let (module, indicator) = strip_module(solver.resolve(&args[0]), solver.module())?;
+    let (name, arity) = predicate_indicator(&indicator)?;
+    match solver.resolve(&args[1]) {
+        Term::Number { value } => {
+            Ok(solver
+                .database()
+                .erase(&module, &name, arity, value as usize))
+        }
+        id => Err(Exception::type_error("integer", id)),
+    }
+}
+
+pub fn retractall(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let (module, head) = strip_module(solver.resolve(&args[0]), solver.module())?;
+    let (module, name, arity) = modifiable_predicate(solver, &module, &head)?;
+    let clauses = solver.database().clauses(&module, &name, arity);
+    for clause in clauses.iter() {
+        let stored = solver.rename(&clause.rule.head);
+        if unify(head.clone(), stored) != UnifyResult::False {
+            solver.database().erase(&module, &name, arity, clause.id);
+        }
+    }
+    solver.database().declare_dynamic(&module, &name, arity);
+    Ok(true)
+}
+
+pub fn abolish(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let (module, indicator) = strip_module(solver.resolve(&args[0]), solver.module())?;
+    let (name, arity) = predicate_indicator(&indicator)?;
+    check_modifiable(solver, &module, &name, arity)?;
+    solver.database().abolish(&module, &name, arity);
+    Ok(true)
+}
+
+pub fn dynamic(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let (module, specification) = strip_module(solver.resolve(&args[0]), solver.module())?;
+    for (name, arity) in predicate_indicators(&specification)? {
+        check_modifiable(solver, &module, &name, arity)?;
+        solver.database().declare_dynamic(&module, &name, arity);
+    }
+    Ok(true)
+}
+
+fn new_clause(solver: &mut Solver, clause: &Term) -> Result<(String, Rule), Exception> {
+    let (module, clause) = strip_module(solver.resolve(clause), solver.module())?;
+    let mut rule = Rule::from_term(clause);
+    let (module, head) = strip_module(rule.head, &module)?;
+    let (module, name, arity) = modifiable_predicate(solver, &module, &head)?;
+    rule.head = head;
+    for goal in &rule.body {
+        if let Term::Number { .. } | Term::Str { .. } = goal {
+            return Err(Exception::type_error("callable", rule.body_term()));
+        }
+    }
+    solver.database().declare_dynamic(&module, &name, arity);
+    Ok((module, rule))
+}
+
+fn modifiable_predicate(
+    solver: &mut Solver,
+    module: &str,
+    head: &Term,
+) -> Result<(String, String, usize), Exception> {
+    match head {
+        Term::Variable { .. } => Err(Exception::instantiation_error()),
+        Term::Functor { name, arguments } => {
+            let key = (name.clone(), arguments.len());
+            let module = solver.database().definition_module(module, &key);
+            check_modifiable(solver, &module, name, arguments.len())?;
+            Ok((module, key.0, key.1))
+        }
+        head => Err(Exception::type_error("callable", head.clone())),
+    }
+}
+
+// Predicates loaded from a program are static unless declared dynamic, and
+// builtins can never be changed.
+fn check_modifiable(
+    solver: &mut Solver,
+    module: &str,
+    name: &str,
+    arity: usize,
+) -> Result<(), Exception> {
+    let database = solver.database();
+    if is_builtin(name, arity)
+        || database.is_defined(module, name, arity) && !database.is_dynamic(module, name, arity)
+    {
+        return Err(Exception::permission_error(
+            "modify",
+            "static_procedure",
+            Term::functor("/", [Term::atom(name), Term::number(arity as i64)]),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::query;
+    use crate::{
+        assignments::Assignments, database::Database, exception::Exception, parser::MinplParser,
+        proof_search::prove, term::Term,
+    };
+
+    fn database() -> Database {
+        MinplParser::parse_database(
+            ":- dynamic counter/1.
+            counter(0).
+            static(a).",
+        )
+        .unwrap()
+    }
+
+    fn numbers(values: &[i64]) -> Term {
+        Term::list(values.iter().copied().map(Term::number))
+    }
+
+    #[test]
+    fn assert_adds_clauses_at_both_ends() {
+        let result = query(
+            &database(),
+            "assertz(counter(2)), asserta(counter(1)), findall(X, counter(X), L).",
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with("L", numbers(&[1, 0, 2]))]
+        );
+    }
+
+    #[test]
+    fn asserted_rules_are_callable() {
+        let result = query(
+            &database(),
+            "assertz((double(X, f(X, X)) :- true)), assertz((twice(X, Y) :- double(X, Y))), twice(a, Y).",
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty()
+                .with("Y", Term::functor("f", [Term::atom("a"), Term::atom("a")]))]
+        );
+    }
+
+    #[test]
+    fn assert_changes_persist_between_queries() {
+        let mut database = database();
+
+        prove(
+            &mut database,
+            MinplParser::parse_query("assertz(seen(a)).").unwrap(),
+        )
+        .unwrap();
+        let result = prove(&mut database, MinplParser::parse_query("seen(X).").unwrap());
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with("X", Term::atom("a"))]
+        );
+    }
+
+    #[test]
+    fn running_calls_do_not_see_asserted_clauses() {
+        let result = query(
+            &database(),
+            "findall(X, (counter(X), assertz(counter(1))), L), findall(X, counter(X), All).",
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty()
+                .with("L", numbers(&[0]))
+                .with("All", numbers(&[0, 1]))]
+        );
+    }
+
+    #[test]
+    fn running_calls_still_see_retracted_clauses() {
+        let result = query(
+            &database(),
+            "assertz(counter(1)), findall(X, (counter(X), retractall(counter(_))), L), findall(X, counter(X), All).",
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty()
+                .with("L", numbers(&[0, 1]))
+                .with("All", numbers(&[]))]
+        );
+    }
+
+    #[test]
+    fn retract_is_nondeterministic() {
+        let result = query(
+            &database(),
+            "assertz(counter(1)), assertz(counter(2)), retract(counter(X)), X == 1, findall(Y, counter(Y), L).",
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty()
+                .with("X", Term::number(1))
+                .with("L", numbers(&[2]))]
+        );
+    }
+
+    #[test]
+    fn retract_unifies_with_clause_body() {
+        let result = query(
+            &database(),
+            "assertz((p(X) :- q(X))), retract((p(Y) :- B)), findall(Z, p(Z), L).",
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty()
+                .with("B", Term::functor("q", [Term::variable("Y")]))
+                .with("L", Term::list([]))]
+        );
+    }
+
+    #[test]
+    fn retract_removes_first_matching_clause() {
+        let result = query(
+            &database(),
+            "assertz(counter(1)), assertz(counter(2)), retract(counter(1)), findall(Y, counter(Y), L).",
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with("L", numbers(&[0, 2]))]
+        );
+    }
+
+    #[test]
+    fn abolish_removes_dynamic_predicate() {
+        let result = query(&database(), "abolish(counter/1), counter(X).");
+
+        assert_eq!(result.unwrap(), vec![]);
+    }
+
+    #[test]
+    fn retractall_creates_dynamic_predicate() {
+        let result = query(
+            &database(),
+            "retractall(fresh(_)), assertz(fresh(1)), fresh(X).",
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with("X", Term::number(1))]
+        );
+    }
+
+    #[test]
+    fn modifying_static_predicate_is_a_permission_error() {
+        let result = query(&database(), "assertz(static(b)).");
+
+        assert_eq!(
+            result,
+            Err(Exception::permission_error(
+                "modify",
+                "static_procedure",
+                Term::functor("/", [Term::atom("static"), Term::number(1)])
+            ))
+        );
+    }
+
+    #[test]
+    fn modifying_builtin_is_a_permission_error() {
+        let result = query(&database(), "retract(atom(_)).");
+
+        assert_eq!(
+            result,
+            Err(Exception::permission_error(
+                "modify",
+                "static_procedure",
+                Term::functor("/", [Term::atom("atom"), Term::number(1)])
+            ))
+        );
+    }
+
+    #[test]
+    fn assert_with_variable_head_is_an_instantiation_error() {
+        let result = query(&database(), "assertz((X :- true)).");
+
+        assert_eq!(result, Err(Exception::instantiation_error()));
+    }
+}
@@ -0,0 +1,133 @@
+use super::Solver;
+use crate::{exception::Exception, term::Term};
+
+pub fn set_prolog_flag(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let flag = solver.resolve(&args[0]);
+    let value = solver.resolve(&args[1]);
+    let name = flag_name(&flag)?;
+    if let Term::Variable { .. } = value {
+        return Err(Exception::instantiation_error());
+    }
+    let valid = match name.as_str() {
+        "unknown" => matches!(value.name(), Some("error" | "fail" | "warning")),
+        "evaluation" => matches!(value.name(), Some("top_down" | "bottom_up")),
+        "bounded" | "max_integer" | "min_integer" => {
+            return Err(Exception::permission_error("modify", "flag", flag))
+        }
+        _ => return Err(Exception::domain_error("prolog_flag", flag)),
+    };
+    if !valid || value.arity() > 0 {
+        return Err(Exception::domain_error(
+            "flag_value",
+            Term::functor("+", [flag, value]),
+        ));
+    }
+    solver.database().set_flag(&name, value);
+    Ok(true)
+}
+
+pub fn current_prolog_flag(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    match solver.resolve(&args[0]) {
+        Term::Variable { .. } => {
+            let alternatives = solver
+                .database()
+                .flags()
+                .into_iter()
+                .map(|(name, value)| {
+                    Term::functor(
+                        ",",
+                        [
+                            Term::functor("=", [args[0].clone(), Term::atom(name)]),
+                            Term::functor("=", [args[1].clone(), value]),
+                        ],
+                    )
+                })
+                .collect();
+            Ok(solver.alternatives(alternatives))
+        }
+        flag => {
+            let name = flag_name(&flag)?;
+            match solver.database().flag(&name).cloned() {
+                Some(value) => Ok(solver.unify(&args[1], &value)),
+                None => Err(Exception::domain_error("prolog_flag", flag)),
+            }
+        }
+    }
+}
+
+fn flag_name(flag: &Term) -> Result<String, Exception> {
+    match flag {
+        Term::Variable { .. } => Err(Exception::instantiation_error()),
+        Term::Functor { name, arguments } if arguments.is_empty() => Ok(name.clone()),
+        flag => Err(Exception::type_error("atom", flag.clone())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::query;
+    use crate::{assignments::Assignments, database::Database, exception::Exception, term::Term};
+
+    #[test]
+    fn unknown_procedures_fail_by_default() {
+        let result = query(&Database::empty(), "current_prolog_flag(unknown, V).");
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with("V", Term::atom("fail"))]
+        );
+    }
+
+    #[test]
+    fn unknown_procedure_error() {
+        let result = query(
+            &Database::empty(),
+            "set_prolog_flag(unknown, error), missing(1).",
+        );
+
+        assert_eq!(
+            result,
+            Err(Exception::existence_error(
+                "procedure",
+                Term::functor("/", [Term::atom("missing"), Term::number(1)])
+            ))
+        );
+    }
+
+    #[test]
+    fn enumerate_flags() {
+        let result = query(&Database::empty(), "current_prolog_flag(F, true).");
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with("F", Term::atom("bounded"))]
+        );
+    }
+
+    #[test]
+    fn invalid_flag_value() {
+        let result = query(&Database::empty(), "set_prolog_flag(unknown, maybe).");
+
+        assert_eq!(
+            result,
+            Err(Exception::domain_error(
+                "flag_value",
+                Term::functor("+", [Term::atom("unknown"), Term::atom("maybe")])
+            ))
+        );
+    }
+
+    #[test]
+    fn read_only_flag() {
+        let result = query(&Database::empty(), "set_prolog_flag(bounded, false).");
+
+        assert_eq!(
+            result,
+            Err(Exception::permission_error(
+                "modify",
+                "flag",
+                Term::atom("bounded")
+            ))
+        );
+    }
+}
@@ -0,0 +1,286 @@
+use super::{streams::text_sink, write::output, Solver};
+use crate::{
+    exception::Exception,
+    term::{Term, WriteOptions},
+};
+
+pub fn format(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let arguments = args.get(1).cloned().unwrap_or_else(|| Term::list([]));
+    let text = format_text(solver, &args[0], &arguments)?;
+    output(solver, None, &text)
+}
+
+// `format/3` writes to a stream or to `atom(A)`, `string(S)`, `codes(C)` or
+// `chars(C)`.
+pub fn format_to(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let text = format_text(solver, &args[1], &args[2])?;
+    let sink = solver.resolve(&args[0]);
+    match text_sink(&sink, &text) {
+        Some((result, value)) => Ok(solver.unify(result, &value)),
+        None => output(solver, Some(&sink), &text),
+    }
+}
+
+fn format_text(solver: &Solver, format: &Term, arguments: &Term) -> Result<String, Exception> {
+    let format = text(&solver.resolve(format))?;
+    let arguments = match solver.resolve(arguments) {
+        list @ Term::Functor { .. } if list.list_elements().is_ok() => list.list_elements()?,
+        argument => vec![argument],
+    };
+    let mut formatter = Formatter::default();
+    formatter.run(&format, arguments)?;
+    Ok(formatter.output)
+}
+
+// Text of an atom, a string or a list of codes or characters.
+pub fn text(term: &Term) -> Result<String, Exception> {
+    match term {
+        Term::Functor { name, arguments } if arguments.is_empty() && name != "[]" => {
+            Ok(name.clone())
+        }
+        Term::Str { value } => Ok(value.clone()),
+        Term::Variable { .. } => Err(Exception::instantiation_error()),
+        list => list
+            .list_elements()
+            .ok()
+            .and_then(|elements| elements.iter().map(character).collect())
+            .ok_or_else(|| Exception::type_error("text", term.clone())),
+    }
+}
+
+fn character(term: &Term) -> Option<char> {
+    match term {
+        Term::Number { value } => u32::try_from(*value).ok().and_then(char::from_u32),
+        Term::Functor { name, arguments } if arguments.is_empty() => {
+            let mut chars = name.chars();
+            chars.next().filter(|_| chars.next().is_none())
+        }
+        _ => None,
+    }
+}
+
+pub fn codes(text: &str) -> Term {
+    Term::list(text.chars().map(|c| Term::number(c as i64)))
+}
+
+pub fn chars(text: &str) -> Term {
+    Term::list(text.chars().map(Term::atom))
+}
+
+fn format_error(message: &str) -> Exception {
+    Exception::error(Term::functor("format", [Term::atom(message)]))
+}
+
+// Output of the current line since the last column stop is kept apart, with
+// the positions of its `~t` fill points, until the next stop pads it.
+#[derive(Default)]
+struct Formatter {
+    output: String,
+    segment: usize,
+    column: usize,
+    fills: Vec<(usize, char)>,
+}
+
+impl Formatter {
+    fn run(&mut self, format: &str, arguments: Vec<Term>) -> Result<(), Exception> {
+        let mut arguments = arguments.into_iter();
+        let mut next_argument = || {
+            arguments
+                .next()
+                .ok_or_else(|| format_error("not enough arguments"))
+        };
+        let mut chars = format.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '~' {
+                self.emit(&c.to_string());
+                continue;
+            }
+            let mut fill = ' ';
+            let mut number = None;
+            match chars.peek() {
+                Some('`') => {
+                    chars.next();
+                    fill = chars
+                        .next()
+                        .ok_or_else(|| format_error("truncated format"))?;
+                }
+                Some('*') => {
+                    chars.next();
+                    number = Some(integer(&next_argument()?)?);
+                }
+                _ => {
+                    let mut digits = String::new();
+                    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                        digits.push(digit);
+                    }
+                    number = digits.parse().ok();
+                }
+            }
+            let directive = chars
+                .next()
+                .ok_or_else(|| format_error("truncated format"))?;
+            match directive {
+                'w' => self.write(&next_argument()?, WriteOptions::WRITE),
+                'p' | 'q' => self.write(&next_argument()?, WriteOptions::WRITEQ),
+                'a' => match next_argument()? {
+                    Term::Variable { .. } => return Err(Exception::instantiation_error()),
+                    argument @ Term::Functor { .. } if argument.arity() > 0 => {
+                        return Err(Exception::type_error("atomic", argument))
+                    }
+                    argument => self.write(&argument, WriteOptions::WRITE),
+                },
+                'd' => {
+                    let value = integer(&next_argument()?)?;
+                    self.emit(&decimal(value, number.unwrap_or(0)));
+                }
+                's' => {
+                    let argument = next_argument()?;
+                    self.emit(&text(&argument)?);
+                }
+                'c' => {
+                    let argument = next_argument()?;
+                    let c = character(&argument)
+                        .filter(|_| matches!(argument, Term::Number { .. }))
+                        .ok_or_else(|| Exception::type_error("character_code", argument))?;
+                    self.emit(&c.to_string().repeat(number.unwrap_or(1).max(0) as usize));
+                }
+                'n' => self.emit(&"\n".repeat(number.unwrap_or(1).max(0) as usize)),
+                '~' => self.emit("~"),
+                't' => self.fills.push((self.output.len(), fill)),
+                '|' => {
+                    let target = number.map(|column| column.max(0) as usize);
+                    self.column_stop(target);
+                }
+                '+' => {
+                    let target = self.column + number.unwrap_or(8).max(0) as usize;
+                    self.column_stop(Some(target));
+                }
+                other => {
+                    return Err(format_error(&format!("unknown directive ~{}", other)));
+                }
+            }
+        }
+        match next_argument() {
+            Ok(_) => Err(format_error("too many arguments")),
+            Err(_) => Ok(()),
+        }
+    }
+
+    fn write(&mut self, term: &Term, options: WriteOptions) {
+        self.emit(&term.formatted(options).to_string());
+    }
+
+    fn emit(&mut self, text: &str) {
+        self.output.push_str(text);
+        if let Some(newline) = self.output.rfind('\n') {
+            if newline >= self.segment {
+                self.segment = newline + 1;
+                self.column = 0;
+                self.fills.clear();
+            }
+        }
+    }
+
+    // Pads the pending segment up to `target` at its fill points, or at its
+    // end when it has none.
+    fn column_stop(&mut self, target: Option<usize>) {
+        let width = self.output[self.segment..].chars().count();
+        let target = target.unwrap_or(self.column + width);
+        let padding = target.saturating_sub(self.column + width);
+        let fills = match self.fills.is_empty() {
+            true => vec![(self.output.len(), ' ')],
+            false => std::mem::take(&mut self.fills),
+        };
+        for (index, (position, fill)) in fills.iter().enumerate().rev() {
+            let mut count = padding / fills.len();
+            if index < padding % fills.len() {
+                count += 1;
+            }
+            self.output
+                .insert_str(*position, &fill.to_string().repeat(count));
+        }
+        self.column = target.max(self.column + width);
+        self.segment = self.output.len();
+        self.fills.clear();
+    }
+}
+
+fn integer(term: &Term) -> Result<i64, Exception> {
+    match term {
+        Term::Number { value } => Ok(*value),
+        Term::Variable { .. } => Err(Exception::instantiation_error()),
+        term => Err(Exception::type_error("integer", term.clone())),
+    }
+}
+
+// `~Nd` inserts a decimal point `N` digits from the right.
+fn decimal(value: i64, digits: i64) -> String {
+    if digits <= 0 {
+        return value.to_string();
+    }
+    let digits = digits as usize;
+    let magnitude = format!("{:0>width$}", value.unsigned_abs(), width = digits + 1);
+    let (integer, fraction) = magnitude.split_at(magnitude.len() - digits);
+    let sign = if value < 0 { "-" } else { "" };
+    format!("{}{}.{}", sign, integer, fraction)
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::query;
+    use crate::{assignments::Assignments, database::Database, exception::Exception, term::Term};
+    use test_case::test_case;
+
+    fn format(format: &str, arguments: &str) -> Result<String, Exception> {
+        let code = format!("format(atom(A), \"{}\", {}).", format, arguments);
+        let answers = query(&Database::empty(), &code)?;
+        Ok(answers[0].get("A").unwrap().name().unwrap().to_string())
+    }
+
+    #[test_case("~w and ~q", "[a+'B', 'B']" => "a+B and 'B'")]
+    #[test_case("~p", "['x y']" => "'x y'")]
+    #[test_case("~a|~d|~2d|~1d", "[abc, 42, 1234, 5]" => "abc|42|12.34|0.5")]
+    #[test_case("~s and ~s", "[[104, 105], \"str\"]" => "hi and str")]
+    #[test_case("~c~3c", "[65, 66]" => "ABBB")]
+    #[test_case("a~nb~2n~~", "[]" => "a\nb\n\n~")]
+    #[test_case("~w", "hello" => "hello")]
+    #[test_case("~*c", "[3, 0'x]" => "xxx")]
+    fn directives(format_string: &str, arguments: &str) -> String {
+        format(format_string, arguments).unwrap()
+    }
+
+    #[test_case("~w~t~6|~w", "[ab, cd]" => "ab    cd")]
+    #[test_case("~t~w~6|~w", "[ab, cd]" => "    abcd")]
+    #[test_case("~t~w~t~6|", "[ab]" => "  ab  ")]
+    #[test_case("~`-t~5|", "[]" => "-----")]
+    #[test_case("~w~t~4+~w~t~4+~w", "[a, b, c]" => "a   b   c")]
+    #[test_case("~w~2|~w", "[long, x]" => "longx")]
+    #[test_case("ab~nc~t~3|d", "[]" => "ab\nc  d")]
+    fn columns(format_string: &str, arguments: &str) -> String {
+        format(format_string, arguments).unwrap()
+    }
+
+    #[test_case("~w ~w", "[a]" => Exception::error(Term::functor("format", [Term::atom("not enough arguments")])))]
+    #[test_case("~w", "[a, b]" => Exception::error(Term::functor("format", [Term::atom("too many arguments")])))]
+    #[test_case("~d", "[a]" => Exception::type_error("integer", Term::atom("a")))]
+    #[test_case("~a", "[f(x)]" => Exception::type_error("atomic", Term::functor("f", [Term::atom("x")])))]
+    #[test_case("~z", "[]" => Exception::error(Term::functor("format", [Term::atom("unknown directive ~z")])))]
+    fn errors(format_string: &str, arguments: &str) -> Exception {
+        format(format_string, arguments).unwrap_err()
+    }
+
+    #[test]
+    fn format_to_codes_and_chars() {
+        let result = query(
+            &Database::empty(),
+            "format(codes(C), \"~w\", [ab]), format(chars(D), \"~w\", [ab]).",
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty()
+                .with("C", Term::list([Term::number(97), Term::number(98)]))
+                .with("D", Term::list([Term::atom("a"), Term::atom("b")]))]
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use super::Solver;
+use crate::{exception::Exception, term::Term};
+
+pub fn b_setval(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let key = key(solver, &args[0])?;
+    solver.set_global(&key, &args[1], true);
+    Ok(true)
+}
+
+pub fn nb_setval(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let key = key(solver, &args[0])?;
+    solver.set_global(&key, &args[1], false);
+    Ok(true)
+}
+
+pub fn getval(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let key = key(solver, &args[0])?;
+    match solver.global(&key).cloned() {
+        Some(value) => Ok(solver.unify(&args[1], &value)),
+        None => Err(Exception::existence_error("variable", Term::atom(key))),
+    }
+}
+
+fn key(solver: &Solver, key: &Term) -> Result<String, Exception> {
+    match solver.dereference(key) {
+        Term::Variable { .. } => Err(Exception::instantiation_error()),
+        Term::Functor { name, arguments } if arguments.is_empty() => Ok(name.clone()),
+        key => Err(Exception::type_error("atom", key.clone())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::query;
+    use crate::{
+        assignments::Assignments, consult::consult_str, database::Database, exception::Exception,
+        parser::MinplParser, proof_search::prove, term::Term,
+    };
+    use test_case::test_case;
+
+    fn list(elements: &[&str]) -> Term {
+        Term::list(elements.iter().copied().map(Term::atom))
+    }
+
+    #[test]
+    fn nb_setval_survives_backtracking() {
+        let result = query(
+            &Database::empty(),
+            "nb_setval(seen, []),
+            ((X = a ; X = b ; X = c), nb_getval(seen, L), nb_setval(seen, [X|L]), fail ; true),
+            nb_getval(seen, S).",
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with("S", list(&["c", "b", "a"]))]
+        );
+    }
+
+    #[test]
+    fn b_setval_is_undone_on_backtracking() {
+        let result = query(
+            &Database::empty(),
+            "b_setval(v, start), (b_setval(v, changed), fail ; b_getval(v, V)).",
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with("V", Term::atom("start"))]
+        );
+    }
+
+    #[test]
+    fn b_setval_keeps_the_link_to_bindings() {
+        let result = query(
+            &Database::empty(),
+            "b_setval(v, f(X)), X = a, b_getval(v, V).",
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty()
+                .with("X", Term::atom("a"))
+                .with("V", Term::functor("f", [Term::atom("a")]))]
+        );
+    }
+
+    #[test]
+    fn nb_setval_stores_a_copy() {
+        let result = query(
+            &Database::empty(),
+            "(X = a, nb_setval(v, f(X, Y)), fail ; true), nb_getval(v, f(A, _B)), var(_B), _B \\== Y.",
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with("A", Term::atom("a"))]
+        );
+    }
+
+    #[test]
+    fn nb_setval_is_kept_across_queries() {
+        let mut database = Database::empty();
+        consult_str(&mut database, ":- nb_setval(cache, 1).").unwrap();
+        let run = |database: &mut Database, code: &str| {
+            prove(database, MinplParser::parse_query(code).unwrap())
+        };
+
+        let first = run(
+            &mut database,
+            "nb_getval(cache, X), nb_setval(cache, 2), b_setval(scratch, X).",
+        );
+        let second = run(&mut database, "nb_getval(cache, X).");
+        let third = run(&mut database, "b_getval(scratch, X).");
+
+        assert_eq!(
+            first.unwrap(),
+            vec![Assignments::empty().with("X", Term::number(1))]
+        );
+        assert_eq!(
+            second.unwrap(),
+            vec![Assignments::empty().with("X", Term::number(2))]
+        );
+        assert_eq!(
+            third,
+            Err(Exception::existence_error(
+                "variable",
+                Term::atom("scratch")
+            ))
+        );
+    }
+
+    #[test_case("b_getval(missing, V)." => Exception::existence_error("variable", Term::atom("missing")))]
+    #[test_case("nb_getval(missing, V)." => Exception::existence_error("variable", Term::atom("missing")))]
+    #[test_case("nb_setval(K, 1)." => Exception::instantiation_error())]
+    #[test_case("b_setval(1, 1)." => Exception::type_error("atom", Term::number(1)))]
+    fn errors(code: &str) -> Exception {
+        query(&Database::empty(), code).unwrap_err()
+    }
+}
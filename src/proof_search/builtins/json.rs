@@ -0,0 +1,200 @@
+use super::{format::text, streams::text_sink, write::output, Solver};
+use crate::{
+    exception::Exception,
+    json::{json_to_term, term_to_json, JsonOptions, JsonStrings},
+    proof_search::streams::Input,
+    term::Term,
+};
+use serde_json::Value;
+
+// Reads the next JSON value of a stream, or `end_of_file` at its end.
+pub fn json_read(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let stream = solver.resolve(&args[0]);
+    let default = Term::list([]);
+    let (options, _) = options(solver, args.get(2).unwrap_or(&default))?;
+    let term = match value_text(solver.streams().input(&stream)?) {
+        Some(text) => json_to_term(&parse(&text)?, options)?,
+        None => Term::atom("end_of_file"),
+    };
+    Ok(solver.unify(&args[1], &term))
+}
+
+pub fn json_write(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let value = term_to_json(&solver.resolve(&args[1]))?;
+    output(solver, Some(&args[0]), &value.to_string())
+}
+
+// Parses the text of `Atom` if it is bound, and otherwise writes `Term` as
+// the text type given by the `as(Type)` option, an atom by default.
+pub fn atom_json_term(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let (options, text_type) = options(solver, &args[2])?;
+    match solver.resolve(&args[0]) {
+        Term::Variable { .. } => {
+            let json = term_to_json(&solver.resolve(&args[1]))?.to_string();
+            let sink = Term::functor(&text_type, [args[0].clone()]);
+            let (result, value) = text_sink(&sink, &json).ok_or_else(|| {
+                let option = Term::functor("as", [Term::atom(&text_type)]);
+                Exception::domain_error("json_option", option)
+            })?;
+            Ok(solver.unify(result, &value))
+        }
+        atom => {
+            let term = json_to_term(&parse(&text(&atom)?)?, options)?;
+            Ok(solver.unify(&args[1], &term))
+        }
+    }
+}
+
+fn options(solver: &Solver, options: &Term) -> Result<(JsonOptions, String), Exception> {
+    let mut json_options = JsonOptions::default();
+    let mut text_type = String::from("atom");
+    for option in solver.resolve(options).list_elements()? {
+        match (option.name(), option.arguments()) {
+            (Some("value_string_as"), [value]) => {
+                json_options.strings = match value.name() {
+                    Some("atom") => JsonStrings::Atom,
+                    Some("string") => JsonStrings::String,
+                    _ => return Err(Exception::domain_error("json_option", option.clone())),
+                }
+            }
+            (Some("as"), [value]) => match value.name() {
+                Some(name @ ("atom" | "string" | "codes" | "chars")) => {
+                    text_type = name.to_string()
+                }
+                _ => return Err(Exception::domain_error("json_option", option.clone())),
+            },
+            _ if matches!(option, Term::Variable { .. }) => {
+                return Err(Exception::instantiation_error())
+            }
+            _ => return Err(Exception::domain_error("json_option", option.clone())),
+        }
+    }
+    Ok((json_options, text_type))
+}
+
+fn parse(text: &str) -> Result<Value, Exception> {
+    serde_json::from_str(text).map_err(|error| Exception::syntax_error(format!("json: {}", error)))
+}
+
+// The text of the next value: a bracketed object or array, a string or a
+// bare literal. Parsing it is left to `parse`.
+fn value_text(input: &mut Input) -> Option<String> {
+    while input.next_if(|c| c.is_whitespace()).is_some() {}
+    let mut text = String::new();
+    match *input.peek()? {
+        '{' | '[' => {
+            let mut depth = 0;
+            let mut in_string = false;
+            let mut escaped = false;
+            for c in input.by_ref() {
+                text.push(c);
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' if in_string => escaped = true,
+                    '"' => in_string = !in_string,
+                    '{' | '[' if !in_string => depth += 1,
+                    '}' | ']' if !in_string => depth -= 1,
+                    _ => {}
+                }
+                if depth == 0 {
+                    break;
+                }
+            }
+        }
+        '"' => {
+            text.push(input.next()?);
+            let mut escaped = false;
+            for c in input.by_ref() {
+                text.push(c);
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' => break,
+                    _ => {}
+                }
+            }
+        }
+        _ => {
+            while let Some(c) =
+                input.next_if(|c| !c.is_whitespace() && !matches!(c, ',' | ']' | '}'))
+            {
+                text.push(c);
+            }
+        }
+    }
+    Some(text)
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::query;
+    use crate::{
+        assignments::Assignments, database::Database, exception::Exception, parser::MinplParser,
+        term::Term,
+    };
+    use std::{env, fs};
+    use test_case::test_case;
+
+    fn term(code: &str) -> Term {
+        MinplParser::parse_query(&format!("{}.", code)).unwrap()
+    }
+
+    #[test]
+    fn read_values_from_a_stream() {
+        let path = env::temp_dir().join(format!("minpl-json-{}", std::process::id()));
+        fs::write(&path, "{\"a\": [1, \"x\\\"]\"]}\n true \"s\" 42").unwrap();
+        let code = format!(
+            "open('{}', read, S), json_read(S, A), json_read(S, B),
+            json_read(S, C, [value_string_as(string)]), json_read(S, D), json_read(S, E),
+            close(S).",
+            path.display()
+        );
+
+        let result = query(&Database::empty(), &code);
+        fs::remove_file(&path).unwrap();
+
+        let answer = &result.unwrap()[0];
+        let member = Term::functor(
+            "=",
+            [
+                Term::atom("a"),
+                Term::list([Term::number(1), Term::atom("x\"]")]),
+            ],
+        );
+        assert_eq!(
+            answer.get("A"),
+            Some(&Term::functor("json", [Term::list([member])]))
+        );
+        assert_eq!(answer.get("B"), Some(&term("@(true)")));
+        assert_eq!(answer.get("C"), Some(&Term::string("s")));
+        assert_eq!(answer.get("D"), Some(&Term::number(42)));
+        assert_eq!(answer.get("E"), Some(&Term::atom("end_of_file")));
+    }
+
+    #[test]
+    fn write_values() {
+        let result = query(
+            &Database::empty(),
+            "with_output_to(string(S), (current_output(_O), json_write(_O, json([a=[1, b], c= @(null)])))).",
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with("S", Term::string("{\"a\":[1,\"b\"],\"c\":null}"))]
+        );
+    }
+
+    #[test_case("atom_json_term('{\"k\": \"v\"}', T, [])." => Ok(term("json([k=v])")); "parse")]
+    #[test_case("atom_json_term('[true, \"true\", \"[]\"]', T, [])." => Ok(term("[@(true), true, \"[]\"]")); "constants")]
+    #[test_case("atom_json_term(A, [@(null), null], []), T = A." => Ok(Term::atom("[null,\"null\"]")); "write constants")]
+    #[test_case("atom_json_term(\"[1, \\\"v\\\"]\", T, [value_string_as(string)])." => Ok(term("[1, \"v\"]")); "strings")]
+    #[test_case("atom_json_term(A, json([k=[@(true)]]), []), T = A." => Ok(Term::atom("{\"k\":[true]}")); "write")]
+    #[test_case("atom_json_term(A, [x], [as(string)]), T = A." => Ok(Term::string("[\"x\"]")); "write string")]
+    #[test_case("atom_json_term('[1,', T, [])." => Err(Exception::syntax_error("json: EOF while parsing a value at line 1 column 3")); "syntax error")]
+    #[test_case("atom_json_term('1.5', T, [])." => Err(Exception::representation_error("integer")); "float")]
+    #[test_case("atom_json_term(A, f(x), [])." => Err(Exception::type_error("json_term", term("f(x)"))); "not json")]
+    #[test_case("atom_json_term(A, [], [as(f)])." => Err(Exception::domain_error("json_option", term("as(f)"))); "bad option")]
+    fn atom_json_term(code: &str) -> Result<Term, Exception> {
+        query(&Database::empty(), code).map(|answers| answers[0].get("T").unwrap().clone())
+    }
+}
@@ -0,0 +1,193 @@
+mod all_solutions;
+mod attributes;
+mod clpfd;
+mod comparison;
+mod consult;
+mod control;
+mod datalog;
+mod dynamic;
+mod flags;
+mod format;
+mod globals;
+mod json;
+mod read;
+mod streams;
+mod terms;
+mod type_checks;
+mod write;
+
+use super::Solver;
+use crate::{
+    exception::Exception,
+    term::Term,
+    unification::{unify, UnifyResult},
+};
+
+pub use control::check_body;
+pub use datalog::datalog;
+
+pub type Builtin = fn(&mut Solver, &[Term]) -> Result<bool, Exception>;
+
+pub fn get(name: &str, arity: usize) -> Option<Builtin> {
+    let builtin: Builtin = match (name, arity) {
+        ("call", 1..=8) => control::call,
+        ("\\+", 1) => control::not,
+        ("phrase", 2 | 3) => control::phrase,
+        ("once", 1) => control::once,
+        ("ignore", 1) => control::ignore,
+        ("forall", 2) => control::forall,
+        ("throw", 1) => control::throw,
+        ("catch", 3) => control::catch,
+        ("datalog", 1) => datalog::datalog,
+        ("datalog_rewrite", 2) => datalog::datalog_rewrite,
+        ("findall", 3) => all_solutions::findall,
+        ("findall", 4) => all_solutions::findall_with_tail,
+        ("bagof", 3) => all_solutions::bagof,
+        ("setof", 3) => all_solutions::setof,
+        ("^", 2) => all_solutions::existential,
+        ("aggregate_all", 3) => all_solutions::aggregate_all,
+        ("assert", 1) | ("assertz", 1) => dynamic::assertz,
+        ("asserta", 1) => dynamic::asserta,
+        ("retract", 1) => dynamic::retract,
+        ("retractall", 1) => dynamic::retractall,
+        ("abolish", 1) => dynamic::abolish,
+        ("dynamic", 1) => dynamic::dynamic,
+        ("$erase", 2) => dynamic::erase,
+        ("consult", 1) => consult::consult,
+        (".", 2) => consult::consult_list,
+        ("ensure_loaded", 1) => consult::ensure_loaded,
+        ("use_module", 1 | 2) => consult::use_module,
+        ("put_attr", 3) => attributes::put_attr,
+        ("get_attr", 3) => attributes::get_attr,
+        ("del_attr", 2) => attributes::del_attr,
+        ("$fd_post", 1) => clpfd::post,
+        ("$fd_unify", 2) => clpfd::unify_hook,
+        ("$fd_labeling_options", 6) => clpfd::labeling_options,
+        ("$fd_select", 3) => clpfd::select,
+        ("$fd_bound", 3) => clpfd::bound,
+        ("$fd_values", 3) => clpfd::values,
+        ("$fd_middle", 2) => clpfd::middle,
+        ("$fd_residual", 2) => clpfd::residual,
+        ("set_prolog_flag", 2) => flags::set_prolog_flag,
+        ("current_prolog_flag", 2) => flags::current_prolog_flag,
+        ("b_setval", 2) => globals::b_setval,
+        ("b_getval", 2) | ("nb_getval", 2) => globals::getval,
+        ("nb_setval", 2) => globals::nb_setval,
+        ("read", 1 | 2) => read::read,
+        ("read_term", 2 | 3) => read::read_term,
+        ("read_term_from_atom", 3) => read::read_term_from_atom,
+        ("write", 1 | 2) => write::write,
+        ("print", 1 | 2) | ("writeq", 1 | 2) => write::writeq,
+        ("write_canonical", 1 | 2) => write::write_canonical,
+        ("nl", 0 | 1) => write::nl,
+        ("tab", 1 | 2) => write::tab,
+        ("open", 3 | 4) => streams::open,
+        ("close", 1) => streams::close,
+        ("current_input", 1) => streams::current_input,
+        ("current_output", 1) => streams::current_output,
+        ("set_input", 1) => streams::set_input,
+        ("set_output", 1) => streams::set_output,
+        ("get_char", 1 | 2) => streams::get_char,
+        ("peek_char", 1 | 2) => streams::peek_char,
+        ("put_char", 1 | 2) => streams::put_char,
+        ("read_line_to_string", 2) => streams::read_line_to_string,
+        ("with_output_to", 2) => streams::with_output_to,
+        ("format", 1 | 2) => format::format,
+        ("format", 3) => format::format_to,
+        ("json_read", 2 | 3) => json::json_read,
+        ("json_write", 2) => json::json_write,
+        ("atom_json_term", 3) => json::atom_json_term,
+        ("=", 2) => unifiable,
+        ("\\=", 2) => not_unifiable,
+        ("compare", 3) => comparison::compare,
+        ("==", 2) => comparison::identical,
+        ("\\==", 2) => comparison::not_identical,
+        ("@<", 2) => comparison::less,
+        ("@>", 2) => comparison::greater,
+        ("@=<", 2) => comparison::less_or_equal,
+        ("@>=", 2) => comparison::greater_or_equal,
+        ("sort", 2) => comparison::sort,
+        ("msort", 2) => comparison::msort,
+        ("sort", 4) => comparison::sort_by_key,
+        ("predsort", 3) => comparison::predsort,
+        ("keysort", 2) => comparison::keysort,
+        ("functor", 3) => terms::functor,
+        ("arg", 3) => terms::arg,
+        ("=..", 2) => terms::univ,
+        ("copy_term", 2) => terms::copy_term,
+        ("term_variables", 2) => terms::term_variables,
+        ("setarg", 3) => terms::setarg,
+        ("unifiable", 3) => terms::unifiable,
+        ("var", 1) => type_checks::var,
+        ("nonvar", 1) => type_checks::nonvar,
+        ("atom", 1) => type_checks::atom,
+        ("atomic", 1) => type_checks::atomic,
+        ("compound", 1) => type_checks::compound,
+        ("callable", 1) => type_checks::callable,
+        ("is_list", 1) => type_checks::is_list,
+        ("ground", 1) => type_checks::ground,
+        ("number", 1) => type_checks::number,
+        ("integer", 1) => type_checks::integer,
+        _ => return None,
+    };
+    Some(builtin)
+}
+
+pub fn is_builtin(name: &str, arity: usize) -> bool {
+    let control = matches!(
+        (name, arity),
+        ("true" | "fail" | "false" | "!", 0) | ("," | ";" | "->", 2)
+    );
+    control || get(name, arity).is_some()
+}
+
+fn strip_module(term: Term, module: &str) -> Result<(String, Term), Exception> {
+    let mut module = module.to_string();
+    let mut term = term;
+    loop {
+        match term {
+            Term::Functor {
+                ref name,
+                ref mut arguments,
+            } if name == ":" && arguments.len() == 2 => {
+                let inner = arguments.pop().unwrap();
+                module = match arguments.pop().unwrap() {
+                    Term::Functor {
+                        ref name,
+                        ref arguments,
+                    } if arguments.is_empty() => name.clone(),
+                    Term::Variable { .. } => return Err(Exception::instantiation_error()),
+                    module => return Err(Exception::type_error("atom", module)),
+                };
+                term = inner;
+            }
+            term => return Ok((module, term)),
+        }
+    }
+}
+
+fn unifiable(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    Ok(solver.unify(&args[0], &args[1]))
+}
+
+fn not_unifiable(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let a = solver.resolve(&args[0]);
+    let b = solver.resolve(&args[1]);
+    Ok(unify(a, b) == UnifyResult::False)
+}
+
+fn check_partial_list(list: &Term) -> Result<(), Exception> {
+    match list.list_elements() {
+        Err(exception) if exception == Exception::instantiation_error() => Ok(()),
+        result => result.map(|_| ()),
+    }
+}
+
+#[cfg(test)]
+fn query(
+    database: &crate::database::Database,
+    code: &str,
+) -> Result<Vec<crate::assignments::Assignments>, Exception> {
+    let goal = crate::parser::MinplParser::parse_query(code).unwrap();
+    super::prove(&mut database.clone(), goal)
+}
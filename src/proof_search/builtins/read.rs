@@ -0,0 +1,218 @@
+use super::{format::text, Solver};
+use crate::{exception::Exception, parser::MinplParser, proof_search::streams::Input, term::Term};
+use std::collections::HashMap;
+
+pub fn read(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let (stream, term) = match args {
+        [stream, term] => (solver.resolve(stream), term),
+        [term] => (solver.streams().current_input(), term),
+        _ => unreachable!(),
+    };
+    read_term_with(solver, Source::Stream(stream), term, &Term::list([]))
+}
+
+pub fn read_term(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let (stream, term, options) = match args {
+        [stream, term, options] => (solver.resolve(stream), term, options),
+        [term, options] => (solver.streams().current_input(), term, options),
+        _ => unreachable!(),
+    };
+    read_term_with(solver, Source::Stream(stream), term, options)
+}
+
+pub fn read_term_from_atom(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let chars: Vec<_> = text(&solver.resolve(&args[0]))?.chars().collect();
+    let chars = (Box::new(chars.into_iter()) as Box<dyn Iterator<Item = char>>).peekable();
+    read_term_with(solver, Source::Text(chars), &args[1], &args[2])
+}
+
+enum Source {
+    Stream(Term),
+    Text(Input),
+}
+
+#[derive(PartialEq, Eq)]
+enum SyntaxErrors {
+    Error,
+    Fail,
+    Quiet,
+    Dec10,
+}
+
+fn read_term_with(
+    solver: &mut Solver,
+    mut source: Source,
+    term: &Term,
+    options: &Term,
+) -> Result<bool, Exception> {
+    if let Source::Stream(stream) = &source {
+        solver.streams().input(stream)?;
+    }
+    let options = solver.resolve(options).list_elements()?;
+    let mut syntax_errors = SyntaxErrors::Error;
+    for option in &options {
+        match (option.name(), option.arguments()) {
+            (Some("variable_names" | "variables" | "singletons"), [_]) => {}
+            (Some("syntax_errors"), [value]) => {
+                syntax_errors = match value.name() {
+                    Some("error") => SyntaxErrors::Error,
+                    Some("fail") => SyntaxErrors::Fail,
+                    Some("quiet") => SyntaxErrors::Quiet,
+                    Some("dec10") => SyntaxErrors::Dec10,
+                    _ => return Err(Exception::domain_error("read_option", option.clone())),
+                }
+            }
+            _ if matches!(option, Term::Variable { .. }) => {
+                return Err(Exception::instantiation_error())
+            }
+            _ => return Err(Exception::domain_error("read_option", option.clone())),
+        }
+    }
+    let read = loop {
+        let sentence = match &mut source {
+            Source::Stream(stream) => MinplParser::read_sentence(solver.streams().input(stream)?),
+            Source::Text(chars) => MinplParser::read_sentence(chars),
+        };
+        let sentence = match sentence {
+            Some(sentence) => sentence,
+            None => break Term::atom("end_of_file"),
+        };
+        // Text read from an atom may leave out the final end.
+        let parsed = MinplParser::parse_query(&sentence).or_else(|error| match source {
+            Source::Text(_) => {
+                MinplParser::parse_query(&format!("{} .", sentence)).map_err(|_| error)
+            }
+            Source::Stream(_) => Err(error),
+        });
+        match parsed {
+            Ok(read) => break read,
+            Err(error) => match syntax_errors {
+                SyntaxErrors::Error => return Err(Exception::syntax_error(error)),
+                SyntaxErrors::Fail => return Ok(false),
+                SyntaxErrors::Quiet => return Ok(false),
+                SyntaxErrors::Dec10 => eprintln!("{}", Exception::syntax_error(error)),
+            },
+        }
+    };
+    let names = read.variables();
+    let mut occurrences = HashMap::new();
+    count_occurrences(&read, &mut occurrences);
+    let renaming: HashMap<_, _> = names
+        .iter()
+        .map(|name| (name.clone(), solver.fresh_variable()))
+        .collect();
+    let named = |name: &&String| !name.starts_with('_');
+    let binding = |name: &String| Term::functor("=", [Term::atom(name), renaming[name].clone()]);
+    let results = options.iter().filter_map(|option| {
+        let names = names.iter();
+        let value = match option.name()? {
+            "variable_names" => Term::list(names.filter(named).map(binding)),
+            "variables" => Term::list(names.map(|name| renaming[name].clone())),
+            "singletons" => Term::list(
+                names
+                    .filter(named)
+                    .filter(|name| occurrences[*name] == 1)
+                    .map(binding),
+            ),
+            _ => return None,
+        };
+        Some((option.arguments()[0].clone(), value))
+    });
+    let mut unifications = vec![(term.clone(), rename(&read, &renaming))];
+    unifications.extend(results);
+    Ok(unifications
+        .iter()
+        .all(|(left, right)| solver.unify(left, right)))
+}
+
+fn count_occurrences(term: &Term, occurrences: &mut HashMap<String, usize>) {
+    match term {
+        Term::Variable { name } => *occurrences.entry(name.clone()).or_default() += 1,
+        Term::Functor { arguments, .. } => {
+            for argument in arguments {
+                count_occurrences(argument, occurrences);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn rename(term: &Term, renaming: &HashMap<String, Term>) -> Term {
+    match term {
+        Term::Variable { name } => renaming[name].clone(),
+        Term::Functor { name, arguments } => Term::functor(
+            name,
+            arguments.iter().map(|argument| rename(argument, renaming)),
+        ),
+        term => term.clone(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::query;
+    use crate::{assignments::Assignments, database::Database, exception::Exception, term::Term};
+
+    #[test]
+    fn read_term_options() {
+        let result = query(
+            &Database::empty(),
+            "read_term_from_atom('g(A, B, A, _C, _).', T, [variable_names(N), singletons(S)]),
+            T = g(X, Y, Z, _, _), X == Z,
+            N = ['A' = X1, 'B' = Y1], X1 == X, Y1 == Y,
+            S = ['B' = Y2], Y2 == Y.",
+        );
+
+        assert_eq!(result.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn read_term_variables_are_fresh() {
+        let result = query(
+            &Database::empty(),
+            "X = a, read_term_from_atom('f(X, Y, X)', T, [variables(V)]), T = f(A, b, C), A == C.",
+        );
+
+        assert_eq!(
+            result.unwrap()[0].get("V").map(|vars| vars.arity()),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn end_of_file_when_nothing_is_left() {
+        let result = query(
+            &Database::empty(),
+            "read_term_from_atom(' % only a comment', T, []).",
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with("T", Term::atom("end_of_file"))]
+        );
+    }
+
+    #[test]
+    fn syntax_errors_option() {
+        let error = query(&Database::empty(), "read_term_from_atom('f(', T, []).").unwrap_err();
+        let failed = query(
+            &Database::empty(),
+            "read_term_from_atom('f(', T, [syntax_errors(fail)]).",
+        );
+
+        assert_eq!(error.ball().arguments()[0].name(), Some("syntax_error"));
+        assert_eq!(failed.unwrap(), vec![]);
+    }
+
+    #[test]
+    fn invalid_options() {
+        assert_eq!(
+            query(&Database::empty(), "read_term_from_atom(a, T, [foo]).").unwrap_err(),
+            Exception::domain_error("read_option", Term::atom("foo"))
+        );
+        assert_eq!(
+            query(&Database::empty(), "read_term(user_output, T, []).").unwrap_err(),
+            Exception::permission_error("input", "stream", Term::atom("user_output"))
+        );
+    }
+}
@@ -0,0 +1,321 @@
+use super::{
+    format::{chars, codes},
+    Solver,
+};
+use crate::{exception::Exception, proof_search::streams::Input, term::Term};
+use std::{io, path::Path};
+
+pub fn open(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let file = solver.resolve(&args[0]);
+    let path = match &file {
+        Term::Variable { .. } => return Err(Exception::instantiation_error()),
+        Term::Functor { name, arguments } if arguments.is_empty() => name.clone(),
+        Term::Str { value } => value.clone(),
+        file => return Err(Exception::domain_error("source_sink", file.clone())),
+    };
+    let mode = match solver.resolve(&args[1]) {
+        Term::Variable { .. } => return Err(Exception::instantiation_error()),
+        mode if matches!(mode.name(), Some("read" | "write" | "append")) && mode.arity() == 0 => {
+            mode.name().unwrap().to_string()
+        }
+        mode if mode.arity() == 0 && mode.name().is_some() => {
+            return Err(Exception::domain_error("io_mode", mode))
+        }
+        mode => return Err(Exception::type_error("atom", mode)),
+    };
+    if !matches!(solver.dereference(&args[2]), Term::Variable { .. }) {
+        return Err(Exception::uninstantiation_error(solver.resolve(&args[2])));
+    }
+    let options = match args.get(3) {
+        Some(options) => solver.resolve(options).list_elements()?,
+        None => vec![],
+    };
+    let mut alias = None;
+    for option in options {
+        match (option.name(), option.arguments()) {
+            (Some("alias"), [Term::Functor { name, arguments }]) if arguments.is_empty() => {
+                if solver.streams().is_alias(name) {
+                    return Err(Exception::permission_error("open", "source_sink", option));
+                }
+                alias = Some(name.clone());
+            }
+            (Some("type" | "encoding" | "eof_action" | "reposition"), [_]) => {}
+            (None, _) if matches!(option, Term::Variable { .. }) => {
+                return Err(Exception::instantiation_error())
+            }
+            _ => return Err(Exception::domain_error("stream_option", option)),
+        }
+    }
+    let stream = solver
+        .streams()
+        .open(Path::new(&path), &mode, alias)
+        .map_err(|error| match error.kind() {
+            io::ErrorKind::NotFound => Exception::existence_error("source_sink", file.clone()),
+            _ => Exception::permission_error("open", "source_sink", file.clone()),
+        })?;
+    Ok(solver.unify(&args[2], &stream))
+}
+
+pub fn close(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let stream = solver.resolve(&args[0]);
+    solver.streams().close(&stream)?;
+    Ok(true)
+}
+
+pub fn current_input(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let stream = solver.streams().current_input();
+    current_stream(solver, &args[0], stream)
+}
+
+pub fn current_output(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let stream = solver.streams().current_output();
+    current_stream(solver, &args[0], stream)
+}
+
+fn current_stream(solver: &mut Solver, argument: &Term, stream: Term) -> Result<bool, Exception> {
+    match solver.resolve(argument) {
+        Term::Variable { .. } => Ok(solver.unify(argument, &stream)),
+        argument if argument.name() == Some("$stream") => Ok(argument == stream),
+        argument => Err(Exception::domain_error("stream", argument)),
+    }
+}
+
+pub fn set_input(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let stream = solver.resolve(&args[0]);
+    solver.streams().set_input(&stream)?;
+    Ok(true)
+}
+
+pub fn set_output(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let stream = solver.resolve(&args[0]);
+    solver.streams().set_output(&stream)?;
+    Ok(true)
+}
+
+pub fn get_char(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    read_char(solver, args, |input| input.next())
+}
+
+pub fn peek_char(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    read_char(solver, args, |input| input.peek().copied())
+}
+
+fn read_char(
+    solver: &mut Solver,
+    args: &[Term],
+    read: fn(&mut Input) -> Option<char>,
+) -> Result<bool, Exception> {
+    let (stream, argument) = input_arguments(solver, args);
+    match solver.resolve(argument) {
+        Term::Variable { .. } => {}
+        Term::Functor {
+            ref name,
+            ref arguments,
+        } if arguments.is_empty() && (name.chars().count() == 1 || name == "end_of_file") => {}
+        argument => return Err(Exception::type_error("in_character", argument)),
+    }
+    let c = read(solver.streams().input(&stream)?);
+    let c = c.map_or_else(|| Term::atom("end_of_file"), Term::atom);
+    Ok(solver.unify(argument, &c))
+}
+
+pub fn put_char(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let (stream, argument) = match args {
+        [stream, argument] => (solver.resolve(stream), argument),
+        [argument] => (solver.streams().current_output(), argument),
+        _ => unreachable!(),
+    };
+    match solver.resolve(argument) {
+        Term::Variable { .. } => Err(Exception::instantiation_error()),
+        Term::Functor {
+            ref name,
+            ref arguments,
+        } if arguments.is_empty() && name.chars().count() == 1 => {
+            solver.streams().write(&stream, name)?;
+            Ok(true)
+        }
+        argument => Err(Exception::type_error("character", argument)),
+    }
+}
+
+// A line without its end, or `end_of_file` when nothing is left.
+pub fn read_line_to_string(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let stream = solver.resolve(&args[0]);
+    let input = solver.streams().input(&stream)?;
+    let mut line = String::new();
+    let mut ended = false;
+    for c in input.by_ref() {
+        ended = true;
+        if c == '\n' {
+            break;
+        }
+        line.push(c);
+    }
+    let line = match ended {
+        true => Term::string(line.strip_suffix('\r').unwrap_or(&line)),
+        false => Term::atom("end_of_file"),
+    };
+    Ok(solver.unify(&args[1], &line))
+}
+
+// Runs `Goal` once with its output collected into `atom(A)`, `string(S)`,
+// `codes(C)` or `chars(C)`.
+pub fn with_output_to(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let sink = solver.resolve(&args[0]);
+    if text_sink(&sink, "").is_none() {
+        return match sink {
+            Term::Variable { .. } => Err(Exception::instantiation_error()),
+            sink => Err(Exception::domain_error("output_sink", sink)),
+        };
+    }
+    let memory = solver.streams().open_memory();
+    let previous = solver.streams().current_output();
+    solver.streams().set_output(&memory)?;
+    let result = solver.solve_once(args[1].clone());
+    if solver.streams().set_output(&previous).is_err() {
+        solver.streams().set_output(&Term::atom("user_output"))?;
+    }
+    let text = solver.streams().close(&memory)?.unwrap_or_default();
+    match result? {
+        true => {
+            let (result, value) = text_sink(&sink, &text).unwrap();
+            Ok(solver.unify(result, &value))
+        }
+        false => Ok(false),
+    }
+}
+
+// The variable and value for text written to `atom(A)`, `string(S)`,
+// `codes(C)` or `chars(C)`.
+pub fn text_sink<'t>(sink: &'t Term, text: &str) -> Option<(&'t Term, Term)> {
+    let value = match sink.name()? {
+        "atom" => Term::atom(text),
+        "string" => Term::string(text),
+        "codes" => codes(text),
+        "chars" => chars(text),
+        _ => return None,
+    };
+    match sink.arguments() {
+        [result] => Some((result, value)),
+        _ => None,
+    }
+}
+
+fn input_arguments<'a>(solver: &mut Solver, args: &'a [Term]) -> (Term, &'a Term) {
+    match args {
+        [stream, argument] => (solver.resolve(stream), argument),
+        [argument] => (solver.streams().current_input(), argument),
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::query;
+    use crate::{
+        assignments::Assignments, database::Database, exception::Exception, parser::MinplParser,
+        proof_search::prove, term::Term,
+    };
+    use std::{env, fs};
+    use test_case::test_case;
+
+    fn temp_file(name: &str) -> String {
+        let path = env::temp_dir().join(format!("minpl-{}-{}", name, std::process::id()));
+        path.display().to_string()
+    }
+
+    #[test]
+    fn write_and_read_back_a_file() {
+        let path = temp_file("streams");
+        let code = format!(
+            "open('{0}', write, W), write(W, f(x)), put_char(W, '.'), nl(W), close(W),
+            open('{0}', append, A, [alias(log)]), format(log, \"line ~w~n\", [2]), close(log),
+            open('{0}', read, R), read(R, T), peek_char(R, P), get_char(R, C),
+            read_line_to_string(R, L), read_line_to_string(R, E), close(R).",
+            path
+        );
+
+        let result = query(&Database::empty(), &code);
+        fs::remove_file(&path).unwrap();
+
+        let answer = &result.unwrap()[0];
+        assert_eq!(
+            answer.get("T"),
+            Some(&Term::functor("f", [Term::atom("x")]))
+        );
+        assert_eq!(answer.get("P"), Some(&Term::atom("l")));
+        assert_eq!(answer.get("C"), Some(&Term::atom("l")));
+        assert_eq!(answer.get("L"), Some(&Term::string("ine 2")));
+        assert_eq!(answer.get("E"), Some(&Term::atom("end_of_file")));
+    }
+
+    #[test]
+    fn streams_are_kept_across_queries() {
+        let path = temp_file("queries");
+        let mut database = Database::empty();
+        let mut run = |code: String| prove(&mut database, MinplParser::parse_query(&code).unwrap());
+
+        let opened = run(format!(
+            "open('{}', write, S, [alias(log)]), set_output(log).",
+            path
+        ));
+        let written = run(String::from(
+            "write(first), write(log, ' second'), close(log).",
+        ));
+        let content = fs::read_to_string(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(opened.is_ok());
+        assert_eq!(written, Ok(vec![Assignments::empty()]));
+        assert_eq!(content.unwrap(), "first second");
+    }
+
+    #[test_case("with_output_to(atom(A), (write(a), nl, print('B')))" => Term::atom("a\n'B'"))]
+    #[test_case("with_output_to(string(A), format(\"~w-~w\", [x, y]))" => Term::string("x-y"))]
+    #[test_case("with_output_to(codes(A), write(hi))" => Term::list([Term::number(104), Term::number(105)]))]
+    #[test_case("with_output_to(chars(A), (current_output(S), put_char(S, c)))" => Term::list([Term::atom("c")]))]
+    fn with_output_to(code: &str) -> Term {
+        let result = query(&Database::empty(), &format!("{}.", code));
+        result.unwrap()[0].get("A").unwrap().clone()
+    }
+
+    #[test]
+    fn with_output_to_restores_the_output() {
+        let result = query(
+            &Database::empty(),
+            "current_output(S), catch(with_output_to(atom(_), throw(e)), e, true),
+            \\+ with_output_to(atom(_), fail), current_output(S).",
+        );
+
+        assert_eq!(result.unwrap().len(), 1);
+    }
+
+    #[test_case("open(F, read, S)" => Exception::instantiation_error())]
+    #[test_case("open(f, update, S)" => Exception::domain_error("io_mode", Term::atom("update")))]
+    #[test_case("open(f, read, s)" => Exception::uninstantiation_error(Term::atom("s")))]
+    #[test_case("open('/nonexistent/file', read, S)" => Exception::existence_error("source_sink", Term::atom("/nonexistent/file")))]
+    #[test_case("open(f, read, S, [bad])" => Exception::domain_error("stream_option", Term::atom("bad")))]
+    #[test_case("open(f, read, S, [alias(user_error)])" => Exception::permission_error("open", "source_sink", Term::functor("alias", [Term::atom("user_error")])))]
+    #[test_case("close(nope)" => Exception::existence_error("stream", Term::atom("nope")))]
+    #[test_case("close(f(x))" => Exception::domain_error("stream_or_alias", Term::functor("f", [Term::atom("x")])))]
+    #[test_case("get_char(user_output, C)" => Exception::permission_error("input", "stream", Term::atom("user_output")))]
+    #[test_case("put_char(user_input, a)" => Exception::permission_error("output", "stream", Term::atom("user_input")))]
+    #[test_case("put_char(user_output, ab)" => Exception::type_error("character", Term::atom("ab")))]
+    #[test_case("get_char(user_input, 1)" => Exception::type_error("in_character", Term::number(1)))]
+    #[test_case("with_output_to(file, true)" => Exception::domain_error("output_sink", Term::atom("file")))]
+    fn stream_errors(code: &str) -> Exception {
+        query(&Database::empty(), &format!("{}.", code)).unwrap_err()
+    }
+
+    #[test]
+    fn user_streams_are_current_by_default() {
+        let result = query(&Database::empty(), "current_input(I), current_output(O).");
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty()
+                .with("I", Term::functor("$stream", [Term::number(0)]))
+                .with("O", Term::functor("$stream", [Term::number(1)]))]
+        );
+    }
+}
@@ -0,0 +1,437 @@
+use super::Solver;
+use crate::{
+    exception::Exception,
+    term::Term,
+    unification::{unify, UnifyResult},
+};
+
+pub fn functor(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    match solver.resolve(&args[0]) {
+        Term::Variable { .. } => {
+            let name = solver.resolve(&args[1]);
+            let arity = match solver.resolve(&args[2]) {
+                Term::Number { value } if value < 0 => {
+                    return Err(Exception::domain_error(
+                        "not_less_than_zero",
+                        Term::number(value),
+                    ))
+                }
+                Term::Number { value } => value as usize,
+                Term::Variable { .. } => return Err(Exception::instantiation_error()),
+                arity => return Err(Exception::type_error("integer", arity)),
+            };
+            let term = match name {
+                Term::Variable { .. } => return Err(Exception::instantiation_error()),
+                name if arity == 0 && name.arity() == 0 => name,
+                Term::Functor {
+                    ref name,
+                    ref arguments,
+                } if arguments.is_empty() => {
+                    let arguments = (0..arity)
+                        .map(|_| solver.fresh_variable())
+                        .collect::<Vec<_>>();
+                    Term::functor(name, arguments)
+                }
+                name @ Term::Functor { .. } => return Err(Exception::type_error("atomic", name)),
+                name => return Err(Exception::type_error("atom", name)),
+            };
+            Ok(solver.unify(&args[0], &term))
+        }
+        term => {
+            let name = match &term {
+                Term::Functor { name, .. } => Term::atom(name),
+                atomic => atomic.clone(),
+            };
+            let arity = Term::number(term.arity() as i64);
+            Ok(solver.unify(&args[1], &name) && solver.unify(&args[2], &arity))
+        }
+    }
+}
+
+// The argument is unified through a term of fresh variables, so that it is
+// shared with the compound's value rather than copied out of it.
+pub fn arg(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let term = compound_argument(solver, &args[1])?;
+    share_arguments(solver, &args[1]);
+    match solver.resolve(&args[0]) {
+        Term::Number { value } if value > 0 && value as usize <= term.arity() => {
+            let pattern = argument_pattern(solver, &term, value as usize, &args[2]);
+            Ok(solver.unify(&args[1], &pattern))
+        }
+        Term::Number { .. } => Ok(false),
+        Term::Variable { .. } => {
+            let alternatives = (1..=term.arity())
+                .map(|position| {
+                    let pattern = argument_pattern(solver, &term, position, &args[2]);
+                    Term::functor(
+                        ",",
+                        [
+                            Term::functor("=", [args[0].clone(), Term::number(position as i64)]),
+                            Term::functor("=", [args[1].clone(), pattern]),
+                        ],
+                    )
+                })
+                .collect();
+            Ok(solver.alternatives(alternatives))
+        }
+        position => Err(Exception::type_error("integer", position)),
+    }
+}
+
+// A term with the name and arity of `term`, `argument` at `position` and fresh
+// variables elsewhere.
+fn argument_pattern(solver: &mut Solver, term: &Term, position: usize, argument: &Term) -> Term {
+    let arguments = (1..=term.arity())
+        .map(|index| match index == position {
+            true => argument.clone(),
+            false => solver.fresh_variable(),
+        })
+        .collect::<Vec<_>>();
+    Term::functor(term.name().unwrap_or_default(), arguments)
+}
+
+// Puts each compound argument of the value of the variable holding `term` in a
+// variable of its own, so that changing the argument with setarg/3 changes it
+// in `term` too. The value is rebound, so this is undone on backtracking.
+fn share_arguments(solver: &mut Solver, term: &Term) {
+    let Some(holder) = solver.bound_variable(term) else {
+        return;
+    };
+    let value = solver.dereference(term).clone();
+    if value
+        .arguments()
+        .iter()
+        .all(|argument| argument.arity() == 0)
+    {
+        return;
+    }
+    let mut arguments = vec![];
+    for argument in value.arguments() {
+        match argument.arity() {
+            0 => arguments.push(argument.clone()),
+            _ => {
+                let cell = solver.fresh_variable();
+                solver.unify(&cell, argument);
+                arguments.push(cell);
+            }
+        }
+    }
+    solver.rebind(
+        &holder,
+        Term::functor(value.name().unwrap_or_default(), arguments),
+    );
+}
+
+pub fn univ(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    match &mut solver.resolve(&args[0]) {
+        Term::Variable { .. } => {
+            let mut elements = solver.resolve(&args[1]).list_elements()?.into_iter();
+            let term = match (elements.next(), elements.len()) {
+                (None, _) => return Err(Exception::domain_error("non_empty_list", Term::list([]))),
+                (Some(Term::Variable { .. }), _) => return Err(Exception::instantiation_error()),
+                (Some(name), 0) if name.arity() == 0 => name,
+                (
+                    Some(Term::Functor {
+                        ref name,
+                        ref arguments,
+                    }),
+                    _,
+                ) if arguments.is_empty() => Term::functor(name, elements),
+                (Some(name @ Term::Functor { .. }), _) => {
+                    return Err(Exception::type_error("atomic", name))
+                }
+                (Some(name), _) => return Err(Exception::type_error("atom", name)),
+            };
+            Ok(solver.unify(&args[0], &term))
+        }
+        Term::Functor { name, arguments } => {
+            let arguments = std::mem::take(arguments);
+            let list = Term::list([Term::atom(name)].into_iter().chain(arguments));
+            Ok(solver.unify(&args[1], &list))
+        }
+        atomic => Ok(solver.unify(&args[1], &Term::list([atomic.clone()]))),
+    }
+}
+
+pub fn copy_term(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let copy = solver.rename(&solver.resolve(&args[0]));
+    Ok(solver.unify(&args[1], &copy))
+}
+
+pub fn term_variables(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let variables = solver
+        .resolve(&args[0])
+        .variables()
+        .into_iter()
+        .map(Term::variable);
+    Ok(solver.unify(&args[1], &Term::list(variables)))
+}
+
+// The most general unifier as a list of `Variable = Value`, without binding
+// anything.
+pub fn unifiable(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let a = solver.resolve(&args[0]);
+    let b = solver.resolve(&args[1]);
+    let mut variables = a.variables();
+    variables.extend(b.variables());
+    let assignments = match unify(a, b) {
+        UnifyResult::True(assignments) => assignments,
+        UnifyResult::False => return Ok(false),
+    };
+    let mut unifier = vec![];
+    for name in variables {
+        if let Some(value) = assignments.get(&name) {
+            if !unifier.iter().any(|(bound, _)| *bound == name) {
+                unifier.push((name, value.clone()));
+            }
+        }
+    }
+    let unifier = unifier
+        .into_iter()
+        .map(|(name, value)| Term::functor("=", [Term::variable(name), value]));
+    Ok(solver.unify(&args[2], &Term::list(unifier)))
+}
+
+// Terms are values, so the change is made by rebinding the variable holding the
+// compound term: it is undone on backtracking like any other binding. Only the
+// value of a variable can be changed in place.
+pub fn setarg(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let term = compound_argument(solver, &args[1])?;
+    let position = match solver.resolve(&args[0]) {
+        Term::Number { value } if value > 0 && (value as usize) <= term.arity() => value as usize,
+        Term::Number { .. } => return Ok(false),
+        Term::Variable { .. } => return Err(Exception::instantiation_error()),
+        position => return Err(Exception::type_error("integer", position)),
+    };
+    let variable = match solver.bound_variable(&args[1]) {
+        Some(variable) => variable,
+        None => return Err(Exception::permission_error("modify", "compound", term)),
+    };
+    if let Term::Functor {
+        ref name,
+        ref mut arguments,
+    } = solver.dereference(&args[1]).clone()
+    {
+        arguments[position - 1] = args[2].clone();
+        solver.rebind(&variable, Term::functor(name, std::mem::take(arguments)));
+    }
+    Ok(true)
+}
+
+fn compound_argument(solver: &Solver, term: &Term) -> Result<Term, Exception> {
+    match solver.resolve(term) {
+        term @ Term::Functor { .. } if term.arity() > 0 => Ok(term),
+        Term::Variable { .. } => Err(Exception::instantiation_error()),
+        term => Err(Exception::type_error("compound", term)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::query;
+    use crate::{assignments::Assignments, database::Database, exception::Exception, term::Term};
+    use test_case::test_case;
+
+    #[test]
+    fn functor_of_compound_term() {
+        let result = query(&Database::empty(), "functor(f(a, b), N, A).");
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty()
+                .with("N", Term::atom("f"))
+                .with("A", Term::number(2))]
+        );
+    }
+
+    #[test]
+    fn functor_builds_term_with_fresh_arguments() {
+        let result = query(
+            &Database::empty(),
+            "functor(T, f, 2), T = f(X, Y), X \\== Y.",
+        );
+
+        assert_eq!(result.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn functor_with_unbound_name_is_an_instantiation_error() {
+        let result = query(&Database::empty(), "functor(T, N, 1).");
+
+        assert_eq!(result, Err(Exception::instantiation_error()));
+    }
+
+    #[test]
+    fn functor_with_compound_name_is_a_type_error() {
+        let result = query(&Database::empty(), "functor(T, f(a), 1).");
+
+        assert_eq!(
+            result,
+            Err(Exception::type_error(
+                "atomic",
+                Term::functor("f", [Term::atom("a")])
+            ))
+        );
+    }
+
+    #[test]
+    fn arg_enumerates_arguments() {
+        let result = query(&Database::empty(), "arg(N, f(a, b), X).");
+
+        assert_eq!(
+            result.unwrap(),
+            vec![
+                Assignments::empty()
+                    .with("N", Term::number(1))
+                    .with("X", Term::atom("a")),
+                Assignments::empty()
+                    .with("N", Term::number(2))
+                    .with("X", Term::atom("b")),
+            ]
+        );
+    }
+
+    #[test]
+    fn arg_of_atom_is_a_type_error() {
+        let result = query(&Database::empty(), "arg(1, foo, X).");
+
+        assert_eq!(
+            result,
+            Err(Exception::type_error("compound", Term::atom("foo")))
+        );
+    }
+
+    #[test]
+    fn unifiable_returns_the_unifier_without_binding() {
+        let result = query(
+            &Database::empty(),
+            "unifiable(f(X, b), f(a, Y), U), var(X).",
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with(
+                "U",
+                Term::list([
+                    Term::functor("=", [Term::variable("X"), Term::atom("a")]),
+                    Term::functor("=", [Term::variable("Y"), Term::atom("b")]),
+                ])
+            )]
+        );
+    }
+
+    #[test]
+    fn univ_decomposes_and_builds_terms() {
+        let result = query(&Database::empty(), "f(a, B) =.. L, T =.. [g, 1].");
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty()
+                .with(
+                    "L",
+                    Term::list([Term::atom("f"), Term::atom("a"), Term::variable("B")])
+                )
+                .with("T", Term::functor("g", [Term::number(1)]))]
+        );
+    }
+
+    #[test]
+    fn univ_with_partial_list_is_an_instantiation_error() {
+        let result = query(&Database::empty(), "T =.. [f|Args].");
+
+        assert_eq!(result, Err(Exception::instantiation_error()));
+    }
+
+    #[test]
+    fn copy_term_renames_variables_consistently() {
+        let result = query(
+            &Database::empty(),
+            "copy_term(f(X, Y, X), f(A, B, C)), A == C, A \\== X, B \\== Y.",
+        );
+
+        assert_eq!(result.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn term_variables_in_depth_first_order() {
+        let result = query(&Database::empty(), "term_variables(f(X, g(Y, X), Z), V).");
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with(
+                "V",
+                Term::list([
+                    Term::variable("X"),
+                    Term::variable("Y"),
+                    Term::variable("Z")
+                ])
+            )]
+        );
+    }
+
+    #[test]
+    fn setarg_is_undone_on_backtracking() {
+        let database = crate::parser::MinplParser::parse_database(
+            "choice(T) :- setarg(1, T, b), fail. choice(_).",
+        )
+        .unwrap();
+
+        let result = query(&database, "T = f(a), choice(T).");
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Assignments::empty().with("T", Term::functor("f", [Term::atom("a")]))]
+        );
+    }
+
+    #[test]
+    fn setarg_changes_a_compound_passed_to_a_clause() {
+        let database = crate::parser::MinplParser::parse_database(
+            "set(T) :- setarg(1, T, b). set_first(T) :- arg(1, T, A), setarg(1, A, c).",
+        )
+        .unwrap();
+
+        let result = query(
+            &database,
+            "T = f(a), set(T), X = g(Y), Y = f(a), set_first(X).",
+        );
+
+        let answer = &result.unwrap()[0];
+        assert_eq!(
+            answer.get("T"),
+            Some(&Term::functor("f", [Term::atom("b")]))
+        );
+        assert_eq!(
+            answer.get("X"),
+            Some(&Term::functor("g", [Term::functor("f", [Term::atom("c")])]))
+        );
+    }
+
+    #[test]
+    fn setarg_on_a_compound_no_variable_holds() {
+        let result = query(&Database::empty(), "setarg(1, f(a), b).");
+
+        assert_eq!(
+            result,
+            Err(Exception::permission_error(
+                "modify",
+                "compound",
+                Term::functor("f", [Term::atom("a")])
+            ))
+        );
+    }
+
+    #[test_case("T = f(g(a)), arg(1, T, A), setarg(1, A, b).", "b" ; "changed")]
+    #[test_case("T = f(g(a)), (arg(1, T, A), setarg(1, A, b), fail ; true).", "a" ; "undone")]
+    fn setarg_on_an_argument_changes_the_compound(code: &str, expected: &str) {
+        let result = query(&Database::empty(), code);
+
+        assert_eq!(
+            result.unwrap()[0].get("T"),
+            Some(&Term::functor(
+                "f",
+                [Term::functor("g", [Term::atom(expected)])]
+            ))
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use super::Solver;
+use crate::{exception::Exception, term::Term};
+
+pub fn var(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    Ok(matches!(
+        solver.dereference(&args[0]),
+        Term::Variable { .. }
+    ))
+}
+
+pub fn nonvar(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    Ok(!matches!(
+        solver.dereference(&args[0]),
+        Term::Variable { .. }
+    ))
+}
+
+pub fn atom(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    Ok(matches!(
+        solver.dereference(&args[0]),
+        Term::Functor { arguments, .. } if arguments.is_empty()
+    ))
+}
+
+pub fn atomic(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    Ok(match solver.dereference(&args[0]) {
+        Term::Variable { .. } => false,
+        term => term.arity() == 0,
+    })
+}
+
+pub fn compound(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    Ok(solver.dereference(&args[0]).arity() > 0)
+}
+
+pub fn callable(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    Ok(matches!(solver.dereference(&args[0]), Term::Functor { .. }))
+}
+
+pub fn is_list(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let mut tail = solver.dereference(&args[0]);
+    loop {
+        match tail {
+            Term::Functor { name, arguments } if name == "." && arguments.len() == 2 => {
+                tail = solver.dereference(&arguments[1]);
+            }
+            Term::Functor { name, arguments } if name == "[]" && arguments.is_empty() => {
+                return Ok(true)
+            }
+            _ => return Ok(false),
+        }
+    }
+}
+
+pub fn ground(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    Ok(solver.resolve(&args[0]).variables().is_empty())
+}
+
+pub fn number(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    Ok(matches!(solver.dereference(&args[0]), Term::Number { .. }))
+}
+
+// Numbers are all integers.
+pub fn integer(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    number(solver, args)
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::query;
+    use crate::{assignments::Assignments, database::Database, parser::MinplParser};
+    use test_case::test_case;
+
+    #[test_case("var(X)", true)]
+    #[test_case("X = Y, var(X)", true)]
+    #[test_case("X = a, var(X)", false)]
+    #[test_case("X = a, nonvar(X)", true)]
+    #[test_case("atom(foo)", true)]
+    #[test_case("atom([])", true)]
+    #[test_case("atom(\"foo\")", false)]
+    #[test_case("atom(1)", false)]
+    #[test_case("atomic(1)", true)]
+    #[test_case("atomic(\"foo\")", true)]
+    #[test_case("atomic(f(x))", false)]
+    #[test_case("compound(f(x))", true)]
+    #[test_case("compound([a])", true)]
+    #[test_case("compound(a)", false)]
+    #[test_case("callable(a)", true)]
+    #[test_case("callable(f(X))", true)]
+    #[test_case("callable(1)", false)]
+    #[test_case("is_list([a, b])", true)]
+    #[test_case("T = [], is_list([a|T])", true)]
+    #[test_case("is_list([a|T])", false)]
+    #[test_case("ground(f(a, [b]))", true)]
+    #[test_case("ground(f(a, [X]))", false)]
+    #[test_case("X = b, ground(f(a, [X]))", true)]
+    #[test_case("number(3)", true)]
+    #[test_case("X = 3, integer(X)", true)]
+    #[test_case("integer(a)", false)]
+    fn type_check(goal: &str, expected: bool) {
+        let result = query(&Database::empty(), &format!("{}.", goal));
+
+        assert_eq!(!result.unwrap().is_empty(), expected);
+    }
+
+    #[test]
+    fn type_checks_in_rule_bodies_see_current_bindings() {
+        let database = MinplParser::parse_database(
+            "unbound(X) :- var(X). bind_then_check(X) :- X = a, unbound(X).",
+        )
+        .unwrap();
+
+        assert_eq!(
+            query(&database, "unbound(Y).").unwrap(),
+            vec![Assignments::empty()]
+        );
+        assert_eq!(query(&database, "bind_then_check(Y).").unwrap(), vec![]);
+        assert_eq!(query(&database, "Y = b, unbound(Y).").unwrap(), vec![]);
+    }
+}
@@ -0,0 +1,60 @@
+use super::Solver;
+use crate::{
+    exception::Exception,
+    term::{Term, WriteOptions},
+};
+
+// Each of these takes an optional stream before its other arguments.
+pub fn write(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    write_term(solver, args, WriteOptions::WRITE)
+}
+
+// Without `portray/1` hooks, `print/1` writes like `writeq/1`.
+pub fn writeq(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    write_term(solver, args, WriteOptions::WRITEQ)
+}
+
+pub fn write_canonical(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    write_term(solver, args, WriteOptions::CANONICAL)
+}
+
+pub fn nl(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    output(solver, args.first(), "\n")
+}
+
+pub fn tab(solver: &mut Solver, args: &[Term]) -> Result<bool, Exception> {
+    let (stream, count) = split_stream(args);
+    match solver.resolve(count) {
+        Term::Number { value } => output(solver, stream, &" ".repeat(value.max(0) as usize)),
+        Term::Variable { .. } => Err(Exception::instantiation_error()),
+        term => Err(Exception::type_error("integer", term)),
+    }
+}
+
+fn write_term(
+    solver: &mut Solver,
+    args: &[Term],
+    options: WriteOptions,
+) -> Result<bool, Exception> {
+    let (stream, term) = split_stream(args);
+    let text = solver.resolve(term).formatted(options).to_string();
+    output(solver, stream, &text)
+}
+
+fn split_stream(args: &[Term]) -> (Option<&Term>, &Term) {
+    match args {
+        [stream, argument] => (Some(stream), argument),
+        [argument] => (None, argument),
+        _ => unreachable!(),
+    }
+}
+
+// Writes to `stream`, or to the current output when it is `None`.
+pub fn output(solver: &mut Solver, stream: Option<&Term>, text: &str) -> Result<bool, Exception> {
+    let stream = match stream {
+        Some(stream) => solver.resolve(stream),
+        None => solver.streams().current_output(),
+    };
+    solver.streams().write(&stream, text)?;
+    Ok(true)
+}
@@ -1,61 +1,942 @@
-mod temp_provider;
+mod builtins;
+pub mod streams;
+pub mod temp_provider;
 
 use crate::{
     assignments::Assignments,
-    database::{Database, Rule},
+    consult,
+    database::{Clauses, Database, ForeignPredicate, Rule, Solutions, USER},
+    exception::Exception,
+    library,
     term::Term,
     unification::*,
 };
-use std::collections::HashSet;
+use std::{
+    collections::{HashMap, HashSet},
+    iter::Peekable,
+    rc::Rc,
+};
+use streams::Streams;
 use temp_provider::TempProvider;
 
-pub fn prove(database: &Database, goal: Term) -> Vec<Assignments> {
-    let fvs = goal.free_variables();
-    prove_goals(database, vec![goal], &mut TempProvider::default())
+pub use builtins::is_builtin;
+
+pub fn prove(database: &mut Database, goal: Term) -> Result<Vec<Assignments>, Exception> {
+    solve(database, goal).collect()
+}
+
+pub fn solve(database: &mut Database, goal: Term) -> Solver<'_> {
+    let scope = goal
+        .variables()
         .into_iter()
-        .map(|assignment| assignment.normalized(fvs.iter().map(|s| s.as_str())))
-        .collect()
+        .filter(|name| !name.starts_with('_'))
+        .collect();
+    Solver {
+        database,
+        assignments: Assignments::empty(),
+        attributes: HashMap::new(),
+        wakeups: vec![],
+        trail: vec![],
+        goals: Goals::default().push(Frame::Call {
+            goal,
+            cut_barrier: 0,
+            module: Rc::from(USER),
+        }),
+        choicepoints: vec![],
+        temp_provider: TempProvider::default(),
+        module: Rc::from(USER),
+        catches: 0,
+        scope,
+        started: false,
+        finished: false,
+    }
+}
+
+#[derive(Clone)]
+enum Frame {
+    Call {
+        goal: Term,
+        cut_barrier: usize,
+        module: Rc<str>,
+    },
+    ExitCatch(usize),
+    Stop,
+}
+
+#[derive(Clone, Default)]
+struct Goals(Option<Rc<(Frame, Goals)>>);
+
+impl Goals {
+    fn push(&self, frame: Frame) -> Self {
+        Self(Some(Rc::new((frame, self.clone()))))
+    }
+
+    fn pop(&self) -> Option<(Frame, Goals)> {
+        self.0.as_ref().map(|node| node.as_ref().clone())
+    }
+
+    fn active_catches(&self) -> Vec<usize> {
+        let mut catches = vec![];
+        let mut goals = &self.0;
+        while let Some(node) = goals {
+            if let Frame::ExitCatch(id) = node.0 {
+                catches.push(id);
+            }
+            goals = &node.1 .0;
+        }
+        catches
+    }
+}
+
+// Dropped one frame at a time, as a long chain of goals would overflow the
+// stack if dropped recursively.
+impl Drop for Goals {
+    fn drop(&mut self) {
+        let mut next = self.0.take();
+        while let Some(node) = next {
+            next = match Rc::try_unwrap(node) {
+                Ok((_, mut goals)) => goals.0.take(),
+                Err(_) => None,
+            };
+        }
+    }
+}
+
+enum Alternative {
+    Clauses {
+        goal: Term,
+        module: Rc<str>,
+        clauses: Clauses,
+        next: usize,
+    },
+    Goals {
+        goals: Vec<Term>,
+        cut_barrier: usize,
+        module: Rc<str>,
+    },
+    Catch {
+        id: usize,
+        catcher: Term,
+        recovery: Term,
+        cut_barrier: usize,
+        module: Rc<str>,
+    },
+    Foreign {
+        arguments: Vec<Term>,
+        solutions: Peekable<ForeignSolutions>,
+    },
 }
 
-fn prove_goals(
-    database: &Database,
-    mut goals: Vec<Term>,
-    temp_provider: &mut TempProvider,
-) -> Vec<Assignments> {
-    if let Some(goal) = goals.pop() {
+type ForeignSolutions = Box<dyn Iterator<Item = Result<Vec<Term>, Exception>>>;
+
+enum TrailEntry {
+    Binding(String),
+    Value(String, Term),
+    Attribute(String, String, Option<Term>),
+    Global(String, Option<Term>),
+}
+
+struct ChoicePoint {
+    trail_mark: usize,
+    goals: Goals,
+    alternative: Alternative,
+}
+
+pub struct Solver<'a> {
+    database: &'a mut Database,
+    assignments: Assignments,
+    // Attributes of variables by module, in the order they were first put.
+    attributes: HashMap<String, Vec<(String, Term)>>,
+    // `attr_unify_hook/2` goals to run next, with the trail length at the
+    // binding that woke them.
+    wakeups: Vec<(usize, Term)>,
+    trail: Vec<TrailEntry>,
+    goals: Goals,
+    choicepoints: Vec<ChoicePoint>,
+    temp_provider: TempProvider,
+    module: Rc<str>,
+    catches: usize,
+    scope: Vec<String>,
+    started: bool,
+    finished: bool,
+}
+
+impl<'a> Solver<'a> {
+    pub fn database(&mut self) -> &mut Database {
+        self.database
+    }
+
+    pub fn streams(&mut self) -> &mut Streams {
+        self.database.streams()
+    }
+
+    pub fn module(&self) -> &str {
+        &self.module
+    }
+
+    pub fn resolve(&self, term: &Term) -> Term {
+        self.assignments.normalize_term(term)
+    }
+
+    pub fn dereference<'t>(&'t self, term: &'t Term) -> &'t Term {
+        self.assignments.dereference(term)
+    }
+
+    pub fn unify(&mut self, a: &Term, b: &Term) -> bool {
+        let mark = self.trail.len();
+        let unified = self.unify_terms(a, b);
+        if !unified {
+            self.undo(mark);
+        }
+        unified
+    }
+
+    pub fn bound_variable(&self, term: &Term) -> Option<String> {
+        let mut holder = None;
+        let mut term = term;
+        while let Term::Variable { name } = term {
+            match self.assignments.get(name) {
+                Some(value) => {
+                    holder = Some(name.clone());
+                    term = value;
+                }
+                None => break,
+            }
+        }
+        holder
+    }
+
+    pub fn rebind(&mut self, variable: &str, value: Term) {
+        if let Some(old_value) = self.assignments.get(variable) {
+            self.trail
+                .push(TrailEntry::Value(String::from(variable), old_value.clone()));
+            self.assignments.add(variable, value);
+        }
+    }
+
+    pub fn attribute(&self, variable: &str, module: &str) -> Option<&Term> {
+        self.attributes
+            .get(variable)?
+            .iter()
+            .find(|(name, _)| name == module)
+            .map(|(_, value)| value)
+    }
+
+    // Sets or, given `None`, removes an attribute. The change is undone on
+    // backtracking.
+    pub fn set_attribute(&mut self, variable: &str, module: &str, value: Option<Term>) {
+        let attributes = self.attributes.entry(variable.to_string()).or_default();
+        let position = attributes.iter().position(|(name, _)| name == module);
+        let old_value = match (position, value) {
+            (Some(position), Some(value)) => {
+                Some(std::mem::replace(&mut attributes[position].1, value))
+            }
+            (Some(position), None) => Some(attributes.remove(position).1),
+            (None, Some(value)) => {
+                attributes.push((module.to_string(), value));
+                None
+            }
+            (None, None) => return,
+        };
+        self.trail.push(TrailEntry::Attribute(
+            variable.to_string(),
+            module.to_string(),
+            old_value,
+        ));
+    }
+
+    pub fn global(&self, key: &str) -> Option<&Term> {
+        self.database.global(key)
+    }
+
+    // Sets a global variable, kept in the database. With `backtrackable` the
+    // value is linked to the current bindings and restored on backtracking,
+    // and when the query is done. Otherwise a copy is kept that later
+    // backtracking and queries do not touch.
+    pub fn set_global(&mut self, key: &str, value: &Term, backtrackable: bool) {
+        if backtrackable {
+            let old_value = self.database.set_global(key, Some(value.clone()));
+            self.trail
+                .push(TrailEntry::Global(key.to_string(), old_value));
+        } else {
+            let value = self.rename(&self.resolve(value));
+            self.database.set_global(key, Some(value));
+        }
+    }
+
+    // Goals describing the constraints left on the variables of the current
+    // answer, built with `Module:attribute_goals//1` where it is defined.
+    pub fn residual_goals(&mut self) -> Result<Vec<Term>, Exception> {
+        let mut variables = vec![];
+        for name in self.scope.clone() {
+            push_variables(&self.resolve(&Term::variable(name)), &mut variables);
+        }
+        let mut goals: Vec<Term> = vec![];
+        let mut index = 0;
+        while index < variables.len() {
+            let variable = variables[index].clone();
+            index += 1;
+            let attributes = self.attributes.get(&variable).cloned().unwrap_or_default();
+            for (module, value) in attributes {
+                push_variables(&self.resolve(&value), &mut variables);
+                for goal in self.attribute_goals(&variable, &module, value)? {
+                    if !goals.contains(&goal) {
+                        goals.push(goal);
+                    }
+                }
+            }
+        }
+        Ok(goals)
+    }
+
+    fn attribute_goals(
+        &mut self,
+        variable: &str,
+        module: &str,
+        value: Term,
+    ) -> Result<Vec<Term>, Exception> {
+        let variable = Term::variable(variable);
+        if self.database.lookup(module, "attribute_goals", 3).is_none() {
+            let goal = Term::functor("put_attr", [variable, Term::atom(module), value]);
+            return Ok(vec![self.resolve(&goal)]);
+        }
+        let list = self.fresh_variable();
+        let goal = Term::functor(
+            ":",
+            [
+                Term::atom(module),
+                Term::functor(
+                    "attribute_goals",
+                    [variable, list.clone(), Term::atom("[]")],
+                ),
+            ],
+        );
+        let mark = self.trail.len();
+        let goals = match self.solve_once(goal)? {
+            true => self.resolve(&list).list_elements()?,
+            false => vec![],
+        };
+        self.undo(mark);
+        Ok(goals)
+    }
+
+    pub fn alternatives(&mut self, goals: Vec<Term>) -> bool {
+        let cut_barrier = self.choicepoints.len();
+        let module = self.module.clone();
+        self.try_alternatives(goals.into_iter().rev().collect(), cut_barrier, module)
+    }
+
+    pub fn push_goal(&mut self, goal: Term) {
+        let cut_barrier = self.choicepoints.len();
+        let module = self.module.clone();
+        self.goals = self.goals.push(Frame::Call {
+            goal,
+            cut_barrier,
+            module,
+        });
+    }
+
+    pub fn catch(&mut self, goal: Term, catcher: Term, recovery: Term) {
+        self.catches += 1;
+        let id = self.catches;
+        let cut_barrier = self.choicepoints.len();
+        self.choicepoints.push(ChoicePoint {
+            trail_mark: self.trail.len(),
+            goals: self.goals.clone(),
+            alternative: Alternative::Catch {
+                id,
+                catcher,
+                recovery,
+                cut_barrier,
+                module: self.module.clone(),
+            },
+        });
+        self.goals = self.goals.push(Frame::ExitCatch(id));
+        self.push_goal(goal);
+    }
+
+    pub fn fresh_variable(&mut self) -> Term {
+        Term::variable(self.temp_provider.get())
+    }
+
+    pub fn rename(&mut self, term: &Term) -> Term {
+        term.rename(&mut self.temp_provider)
+    }
+
+    pub fn solve_once(&mut self, goal: Term) -> Result<bool, Exception> {
+        let base = self.choicepoints.len();
+        let goals = std::mem::replace(
+            &mut self.goals,
+            Goals::default().push(Frame::Stop).push(Frame::Call {
+                goal,
+                cut_barrier: base,
+                module: self.module.clone(),
+            }),
+        );
+        let result = self.run(base);
+        self.choicepoints.truncate(base);
+        self.goals = goals;
+        result
+    }
+
+    pub fn solve_all(&mut self, goal: Term, template: &Term) -> Result<Vec<Term>, Exception> {
+        let base = self.choicepoints.len();
+        let mark = self.trail.len();
+        let goals = std::mem::replace(
+            &mut self.goals,
+            Goals::default().push(Frame::Stop).push(Frame::Call {
+                goal,
+                cut_barrier: base,
+                module: self.module.clone(),
+            }),
+        );
         let mut solutions = vec![];
-        for rule in database.rules() {
-            let rule = fresh_variables(rule, temp_provider);
-            if let UnifyResult::True(assignments) = unify(goal.clone(), rule.head.clone()) {
-                let subgoals: Vec<_> = rule
-                    .body
-                    .iter()
-                    .cloned()
-                    .map(|subgoal| subgoal.substitute_all(&assignments))
-                    .rev()
-                    .collect();
-                solutions.extend(
-                    prove_goals(database, subgoals, temp_provider)
-                        .into_iter()
-                        .map(|solution| assignments.clone().merge(solution).unwrap()),
-                );
-            }
-        }
-        solutions
+        let result = loop {
+            match self.run(base) {
+                Ok(true) => {
+                    let solution = self.resolve(template);
+                    solutions.push(self.rename(&solution));
+                    if !self.backtrack(base) {
+                        break Ok(solutions);
+                    }
+                }
+                Ok(false) => break Ok(solutions),
+                Err(exception) => break Err(exception),
+            }
+        };
+        self.choicepoints.truncate(base);
+        self.undo(mark);
+        self.goals = goals;
+        result
+    }
+
+    fn run(&mut self, base: usize) -> Result<bool, Exception> {
+        loop {
+            for (_, goal) in std::mem::take(&mut self.wakeups).into_iter().rev() {
+                self.push_goal(goal);
+            }
+            let succeeded = match self.goals.pop() {
+                None => return Ok(true),
+                Some((Frame::Stop, goals)) => {
+                    self.goals = goals;
+                    return Ok(true);
+                }
+                Some((Frame::ExitCatch(_), goals)) => {
+                    self.goals = goals;
+                    true
+                }
+                Some((
+                    Frame::Call {
+                        goal,
+                        cut_barrier,
+                        module,
+                    },
+                    goals,
+                )) => {
+                    self.goals = goals;
+                    self.module = module;
+                    match self.call(goal, cut_barrier) {
+                        Ok(succeeded) => succeeded,
+                        Err(exception) => self.recover(exception, base)?,
+                    }
+                }
+            };
+            if !succeeded && !self.backtrack(base) {
+                return Ok(false);
+            }
+        }
+    }
+
+    fn call(&mut self, goal: Term, cut_barrier: usize) -> Result<bool, Exception> {
+        let (goal, cut_barrier) = match goal {
+            Term::Variable { .. } => {
+                builtins::check_body(self, &goal)?;
+                (
+                    self.assignments.dereference(&goal).clone(),
+                    self.choicepoints.len(),
+                )
+            }
+            goal => (goal, cut_barrier),
+        };
+        match &goal {
+            Term::Variable { .. } => Err(Exception::instantiation_error()),
+            Term::Number { .. } | Term::Str { .. } => Err(Exception::type_error("callable", goal)),
+            Term::Functor { name, arguments } => match (name.as_str(), arguments.len()) {
+                ("true", 0) => Ok(true),
+                ("fail", 0) | ("false", 0) => Ok(false),
+                ("!", 0) => {
+                    self.choicepoints.truncate(cut_barrier);
+                    Ok(true)
+                }
+                (";", 2) => match &arguments[0] {
+                    Term::Functor {
+                        name,
+                        arguments: condition,
+                    } if name == "->" && condition.len() == 2 => {
+                        self.if_then_else(&condition[0], &condition[1], &arguments[1], cut_barrier);
+                        Ok(true)
+                    }
+                    _ => Ok(self.try_alternatives(
+                        vec![arguments[1].clone(), arguments[0].clone()],
+                        cut_barrier,
+                        self.module.clone(),
+                    )),
+                },
+                ("->", 2) => {
+                    self.if_then_else(
+                        &arguments[0],
+                        &arguments[1],
+                        &Term::atom("fail"),
+                        cut_barrier,
+                    );
+                    Ok(true)
+                }
+                (",", 2) => {
+                    self.goals = self
+                        .goals
+                        .push(Frame::Call {
+                            goal: arguments[1].clone(),
+                            cut_barrier,
+                            module: self.module.clone(),
+                        })
+                        .push(Frame::Call {
+                            goal: arguments[0].clone(),
+                            cut_barrier,
+                            module: self.module.clone(),
+                        });
+                    Ok(true)
+                }
+                (":", 2) => {
+                    let module = match self.assignments.dereference(&arguments[0]) {
+                        Term::Functor { name, arguments } if arguments.is_empty() => {
+                            Rc::from(name.as_str())
+                        }
+                        Term::Variable { .. } => return Err(Exception::instantiation_error()),
+                        module => return Err(Exception::type_error("atom", module.clone())),
+                    };
+                    self.goals = self.goals.push(Frame::Call {
+                        goal: arguments[1].clone(),
+                        cut_barrier,
+                        module,
+                    });
+                    Ok(true)
+                }
+                (name, arity) => match builtins::get(name, arity) {
+                    Some(builtin) => builtin(self, arguments),
+                    None => match self.database.foreign(name, arity) {
+                        Some(predicate) => self.call_foreign(predicate, arguments),
+                        None => match self.lookup(name, arity)? {
+                            Some((module, _)) if module == USER && self.bottom_up() => {
+                                builtins::datalog(self, std::slice::from_ref(&goal))
+                            }
+                            Some((module, clauses)) => {
+                                let goal = self.qualify_meta_arguments(goal, &module);
+                                Ok(self.resolve_clauses(goal, Rc::from(module), clauses, 0))
+                            }
+                            None => self.unknown_procedure(name, arity),
+                        },
+                    },
+                },
+            },
+        }
+    }
+
+    // Predicates of bundled libraries are loaded on their first call.
+    fn lookup(&mut self, name: &str, arity: usize) -> Result<Option<(String, Clauses)>, Exception> {
+        if let Some(found) = self.database.lookup(&self.module, name, arity) {
+            return Ok(Some(found));
+        }
+        match library::autoload(name, arity) {
+            Some(library) => {
+                let file = Term::functor("library", [Term::atom(library)]);
+                consult::use_module(self.database, &file, None, &self.module)?;
+                Ok(self.database.lookup(&self.module, name, arity))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // Goals passed to a meta-predicate of another module still run in the
+    // module of the caller.
+    fn qualify_meta_arguments(&self, goal: Term, module: &str) -> Term {
+        let (name, arguments) = match &goal {
+            Term::Functor { name, arguments } if module != &*self.module => (name, arguments),
+            _ => return goal,
+        };
+        let meta_arguments = match self.database.meta_arguments(module, name, arguments.len()) {
+            Some(meta_arguments) => meta_arguments,
+            None => return goal,
+        };
+        let arguments = arguments
             .iter()
-            .flat_map(|solution| {
-                let remaining_goals: Vec<_> = goals
-                    .iter()
-                    .cloned()
-                    .map(|goal| goal.substitute_all(&solution))
-                    .collect();
-                prove_goals(database, remaining_goals, temp_provider)
-                    .into_iter()
-                    .flat_map(|tail_solution| solution.clone().merge(tail_solution))
+            .zip(meta_arguments)
+            .map(|(argument, meta)| {
+                match *meta && !is_qualified(self.assignments.dereference(argument)) {
+                    true => Term::functor(":", [Term::atom(&*self.module), argument.clone()]),
+                    false => argument.clone(),
+                }
+            });
+        Term::functor(name, arguments)
+    }
+
+    // With the `evaluation` flag set to `bottom_up`, the predicates of `user`
+    // are Datalog, evaluated as by `datalog/1`.
+    fn bottom_up(&self) -> bool {
+        self.database.flag("evaluation").and_then(Term::name) == Some("bottom_up")
+    }
+
+    fn unknown_procedure(&self, name: &str, arity: usize) -> Result<bool, Exception> {
+        let indicator = Term::functor("/", [Term::atom(name), Term::number(arity as i64)]);
+        match self.database.flag("unknown").and_then(Term::name) {
+            Some("error") => Err(Exception::existence_error("procedure", indicator)),
+            Some("warning") => {
+                eprintln!("Warning: unknown procedure: {}", indicator);
+                Ok(false)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn if_then_else(
+        &mut self,
+        condition: &Term,
+        then: &Term,
+        otherwise: &Term,
+        cut_barrier: usize,
+    ) {
+        let condition_barrier = self.choicepoints.len();
+        self.choicepoints.push(ChoicePoint {
+            trail_mark: self.trail.len(),
+            goals: self.goals.clone(),
+            alternative: Alternative::Goals {
+                goals: vec![otherwise.clone()],
+                cut_barrier,
+                module: self.module.clone(),
+            },
+        });
+        self.goals = self
+            .goals
+            .push(Frame::Call {
+                goal: then.clone(),
+                cut_barrier,
+                module: self.module.clone(),
+            })
+            .push(Frame::Call {
+                goal: Term::atom("!"),
+                cut_barrier: condition_barrier,
+                module: self.module.clone(),
             })
-            .collect()
-    } else {
-        vec![Assignments::empty()]
+            .push(Frame::Call {
+                goal: condition.clone(),
+                cut_barrier: condition_barrier + 1,
+                module: self.module.clone(),
+            });
+    }
+
+    fn recover(&mut self, exception: Exception, base: usize) -> Result<bool, Exception> {
+        let active = self.goals.active_catches();
+        let ball = self.rename(exception.ball());
+        while self.choicepoints.len() > base {
+            let choicepoint = self.choicepoints.pop().unwrap();
+            if let Alternative::Catch {
+                id,
+                catcher,
+                recovery,
+                cut_barrier,
+                module,
+            } = choicepoint.alternative
+            {
+                if active.contains(&id) {
+                    self.undo(choicepoint.trail_mark);
+                    self.goals = choicepoint.goals;
+                    if self.unify(&catcher, &ball) {
+                        self.goals = self.goals.push(Frame::Call {
+                            goal: recovery,
+                            cut_barrier,
+                            module,
+                        });
+                        return Ok(true);
+                    }
+                }
+            }
+        }
+        Err(exception)
+    }
+
+    fn resolve_clauses(
+        &mut self,
+        goal: Term,
+        module: Rc<str>,
+        clauses: Clauses,
+        start: usize,
+    ) -> bool {
+        for index in start..clauses.len() {
+            let mark = self.trail.len();
+            let rule = fresh_variables(&clauses[index].rule, &mut self.temp_provider);
+            // The head goes first so that its fresh variables are bound to
+            // the goal's, leaving the caller's variables unbound.
+            if self.unify_terms(&rule.head, &goal) {
+                let cut_barrier = self.choicepoints.len();
+                if index + 1 < clauses.len() {
+                    self.choicepoints.push(ChoicePoint {
+                        trail_mark: mark,
+                        goals: self.goals.clone(),
+                        alternative: Alternative::Clauses {
+                            goal,
+                            module: module.clone(),
+                            clauses,
+                            next: index + 1,
+                        },
+                    });
+                }
+                for goal in rule.body.into_iter().rev() {
+                    self.goals = self.goals.push(Frame::Call {
+                        goal,
+                        cut_barrier,
+                        module: module.clone(),
+                    });
+                }
+                return true;
+            }
+            self.undo(mark);
+        }
+        false
+    }
+
+    fn call_foreign(
+        &mut self,
+        predicate: Rc<dyn ForeignPredicate>,
+        arguments: &[Term],
+    ) -> Result<bool, Exception> {
+        let arguments = arguments
+            .iter()
+            .map(|argument| self.resolve(argument))
+            .collect::<Vec<_>>();
+        match predicate.solve(&arguments)? {
+            Solutions::Deterministic(Some(solution)) => {
+                Ok(self.unify_arguments(&arguments, &solution))
+            }
+            Solutions::Deterministic(None) => Ok(false),
+            Solutions::Nondeterministic(solutions) => {
+                self.next_foreign(arguments, solutions.peekable())
+            }
+        }
+    }
+
+    // Tries the solutions of a nondeterministic foreign predicate in order,
+    // keeping a choicepoint while there are more.
+    fn next_foreign(
+        &mut self,
+        arguments: Vec<Term>,
+        mut solutions: Peekable<ForeignSolutions>,
+    ) -> Result<bool, Exception> {
+        while let Some(solution) = solutions.next() {
+            let solution = solution?;
+            let mark = self.trail.len();
+            if self.unify_arguments(&arguments, &solution) {
+                if solutions.peek().is_some() {
+                    self.choicepoints.push(ChoicePoint {
+                        trail_mark: mark,
+                        goals: self.goals.clone(),
+                        alternative: Alternative::Foreign {
+                            arguments,
+                            solutions,
+                        },
+                    });
+                }
+                return Ok(true);
+            }
+            self.undo(mark);
+        }
+        Ok(false)
+    }
+
+    fn unify_arguments(&mut self, arguments: &[Term], solution: &[Term]) -> bool {
+        arguments.len() == solution.len()
+            && arguments
+                .iter()
+                .zip(solution)
+                .all(|(argument, value)| self.unify_terms(argument, value))
+    }
+
+    fn try_alternatives(
+        &mut self,
+        mut goals: Vec<Term>,
+        cut_barrier: usize,
+        module: Rc<str>,
+    ) -> bool {
+        match goals.pop() {
+            Some(goal) => {
+                if !goals.is_empty() {
+                    self.choicepoints.push(ChoicePoint {
+                        trail_mark: self.trail.len(),
+                        goals: self.goals.clone(),
+                        alternative: Alternative::Goals {
+                            goals,
+                            cut_barrier,
+                            module: module.clone(),
+                        },
+                    });
+                }
+                self.goals = self.goals.push(Frame::Call {
+                    goal,
+                    cut_barrier,
+                    module,
+                });
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn backtrack(&mut self, base: usize) -> bool {
+        while self.choicepoints.len() > base {
+            let choicepoint = self.choicepoints.pop().unwrap();
+            self.undo(choicepoint.trail_mark);
+            self.goals = choicepoint.goals;
+            let resumed = match choicepoint.alternative {
+                Alternative::Clauses {
+                    goal,
+                    module,
+                    clauses,
+                    next,
+                } => self.resolve_clauses(goal, module, clauses, next),
+                Alternative::Goals {
+                    goals,
+                    cut_barrier,
+                    module,
+                } => self.try_alternatives(goals, cut_barrier, module),
+                Alternative::Catch { .. } => false,
+                // An error from a later solution is thrown where the call was.
+                Alternative::Foreign {
+                    arguments,
+                    solutions,
+                } => match self.next_foreign(arguments, solutions) {
+                    Ok(resumed) => resumed,
+                    Err(exception) => {
+                        self.push_goal(Term::functor("throw", [exception.ball().clone()]));
+                        true
+                    }
+                },
+            };
+            if resumed {
+                return true;
+            }
+        }
+        false
+    }
+
+    // Binding an attributed variable wakes the `attr_unify_hook/2` of each of
+    // its attributes. When it meets a plain variable, that one is bound
+    // instead, so nothing needs to wake.
+    fn unify_terms(&mut self, a: &Term, b: &Term) -> bool {
+        let mut bound = vec![];
+        let unified = unify_with(a, b, &mut self.assignments, &mut bound);
+        for mut name in bound {
+            if unified && self.attributes.get(&name).is_some_and(|a| !a.is_empty()) {
+                match self.assignments.get(&name) {
+                    Some(Term::Variable { name: other }) if self.is_plain_variable(other) => {
+                        let other = other.clone();
+                        self.assignments.remove(&name);
+                        self.assignments.add(&other, Term::variable(&name));
+                        name = other;
+                    }
+                    Some(value) => {
+                        for (module, attribute) in &self.attributes[&name] {
+                            let hook = Term::functor(
+                                "attr_unify_hook",
+                                [attribute.clone(), value.clone()],
+                            );
+                            self.wakeups.push((
+                                self.trail.len(),
+                                Term::functor(":", [Term::atom(module), hook]),
+                            ));
+                        }
+                    }
+                    None => {}
+                }
+            }
+            self.trail.push(TrailEntry::Binding(name));
+        }
+        unified
+    }
+
+    fn is_plain_variable(&self, name: &str) -> bool {
+        self.assignments.get(name).is_none() && self.attributes.get(name).is_none_or(Vec::is_empty)
+    }
+
+    fn undo(&mut self, mark: usize) {
+        for entry in self.trail.drain(mark..).rev() {
+            match entry {
+                TrailEntry::Binding(name) => self.assignments.remove(&name),
+                TrailEntry::Value(name, value) => self.assignments.add(name, value),
+                TrailEntry::Attribute(name, module, value) => {
+                    let attributes = self.attributes.entry(name).or_default();
+                    let position = attributes.iter().position(|(name, _)| *name == module);
+                    match (position, value) {
+                        (Some(position), Some(value)) => attributes[position].1 = value,
+                        (Some(position), None) => {
+                            attributes.remove(position);
+                        }
+                        (None, Some(value)) => attributes.push((module, value)),
+                        (None, None) => {}
+                    }
+                }
+                TrailEntry::Global(key, value) => {
+                    self.database.set_global(&key, value);
+                }
+            }
+        }
+        self.wakeups.retain(|(position, _)| *position < mark);
+    }
+}
+
+// Undoes what is left on the trail, so that values set with `b_setval/2` do
+// not outlive the query.
+impl Drop for Solver<'_> {
+    fn drop(&mut self) {
+        self.undo(0);
+    }
+}
+
+impl<'a> Iterator for Solver<'a> {
+    type Item = Result<Assignments, Exception>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        if self.started && !self.backtrack(0) {
+            self.finished = true;
+            return None;
+        }
+        self.started = true;
+        match self.run(0) {
+            Ok(true) => Some(Ok(self
+                .assignments
+                .clone()
+                .normalized(self.scope.iter().map(|s| s.as_str())))),
+            Ok(false) => {
+                self.finished = true;
+                None
+            }
+            Err(exception) => {
+                self.finished = true;
+                Some(Err(exception))
+            }
+        }
+    }
+}
+
+fn is_qualified(term: &Term) -> bool {
+    matches!(term, Term::Functor { name, arguments } if name == ":" && arguments.len() == 2)
+}
+
+fn push_variables(term: &Term, variables: &mut Vec<String>) {
+    for name in term.variables() {
+        if !variables.contains(&name) {
+            variables.push(name);
+        }
     }
 }
 
@@ -85,45 +966,46 @@ fn fresh_variables(rule: &Rule, temp_provider: &mut TempProvider) -> Rule {
 #[cfg(test)]
 mod test {
     use super::*;
+    use test_case::test_case;
 
     #[test]
     fn proves_given_fact() {
-        let database = Database::empty().with_fact(Term::functor("f", [Term::atom("a")]));
+        let mut database = Database::empty().with_fact(Term::functor("f", [Term::atom("a")]));
 
-        let result = prove(&database, Term::functor("f", [Term::atom("a")]));
+        let result = prove(&mut database, Term::functor("f", [Term::atom("a")]));
 
-        assert_eq!(result, vec![Assignments::empty()]);
+        assert_eq!(result.unwrap(), vec![Assignments::empty()]);
     }
 
     #[test]
     fn proves_from_rule() {
-        let database = Database::empty()
+        let mut database = Database::empty()
             .with_fact(Term::functor("f", [Term::atom("a")]))
             .with_rule(
                 Term::functor("g", [Term::atom("a")]),
                 [Term::functor("f", [Term::atom("a")])],
             );
 
-        let result = prove(&database, Term::functor("g", [Term::atom("a")]));
+        let result = prove(&mut database, Term::functor("g", [Term::atom("a")]));
 
-        assert_eq!(result, vec![Assignments::empty()]);
+        assert_eq!(result.unwrap(), vec![Assignments::empty()]);
     }
 
     #[test]
     fn do_not_proves_if_does_not_follow_from_rules() {
-        let database = Database::empty().with_rule(
+        let mut database = Database::empty().with_rule(
             Term::functor("g", [Term::atom("a")]),
             [Term::functor("f", [Term::atom("a")])],
         );
 
-        let result = prove(&database, Term::functor("g", [Term::atom("a")]));
+        let result = prove(&mut database, Term::functor("g", [Term::atom("a")]));
 
-        assert_eq!(result, vec![]);
+        assert_eq!(result.unwrap(), vec![]);
     }
 
     #[test]
     fn proves_from_rule_with_multiple_goals() {
-        let database = Database::empty()
+        let mut database = Database::empty()
             .with_fact(Term::functor("f", [Term::atom("a")]))
             .with_fact(Term::functor("g", [Term::atom("a")]))
             .with_fact(Term::functor("h", [Term::atom("a")]))
@@ -137,14 +1019,14 @@ mod test {
                 ],
             );
 
-        let result = prove(&database, Term::functor("k", [Term::atom("a")]));
+        let result = prove(&mut database, Term::functor("k", [Term::atom("a")]));
 
-        assert_eq!(result, vec![Assignments::empty()]);
+        assert_eq!(result.unwrap(), vec![Assignments::empty()]);
     }
 
     #[test]
     fn do_not_proves_from_rule_with_multiple_goals_if_some_goal_are_false() {
-        let database = Database::empty()
+        let mut database = Database::empty()
             .with_fact(Term::functor("f", [Term::atom("a")]))
             .with_fact(Term::functor("g", [Term::atom("a")]))
             .with_fact(Term::functor("h", [Term::atom("a")]))
@@ -158,35 +1040,36 @@ mod test {
                 ],
             );
 
-        let result = prove(&database, Term::functor("k", [Term::atom("b")]));
+        let result = prove(&mut database, Term::functor("k", [Term::atom("b")]));
 
-        assert_eq!(result, vec![]);
+        assert_eq!(result.unwrap(), vec![]);
     }
 
     #[test]
     fn proves_fact_with_variable() {
-        let database = Database::empty().with_fact(Term::functor("test", [Term::variable("X")]));
+        let mut database =
+            Database::empty().with_fact(Term::functor("test", [Term::variable("X")]));
 
-        let result = prove(&database, Term::functor("test", [Term::atom("a")]));
+        let result = prove(&mut database, Term::functor("test", [Term::atom("a")]));
 
-        assert_eq!(result, vec![Assignments::empty()]);
+        assert_eq!(result.unwrap(), vec![Assignments::empty()]);
     }
 
     #[test]
     fn proves_goal_with_variable() {
-        let database = Database::empty().with_fact(Term::functor("test", [Term::atom("a")]));
+        let mut database = Database::empty().with_fact(Term::functor("test", [Term::atom("a")]));
 
-        let result = prove(&database, Term::functor("test", [Term::variable("X")]));
+        let result = prove(&mut database, Term::functor("test", [Term::variable("X")]));
 
         assert_eq!(
-            result,
+            result.unwrap(),
             vec![Assignments::empty().with("X", Term::atom("a"))]
         );
     }
 
     #[test]
     fn prove_goal_and_rule_with_same_variable_name() {
-        let database = Database::empty()
+        let mut database = Database::empty()
             .with_fact(Term::functor("one", [Term::atom("a")]))
             .with_rule(
                 Term::functor("test", [Term::variable("X"), Term::atom("b")]),
@@ -194,13 +1077,57 @@ mod test {
             );
 
         let result = prove(
-            &database,
+            &mut database,
             Term::functor("test", [Term::atom("a"), Term::variable("X")]),
         );
 
         assert_eq!(
-            result,
+            result.unwrap(),
             vec![Assignments::empty().with("X", Term::atom("b"))]
         );
     }
+
+    #[test]
+    fn drop_long_chain_of_goals() {
+        let mut goals = Goals::default();
+        for _ in 0..1_000_000 {
+            goals = goals.push(Frame::Stop);
+        }
+
+        drop(goals);
+    }
+
+    #[test]
+    fn goal_variables_are_not_bound_to_clause_variables() {
+        let mut database = Database::empty().with_fact(Term::functor("any", [Term::variable("X")]));
+
+        let result = prove(&mut database, Term::functor("any", [Term::variable("Y")]));
+
+        assert_eq!(result.unwrap(), vec![Assignments::empty()]);
+    }
+
+    #[test_case("q(_1, Z)." ; "clashing with a renamed variable")]
+    #[test_case("q(_2, Z)." ; "clashing with another renamed variable")]
+    fn source_variables_do_not_clash_with_renamed_ones(code: &str) {
+        let database = crate::parser::MinplParser::parse_database("q(X, Y) :- X = a, Y = b.");
+        let goal = crate::parser::MinplParser::parse_query(code).unwrap();
+
+        for _ in 0..20 {
+            let result = prove(&mut database.clone().unwrap(), goal.clone());
+
+            assert_eq!(
+                result.unwrap(),
+                vec![Assignments::empty().with("Z", Term::atom("b"))]
+            );
+        }
+    }
+
+    #[test]
+    fn source_variables_do_not_clash_with_anonymous_ones() {
+        let goal = crate::parser::MinplParser::parse_query("X = f(_G0, _), X = f(a, b).").unwrap();
+
+        let result = prove(&mut Database::empty(), goal);
+
+        assert_eq!(result.unwrap().len(), 1);
+    }
 }
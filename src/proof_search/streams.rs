@@ -0,0 +1,263 @@
+use crate::{exception::Exception, term::Term};
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Write},
+    iter::Peekable,
+    path::Path,
+};
+
+pub type Input = Peekable<Box<dyn Iterator<Item = char>>>;
+
+// Characters of a reader, read as they are needed.
+pub fn chars<R: Read + 'static>(mut reader: R) -> Input {
+    let chars = std::iter::from_fn(move || {
+        let mut bytes = [0; 4];
+        reader.read_exact(&mut bytes[..1]).ok()?;
+        let length = match bytes[0] {
+            0xF0.. => 4,
+            0xE0.. => 3,
+            0xC0.. => 2,
+            _ => 1,
+        };
+        reader.read_exact(&mut bytes[1..length]).ok()?;
+        Some(
+            std::str::from_utf8(&bytes[..length])
+                .map_or(char::REPLACEMENT_CHARACTER, |c| c.chars().next().unwrap()),
+        )
+    });
+    (Box::new(chars) as Box<dyn Iterator<Item = char>>).peekable()
+}
+
+enum Output {
+    Stdout,
+    Stderr,
+    File(BufWriter<File>),
+    Memory(String),
+}
+
+enum Kind {
+    Input(Input),
+    Output(Output),
+}
+
+struct Stream {
+    kind: Kind,
+    alias: Option<String>,
+}
+
+const USER_INPUT: usize = 0;
+const USER_OUTPUT: usize = 1;
+const USER_ERROR: usize = 2;
+
+// Open streams by number, as `'$stream'(N)` terms, along with the current
+// input and output.
+pub struct Streams {
+    streams: HashMap<usize, Stream>,
+    next: usize,
+    input: usize,
+    output: usize,
+}
+
+impl Default for Streams {
+    fn default() -> Self {
+        let user = |kind, alias: &str| Stream {
+            kind,
+            alias: Some(alias.to_string()),
+        };
+        let streams = HashMap::from([
+            (
+                USER_INPUT,
+                user(Kind::Input(chars(io::stdin())), "user_input"),
+            ),
+            (
+                USER_OUTPUT,
+                user(Kind::Output(Output::Stdout), "user_output"),
+            ),
+            (USER_ERROR, user(Kind::Output(Output::Stderr), "user_error")),
+        ]);
+        Self {
+            streams,
+            next: 3,
+            input: USER_INPUT,
+            output: USER_OUTPUT,
+        }
+    }
+}
+
+// A copy of a database starts with the standard streams only: open files
+// are not shared.
+impl Clone for Streams {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+// Streams are state of a running program rather than part of its clauses,
+// so they make no difference when comparing databases.
+impl PartialEq for Streams {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Streams {}
+
+impl std::fmt::Debug for Streams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut ids = self.streams.keys().collect::<Vec<_>>();
+        ids.sort();
+        f.debug_set().entries(ids).finish()
+    }
+}
+
+impl Streams {
+    pub fn term(id: usize) -> Term {
+        Term::functor("$stream", [Term::number(id as i64)])
+    }
+
+    pub fn current_input(&self) -> Term {
+        Self::term(self.input)
+    }
+
+    pub fn current_output(&self) -> Term {
+        Self::term(self.output)
+    }
+
+    pub fn set_input(&mut self, stream: &Term) -> Result<(), Exception> {
+        self.input(stream)?;
+        self.input = self.lookup(stream)?;
+        Ok(())
+    }
+
+    pub fn set_output(&mut self, stream: &Term) -> Result<(), Exception> {
+        self.output_kind(stream)?;
+        self.output = self.lookup(stream)?;
+        Ok(())
+    }
+
+    // The number of a stream given as `'$stream'(N)` or by its alias.
+    pub fn lookup(&self, stream: &Term) -> Result<usize, Exception> {
+        let id = match (stream.name(), stream.arguments()) {
+            (Some("$stream"), [Term::Number { value }]) => usize::try_from(*value).ok(),
+            (Some(alias), []) => self
+                .streams
+                .iter()
+                .find(|(_, stream)| stream.alias.as_deref() == Some(alias))
+                .map(|(id, _)| *id),
+            (None, _) if matches!(stream, Term::Variable { .. }) => {
+                return Err(Exception::instantiation_error())
+            }
+            _ => return Err(Exception::domain_error("stream_or_alias", stream.clone())),
+        };
+        id.filter(|id| self.streams.contains_key(id))
+            .ok_or_else(|| Exception::existence_error("stream", stream.clone()))
+    }
+
+    pub fn is_alias(&self, alias: &str) -> bool {
+        self.streams
+            .values()
+            .any(|stream| stream.alias.as_deref() == Some(alias))
+    }
+
+    // Opens a file in `read`, `write` or `append` mode.
+    pub fn open(&mut self, path: &Path, mode: &str, alias: Option<String>) -> io::Result<Term> {
+        let kind = match mode {
+            "read" => Kind::Input(chars(BufReader::new(File::open(path)?))),
+            mode => {
+                let file = OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .append(mode == "append")
+                    .truncate(mode != "append")
+                    .open(path)?;
+                Kind::Output(Output::File(BufWriter::new(file)))
+            }
+        };
+        Ok(self.add(kind, alias))
+    }
+
+    // A stream collecting its output in memory, returned by `close`.
+    pub fn open_memory(&mut self) -> Term {
+        self.add(Kind::Output(Output::Memory(String::new())), None)
+    }
+
+    fn add(&mut self, kind: Kind, alias: Option<String>) -> Term {
+        let id = self.next;
+        self.next += 1;
+        self.streams.insert(id, Stream { kind, alias });
+        Self::term(id)
+    }
+
+    // Closing one of the user streams does nothing. The current input or
+    // output goes back to the user streams when it is closed.
+    pub fn close(&mut self, stream: &Term) -> Result<Option<String>, Exception> {
+        let id = self.lookup(stream)?;
+        if id <= USER_ERROR {
+            return Ok(None);
+        }
+        if self.input == id {
+            self.input = USER_INPUT;
+        }
+        if self.output == id {
+            self.output = USER_OUTPUT;
+        }
+        match self.streams.remove(&id).map(|stream| stream.kind) {
+            Some(Kind::Output(Output::File(mut file))) => {
+                file.flush().map_err(|_| io_error("close", stream))?;
+                Ok(None)
+            }
+            Some(Kind::Output(Output::Memory(text))) => Ok(Some(text)),
+            _ => Ok(None),
+        }
+    }
+
+    pub fn input(&mut self, stream: &Term) -> Result<&mut Input, Exception> {
+        let id = self.lookup(stream)?;
+        match &mut self.streams.get_mut(&id).unwrap().kind {
+            Kind::Input(input) => Ok(input),
+            Kind::Output(_) => Err(Exception::permission_error(
+                "input",
+                "stream",
+                stream.clone(),
+            )),
+        }
+    }
+
+    fn output_kind(&mut self, stream: &Term) -> Result<&mut Output, Exception> {
+        let id = self.lookup(stream)?;
+        match &mut self.streams.get_mut(&id).unwrap().kind {
+            Kind::Output(output) => Ok(output),
+            Kind::Input(_) => Err(Exception::permission_error(
+                "output",
+                "stream",
+                stream.clone(),
+            )),
+        }
+    }
+
+    pub fn write(&mut self, stream: &Term, text: &str) -> Result<(), Exception> {
+        let result = match self.output_kind(stream)? {
+            Output::Stdout => {
+                let mut stdout = io::stdout();
+                stdout
+                    .write_all(text.as_bytes())
+                    .and_then(|_| stdout.flush())
+            }
+            Output::Stderr => io::stderr().write_all(text.as_bytes()),
+            Output::File(file) => file.write_all(text.as_bytes()),
+            Output::Memory(memory) => {
+                memory.push_str(text);
+                Ok(())
+            }
+        };
+        result.map_err(|_| io_error("write", stream))
+    }
+}
+
+fn io_error(operation: &str, stream: &Term) -> Exception {
+    Exception::error(Term::functor(
+        "io_error",
+        [Term::atom(operation), stream.clone()],
+    ))
+}
@@ -1,44 +1,62 @@
-use crate::assignments::Assignments;
-use crate::database::Database;
-use crate::parser::MinplParser;
-use crate::proof_search::prove;
 use colored::*;
+use minpl::{term::Term, Answer, Machine};
 use rustyline::{error::ReadlineError, Editor};
-use std::{env, fs::File, io::Read};
+use std::process;
 
 pub struct MinplRepl {
     rl: Editor<()>,
-    database: Database,
+    machine: Machine,
     running: bool,
 }
 
+impl Default for MinplRepl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl MinplRepl {
     pub fn new() -> Self {
         let rl = Editor::new().unwrap();
         Self {
             rl,
-            database: Database::empty(),
+            machine: Machine::new(),
             running: false,
         }
     }
 
-    pub fn run(mut self) {
-        match self.load_database() {
-            Ok(_) => {
-                self.running = true;
-                self.repl_loop();
-            }
+    pub fn run<I: IntoIterator<Item = String>>(mut self, files: I) {
+        match self.load_database(files) {
+            Ok(_) => match self.machine.take_main() {
+                Some(goal) => process::exit(self.run_main(goal)),
+                None => {
+                    self.running = true;
+                    self.repl_loop();
+                }
+            },
             Err(error) => self.print_error(error),
         }
     }
 
-    fn load_database(&mut self) -> anyhow::Result<()> {
-        if let Some(filename) = env::args().skip(1).next() {
+    fn run_main(&mut self, goal: Term) -> i32 {
+        let answer = self.machine.query_term(goal.clone()).next();
+        match answer {
+            Some(Ok(_)) => 0,
+            Some(Err(exception)) => {
+                self.print_error(exception.into());
+                1
+            }
+            None => {
+                self.print_error(anyhow::anyhow!("goal failed: {}", goal));
+                1
+            }
+        }
+    }
+
+    fn load_database<I: IntoIterator<Item = String>>(&mut self, files: I) -> anyhow::Result<()> {
+        for filename in files {
             println!("Loading database '{}'...", filename);
-            let mut file = File::open(filename)?;
-            let mut content = String::new();
-            file.read_to_string(&mut content)?;
-            self.database = MinplParser::parse_database(&content)?;
+            self.machine.consult_file(filename)?;
         }
         Ok(())
     }
@@ -55,9 +73,14 @@ impl MinplRepl {
         match self.rl.readline("?: ") {
             Ok(line) => {
                 self.rl.add_history_entry(line.as_str());
-                let query = MinplParser::parse_query(&line)?;
-                let results = prove(&self.database, query);
-                self.print_result(results);
+                let mut found = false;
+                for answer in self.machine.query(&line) {
+                    found = true;
+                    Self::print_answer(answer?);
+                }
+                if !found {
+                    println!("{}", "false.".bold().red());
+                }
             }
             Err(ReadlineError::Eof) | Err(ReadlineError::Interrupted) => {
                 println!("{}", "quitting.".bold());
@@ -68,17 +91,11 @@ impl MinplRepl {
         Ok(())
     }
 
-    fn print_result(&self, results: Vec<Assignments>) {
-        if results.is_empty() {
-            println!("{}", "false.".bold().red());
+    fn print_answer(answer: Answer) {
+        if answer.is_empty() {
+            println!("{}", "true.".bold().bright_green());
         } else {
-            for result in results {
-                if result.is_empty() {
-                    println!("{}", "true.".bold().bright_green());
-                } else {
-                    println!("{}", result);
-                }
-            }
+            println!("{}", answer);
         }
     }
 
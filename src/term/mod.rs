@@ -0,0 +1,612 @@
+#[cfg(feature = "serde")]
+mod serialize;
+mod write;
+
+use crate::{
+    assignments::Assignments, exception::Exception, proof_search::temp_provider::TempProvider,
+};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    hash::{Hash, Hasher},
+};
+pub use write::*;
+
+// Copied, compared, hashed and dropped without recursion, so that deep terms
+// such as long lists don't overflow the stack.
+#[derive(Debug)]
+pub enum Term {
+    Functor { name: String, arguments: Vec<Term> },
+    Variable { name: String },
+    Number { value: i64 },
+    Str { value: String },
+}
+
+impl Term {
+    pub fn variable<S: ToString>(name: S) -> Self {
+        Self::Variable {
+            name: name.to_string(),
+        }
+    }
+
+    pub fn atom<S: ToString>(name: S) -> Self {
+        Self::Functor {
+            name: name.to_string(),
+            arguments: vec![],
+        }
+    }
+
+    pub fn functor<S: ToString, I: IntoIterator<Item = Term>>(name: S, arguments: I) -> Self {
+        let arguments = arguments.into_iter().collect();
+        Self::Functor {
+            name: name.to_string(),
+            arguments,
+        }
+    }
+
+    pub fn number(value: i64) -> Self {
+        Self::Number { value }
+    }
+
+    pub fn string<S: ToString>(value: S) -> Self {
+        Self::Str {
+            value: value.to_string(),
+        }
+    }
+
+    pub fn list<I: IntoIterator<Item = Term>>(elements: I) -> Self {
+        Self::list_with_tail(elements, Self::atom("[]"))
+    }
+
+    pub fn list_with_tail<I: IntoIterator<Item = Term>>(elements: I, tail: Term) -> Self {
+        let elements: Vec<_> = elements.into_iter().collect();
+        elements
+            .into_iter()
+            .rev()
+            .fold(tail, |list, element| Self::functor(".", [element, list]))
+    }
+
+    // The elements of a proper list, with an instantiation error for a
+    // partial list and a type error for anything else.
+    pub fn list_elements(&self) -> Result<Vec<Term>, Exception> {
+        let mut elements = vec![];
+        let mut tail = self;
+        loop {
+            match tail {
+                Self::Functor { name, arguments } if name == "." && arguments.len() == 2 => {
+                    elements.push(arguments[0].clone());
+                    tail = &arguments[1];
+                }
+                Self::Functor { name, arguments } if name == "[]" && arguments.is_empty() => {
+                    return Ok(elements)
+                }
+                Self::Variable { .. } => return Err(Exception::instantiation_error()),
+                _ => return Err(Exception::type_error("list", self.clone())),
+            }
+        }
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Self::Functor { name, .. } => Some(name),
+            _ => None,
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        self.arguments().len()
+    }
+
+    pub fn arguments(&self) -> &[Term] {
+        match self {
+            Self::Functor { arguments, .. } => arguments,
+            _ => &[],
+        }
+    }
+
+    pub fn argument(&self, position: usize) -> Option<&Term> {
+        position
+            .checked_sub(1)
+            .and_then(|index| self.arguments().get(index))
+    }
+
+    pub fn rename(&self, temp_provider: &mut TempProvider) -> Self {
+        let mut renaming = HashMap::new();
+        self.rebuild(|term| match term {
+            Self::Variable { name } => Err(renaming
+                .entry(name)
+                .or_insert_with(|| Self::variable(temp_provider.get()))
+                .clone()),
+            term => Ok(term),
+        })
+    }
+
+    // Copies the term bottom up without recursion. Each subterm is copied
+    // from the term `visit` gives for it, or replaced by the one it fails
+    // with.
+    pub(crate) fn rebuild<'a>(
+        &'a self,
+        mut visit: impl FnMut(&'a Term) -> Result<&'a Term, Term>,
+    ) -> Term {
+        enum Task<'a> {
+            Visit(&'a Term),
+            Build(&'a str, usize),
+        }
+        let mut tasks = vec![Task::Visit(self)];
+        let mut built = vec![];
+        while let Some(task) = tasks.pop() {
+            match task {
+                Task::Visit(term) => match visit(term) {
+                    Ok(Self::Functor { name, arguments }) if !arguments.is_empty() => {
+                        tasks.push(Task::Build(name, arguments.len()));
+                        tasks.extend(arguments.iter().rev().map(Task::Visit));
+                    }
+                    Ok(term) => built.push(term.clone()),
+                    Err(term) => built.push(term),
+                },
+                Task::Build(name, arity) => {
+                    let arguments = built.split_off(built.len() - arity);
+                    built.push(Self::functor(name, arguments));
+                }
+            }
+        }
+        built.pop().unwrap()
+    }
+
+    pub fn is_variant(&self, other: &Term) -> bool {
+        let mut renaming = HashMap::new();
+        let mut inverse = HashMap::new();
+        let mut pairs = vec![(self, other)];
+        while let Some(pair) = pairs.pop() {
+            match pair {
+                (Self::Variable { name: a }, Self::Variable { name: b }) => {
+                    let renamed = *renaming.entry(a).or_insert(b) == b;
+                    if !renamed || *inverse.entry(b).or_insert(a) != a {
+                        return false;
+                    }
+                }
+                (
+                    Self::Functor {
+                        name: a_name,
+                        arguments: a_args,
+                    },
+                    Self::Functor {
+                        name: b_name,
+                        arguments: b_args,
+                    },
+                ) if a_name == b_name && a_args.len() == b_args.len() => {
+                    pairs.extend(a_args.iter().zip(b_args));
+                }
+                (Self::Functor { .. }, _)
+                | (Self::Variable { .. }, _)
+                | (_, Self::Variable { .. }) => return false,
+                (a, b) if a != b => return false,
+                _ => {}
+            }
+        }
+        true
+    }
+
+    pub fn variables(&self) -> Vec<String> {
+        let mut seen = HashSet::new();
+        let mut variables = vec![];
+        let mut pending = vec![self];
+        while let Some(term) = pending.pop() {
+            match term {
+                Self::Variable { name } if seen.insert(name) => variables.push(name.clone()),
+                Self::Functor { arguments, .. } => pending.extend(arguments.iter().rev()),
+                _ => {}
+            }
+        }
+        variables
+    }
+
+    pub fn substitute(self, var_name: &str, term: &Term) -> Self {
+        self.rebuild(|subterm| match subterm {
+            Self::Variable { name } if name == var_name => Err(term.clone()),
+            subterm => Ok(subterm),
+        })
+    }
+
+    pub fn substitute_all(mut self, assignments: &Assignments) -> Self {
+        for (name, term) in assignments.substitutions() {
+            self = self.substitute(name, term);
+        }
+        self
+    }
+
+    pub fn free_variables(&self) -> HashSet<String> {
+        self.variables().into_iter().collect()
+    }
+
+    fn order_class(&self) -> u8 {
+        match self {
+            Self::Variable { .. } => 0,
+            Self::Number { .. } => 1,
+            Self::Functor { arguments, .. } if arguments.is_empty() => 2,
+            Self::Str { .. } => 3,
+            Self::Functor { .. } => 4,
+        }
+    }
+}
+
+impl Ord for Term {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let mut pairs = vec![(self, other)];
+        while let Some(pair) = pairs.pop() {
+            let ordering = match pair {
+                (Self::Variable { name: a }, Self::Variable { name: b }) => a.cmp(b),
+                (Self::Number { value: a }, Self::Number { value: b }) => a.cmp(b),
+                (Self::Str { value: a }, Self::Str { value: b }) => a.cmp(b),
+                (
+                    Self::Functor {
+                        name: a_name,
+                        arguments: a_args,
+                    },
+                    Self::Functor {
+                        name: b_name,
+                        arguments: b_args,
+                    },
+                ) => {
+                    pairs.extend(a_args.iter().zip(b_args).rev());
+                    a_args
+                        .len()
+                        .cmp(&b_args.len())
+                        .then_with(|| a_name.cmp(b_name))
+                }
+                (a, b) => a.order_class().cmp(&b.order_class()),
+            };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    }
+}
+
+impl PartialEq for Term {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Term {}
+
+impl Hash for Term {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let mut pending = vec![self];
+        while let Some(term) = pending.pop() {
+            std::mem::discriminant(term).hash(state);
+            match term {
+                Self::Functor { name, arguments } => {
+                    name.hash(state);
+                    arguments.len().hash(state);
+                    pending.extend(arguments.iter().rev());
+                }
+                Self::Variable { name } => name.hash(state),
+                Self::Number { value } => value.hash(state),
+                Self::Str { value } => value.hash(state),
+            }
+        }
+    }
+}
+
+impl Clone for Term {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Functor { name, arguments } if arguments.iter().all(|a| a.arity() == 0) => {
+                Self::Functor {
+                    name: name.clone(),
+                    arguments: arguments.clone(),
+                }
+            }
+            Self::Functor { .. } => self.rebuild(Ok),
+            Self::Variable { name } => Self::Variable { name: name.clone() },
+            Self::Number { value } => Self::Number { value: *value },
+            Self::Str { value } => Self::Str {
+                value: value.clone(),
+            },
+        }
+    }
+}
+
+impl PartialOrd for Term {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Drop for Term {
+    fn drop(&mut self) {
+        let Self::Functor { arguments, .. } = self else {
+            return;
+        };
+        if arguments.iter().all(|argument| argument.arity() == 0) {
+            return;
+        }
+        let mut pending = std::mem::take(arguments);
+        while let Some(mut term) = pending.pop() {
+            if let Self::Functor { arguments, .. } = &mut term {
+                pending.append(arguments);
+            }
+        }
+    }
+}
+
+impl Display for Term {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.formatted(WriteOptions::WRITEQ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn build_term() {
+        let term = Term::functor("father", [Term::atom("a"), Term::variable("X")]);
+
+        use Term::*;
+        assert_eq!(
+            term,
+            Functor {
+                name: String::from("father"),
+                arguments: vec![
+                    Functor {
+                        name: String::from("a"),
+                        arguments: vec![]
+                    },
+                    Variable {
+                        name: String::from("X")
+                    },
+                ]
+            }
+        )
+    }
+
+    #[test]
+    fn deep_terms_do_not_overflow_the_stack() {
+        let list = Term::list((0..100_000).map(|_| Term::variable("X")));
+
+        let copy = list.clone();
+
+        assert_eq!(list, copy);
+        assert_eq!(list.cmp(&copy), Ordering::Equal);
+        assert!(list.is_variant(&copy));
+        assert_eq!(list.variables(), vec!["X"]);
+        assert_eq!(HashSet::from([list]).len(), 1);
+    }
+
+    #[test]
+    fn list_elements() {
+        let partial = Term::list_with_tail([Term::atom("a")], Term::variable("T"));
+        let improper = Term::list_with_tail([Term::atom("a")], Term::atom("b"));
+
+        assert_eq!(
+            Term::list([Term::atom("a"), Term::number(1)]).list_elements(),
+            Ok(vec![Term::atom("a"), Term::number(1)])
+        );
+        assert_eq!(
+            partial.list_elements(),
+            Err(Exception::instantiation_error())
+        );
+        assert_eq!(
+            improper.list_elements(),
+            Err(Exception::type_error("list", improper.clone()))
+        );
+    }
+
+    #[test]
+    fn substitute_variable() {
+        let original_term = Term::variable("X");
+
+        let subistituded = original_term.substitute("X", &Term::atom("a"));
+
+        assert_eq!(subistituded, Term::atom("a"));
+    }
+
+    #[test]
+    fn do_not_substitute_different_variable() {
+        let original_term = Term::variable("Y");
+
+        let subistituded = original_term.substitute("X", &Term::atom("a"));
+
+        assert_eq!(subistituded, Term::variable("Y"));
+    }
+
+    #[test]
+    fn do_not_substitute_atom() {
+        let original_term = Term::atom("b");
+
+        let subistituded = original_term.substitute("X", &Term::atom("a"));
+
+        assert_eq!(subistituded, Term::atom("b"));
+    }
+
+    #[test]
+    fn substitute_fuctor_arguments() {
+        let original_term = Term::functor(
+            "test",
+            [Term::atom("b"), Term::variable("X"), Term::variable("Y")],
+        );
+
+        let subistituded = original_term.substitute("X", &Term::atom("a"));
+
+        let expected_term = Term::functor(
+            "test",
+            [Term::atom("b"), Term::atom("a"), Term::variable("Y")],
+        );
+        assert_eq!(subistituded, expected_term)
+    }
+
+    #[test]
+    fn free_variable_of_atom_is_empty() {
+        let term = Term::atom("a");
+
+        assert_eq!(term.free_variables(), HashSet::new());
+    }
+
+    #[test]
+    fn free_variable_of_variable_is_variable() {
+        let term = Term::variable("X");
+
+        let expected_fvs = [String::from("X")].into_iter().collect();
+        assert_eq!(term.free_variables(), expected_fvs);
+    }
+
+    #[test]
+    fn free_variable_of_functor_is_free_variable_of_arguments() {
+        let term = Term::functor(
+            "test",
+            [Term::variable("X"), Term::atom("a"), Term::variable("Y")],
+        );
+
+        let expected_fvs = [String::from("X"), String::from("Y")].into_iter().collect();
+        assert_eq!(term.free_variables(), expected_fvs);
+    }
+
+    #[test]
+    fn free_variable_of_complex_term() {
+        let term = Term::functor(
+            "test",
+            [
+                Term::functor("other", [Term::variable("X"), Term::atom("b")]),
+                Term::atom("a"),
+                Term::functor("thing", [Term::variable("Z")]),
+            ],
+        );
+
+        let expected_fvs = [String::from("X"), String::from("Z")].into_iter().collect();
+        assert_eq!(term.free_variables(), expected_fvs);
+    }
+
+    #[test]
+    fn standard_order_of_term_classes() {
+        let mut terms = vec![
+            Term::functor("f", [Term::atom("a")]),
+            Term::string("s"),
+            Term::atom("a"),
+            Term::number(1),
+            Term::variable("X"),
+        ];
+
+        terms.sort();
+
+        assert_eq!(
+            terms,
+            vec![
+                Term::variable("X"),
+                Term::number(1),
+                Term::atom("a"),
+                Term::string("s"),
+                Term::functor("f", [Term::atom("a")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn compound_terms_are_ordered_by_arity_then_name_then_arguments() {
+        let mut terms = vec![
+            Term::functor("a", [Term::atom("x"), Term::atom("y")]),
+            Term::functor("b", [Term::atom("y")]),
+            Term::functor("b", [Term::atom("x")]),
+            Term::functor("a", [Term::atom("z")]),
+        ];
+
+        terms.sort();
+
+        assert_eq!(
+            terms,
+            vec![
+                Term::functor("a", [Term::atom("z")]),
+                Term::functor("b", [Term::atom("x")]),
+                Term::functor("b", [Term::atom("y")]),
+                Term::functor("a", [Term::atom("x"), Term::atom("y")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn numbers_are_ordered_by_value() {
+        assert!(Term::number(-3) < Term::number(2));
+        assert!(Term::number(10) > Term::number(9));
+    }
+
+    #[test]
+    fn display_list() {
+        let list = Term::list_with_tail([Term::atom("a"), Term::number(1)], Term::variable("T"));
+
+        assert_eq!(list.to_string(), "[a, 1|T]");
+        assert_eq!(Term::list([]).to_string(), "[]");
+    }
+
+    #[test]
+    fn arity_and_arguments() {
+        let term = Term::functor("f", [Term::atom("a"), Term::variable("X")]);
+
+        assert_eq!(term.arity(), 2);
+        assert_eq!(term.argument(2), Some(&Term::variable("X")));
+        assert_eq!(term.argument(0), None);
+        assert_eq!(Term::number(1).arity(), 0);
+    }
+
+    #[test]
+    fn rename_variables_consistently() {
+        let term = Term::functor("f", [Term::variable("X"), Term::variable("X")]);
+
+        let renamed = term.rename(&mut TempProvider::default());
+
+        assert_eq!(
+            renamed,
+            Term::functor("f", [Term::variable("_1"), Term::variable("_1")])
+        );
+    }
+
+    #[test]
+    fn variables_in_order_of_appearance() {
+        let term = Term::functor(
+            "f",
+            [
+                Term::variable("Y"),
+                Term::functor("g", [Term::variable("X"), Term::variable("Y")]),
+            ],
+        );
+
+        assert_eq!(term.variables(), vec![String::from("Y"), String::from("X")]);
+    }
+
+    #[test]
+    fn variant_terms() {
+        let a = Term::functor(
+            "f",
+            [
+                Term::variable("X"),
+                Term::variable("Y"),
+                Term::variable("X"),
+            ],
+        );
+        let b = Term::functor(
+            "f",
+            [
+                Term::variable("A"),
+                Term::variable("B"),
+                Term::variable("A"),
+            ],
+        );
+        let c = Term::functor(
+            "f",
+            [
+                Term::variable("A"),
+                Term::variable("A"),
+                Term::variable("A"),
+            ],
+        );
+
+        assert!(a.is_variant(&b));
+        assert!(!a.is_variant(&c));
+        assert!(!c.is_variant(&a));
+        assert!(!Term::variable("X").is_variant(&Term::atom("a")));
+    }
+}
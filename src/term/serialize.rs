@@ -0,0 +1,76 @@
+use super::Term;
+use serde::{ser::SerializeTupleVariant, Deserialize, Deserializer, Serialize, Serializer};
+
+// Terms are serialized as an enum with one-letter variants, atoms apart from
+// other compound terms. In JSON `f(X, a, 1, "s")` is
+// `{"f":["f",[{"v":"X"},{"a":"a"},{"n":1},{"s":"s"}]]}`.
+#[derive(Deserialize)]
+enum Repr {
+    #[serde(rename = "a")]
+    Atom(String),
+    #[serde(rename = "f")]
+    Functor(String, Vec<Term>),
+    #[serde(rename = "v")]
+    Variable(String),
+    #[serde(rename = "n")]
+    Number(i64),
+    #[serde(rename = "s")]
+    Str(String),
+}
+
+impl Serialize for Term {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Term::Functor { name, arguments } if arguments.is_empty() => {
+                serializer.serialize_newtype_variant("Term", 0, "a", name)
+            }
+            Term::Functor { name, arguments } => {
+                let mut functor = serializer.serialize_tuple_variant("Term", 1, "f", 2)?;
+                functor.serialize_field(name)?;
+                functor.serialize_field(arguments)?;
+                functor.end()
+            }
+            Term::Variable { name } => serializer.serialize_newtype_variant("Term", 2, "v", name),
+            Term::Number { value } => serializer.serialize_newtype_variant("Term", 3, "n", value),
+            Term::Str { value } => serializer.serialize_newtype_variant("Term", 4, "s", value),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Term {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Atom(name) => Term::atom(name),
+            Repr::Functor(name, arguments) => Term::functor(name, arguments),
+            Repr::Variable(name) => Term::variable(name),
+            Repr::Number(value) => Term::number(value),
+            Repr::Str(value) => Term::string(value),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::MinplParser;
+    use test_case::test_case;
+
+    #[test_case("a." => "{\"a\":\"a\"}"; "atom")]
+    #[test_case("f(X, 1, \"s\")." => "{\"f\":[\"f\",[{\"v\":\"X\"},{\"n\":1},{\"s\":\"s\"}]]}"; "compound")]
+    #[test_case("[a]." => "{\"f\":[\".\",[{\"a\":\"a\"},{\"a\":\"[]\"}]]}"; "list")]
+    fn json_representation(code: &str) -> String {
+        serde_json::to_string(&MinplParser::parse_query(code).unwrap()).unwrap()
+    }
+
+    #[test_case("a."; "atom")]
+    #[test_case("f(X, _Y, -3, \"text\", 'quoted atom')."; "compound")]
+    #[test_case("[1, [a|T], {b}, (p :- q, r)]."; "nested")]
+    fn round_trips(code: &str) {
+        let term = MinplParser::parse_query(code).unwrap();
+
+        let json = serde_json::to_string(&term).unwrap();
+        assert_eq!(serde_json::from_str::<Term>(&json).unwrap(), term);
+        let bytes = postcard::to_stdvec(&term).unwrap();
+        assert_eq!(postcard::from_bytes::<Term>(&bytes).unwrap(), term);
+    }
+}
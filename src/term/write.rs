@@ -0,0 +1,301 @@
+use super::Term;
+use crate::operators::{self, is_symbol_char};
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteOptions {
+    pub quoted: bool,
+    pub ignore_ops: bool,
+    pub number_vars: bool,
+    pub priority: u32,
+}
+
+impl WriteOptions {
+    pub const WRITE: Self = Self {
+        quoted: false,
+        ignore_ops: false,
+        number_vars: true,
+        priority: 1200,
+    };
+    pub const WRITEQ: Self = Self {
+        quoted: true,
+        ..Self::WRITE
+    };
+    pub const CANONICAL: Self = Self {
+        quoted: true,
+        ignore_ops: true,
+        number_vars: false,
+        priority: 1200,
+    };
+}
+
+pub struct Formatted<'t> {
+    term: &'t Term,
+    options: WriteOptions,
+}
+
+impl Term {
+    pub fn formatted(&self, options: WriteOptions) -> Formatted<'_> {
+        Formatted {
+            term: self,
+            options,
+        }
+    }
+}
+
+impl Display for Formatted<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&Writer(self.options).term(self.term, self.options.priority))
+    }
+}
+
+struct Writer(WriteOptions);
+
+impl Writer {
+    fn term(&self, term: &Term, priority: u32) -> String {
+        match term {
+            Term::Variable { name } => name.clone(),
+            Term::Number { value } => value.to_string(),
+            Term::Str { value } if self.0.quoted => quote(value, '"'),
+            Term::Str { value } => value.clone(),
+            Term::Functor { name, arguments } => match (name.as_str(), arguments.as_slice()) {
+                (_, []) => self.atom(name),
+                ("$VAR", [Term::Number { value }]) if self.0.number_vars && *value >= 0 => {
+                    variable_name(*value)
+                }
+                (".", [_, _]) => self.list(term),
+                ("{}", [argument]) => format!("{{{}}}", self.term(argument, 1200)),
+                (_, [left, right]) if !self.0.ignore_ops => match operators::infix(name) {
+                    Some(operator) => {
+                        let left = self.operand(left, operator.left_priority());
+                        let right = self.operand(right, operator.right_priority());
+                        let text = self.infix(left, name, right);
+                        parenthesize(text, operator.priority > priority)
+                    }
+                    None => self.canonical(name, arguments),
+                },
+                (_, [argument]) if !self.0.ignore_ops => match operators::prefix(name) {
+                    Some(operator) => {
+                        let operand = self.operand(argument, operator.right_priority());
+                        // `- 1` keeps `-(1)` from reading back as the number -1.
+                        let signed_number = matches!(name.as_str(), "-" | "+")
+                            && matches!(argument, Term::Number { .. });
+                        let separator = match operand.chars().next() {
+                            _ if is_alphanumeric(name) || signed_number => " ",
+                            Some(first) if first == '(' || is_symbol_char(first) => " ",
+                            _ => "",
+                        };
+                        let text = format!("{}{}{}", self.atom(name), separator, operand);
+                        parenthesize(text, operator.priority > priority)
+                    }
+                    None => self.canonical(name, arguments),
+                },
+                _ => self.canonical(name, arguments),
+            },
+        }
+    }
+
+    // Atoms that are operators are bracketed when they are operands.
+    fn operand(&self, term: &Term, priority: u32) -> String {
+        match term {
+            Term::Functor { name, arguments }
+                if arguments.is_empty()
+                    && (operators::infix(name).is_some() || operators::prefix(name).is_some()) =>
+            {
+                format!("({})", self.atom(name))
+            }
+            term => self.term(term, priority),
+        }
+    }
+
+    fn infix(&self, left: String, name: &str, right: String) -> String {
+        let operator = self.atom(name);
+        if name == "," {
+            return format!("{}, {}", left, right);
+        }
+        if is_alphanumeric(name) {
+            return format!("{} {} {}", left, operator, right);
+        }
+        let before = match left.chars().last() {
+            Some(last) if is_symbol_char(last) => " ",
+            _ => "",
+        };
+        let after = match right.chars().next() {
+            Some(first) if is_symbol_char(first) => " ",
+            _ => "",
+        };
+        format!("{}{}{}{}{}", left, before, operator, after, right)
+    }
+
+    fn canonical(&self, name: &str, arguments: &[Term]) -> String {
+        let arguments: Vec<_> = arguments
+            .iter()
+            .map(|argument| self.term(argument, 999))
+            .collect();
+        format!("{}({})", self.atom(name), arguments.join(", "))
+    }
+
+    fn list(&self, list: &Term) -> String {
+        let mut elements = vec![];
+        let mut tail = list;
+        while let Term::Functor { name, arguments } = tail {
+            if name != "." || arguments.len() != 2 {
+                break;
+            }
+            elements.push(self.term(&arguments[0], 999));
+            tail = &arguments[1];
+        }
+        match tail {
+            Term::Functor { name, arguments } if name == "[]" && arguments.is_empty() => {
+                format!("[{}]", elements.join(", "))
+            }
+            tail => format!("[{}|{}]", elements.join(", "), self.term(tail, 999)),
+        }
+    }
+
+    fn atom(&self, name: &str) -> String {
+        match self.0.quoted && needs_quotes(name) {
+            true => quote(name, '\''),
+            false => name.to_string(),
+        }
+    }
+}
+
+fn parenthesize(text: String, parenthesize: bool) -> String {
+    match parenthesize {
+        true => format!("({})", text),
+        false => text,
+    }
+}
+
+// `A`, ..., `Z`, `A1`, ... for `'$VAR'(N)`.
+fn variable_name(number: i64) -> String {
+    let letter = (b'A' + (number % 26) as u8) as char;
+    match number / 26 {
+        0 => letter.to_string(),
+        suffix => format!("{}{}", letter, suffix),
+    }
+}
+
+fn needs_quotes(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        _ if matches!(name, "[]" | "{}" | "!" | ";") => false,
+        Some(first) if first.is_ascii_lowercase() => {
+            !chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        Some(_) => name == "." || !name.chars().all(is_symbol_char),
+        None => true,
+    }
+}
+
+fn quote(text: &str, quote: char) -> String {
+    let mut quoted = String::from(quote);
+    for c in text.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c == quote => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push(quote);
+    quoted
+}
+
+fn is_alphanumeric(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphanumeric())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::MinplParser;
+    use test_case::test_case;
+
+    fn write(code: &str, options: WriteOptions) -> String {
+        let term = MinplParser::parse_query(&format!("{}.", code)).unwrap();
+        term.formatted(options).to_string()
+    }
+
+    #[test_case("a+b*c" => "a+b*c" ; "priorities")]
+    #[test_case("(a+b)*c" => "(a+b)*c" ; "lower priority operand")]
+    #[test_case("a-(b-c)" => "a-(b-c)" ; "right operand of yfx")]
+    #[test_case("(a-b)-c" => "a-b-c" ; "left operand of yfx")]
+    #[test_case("1-(-1)" => "1- -1" ; "space between symbol chars")]
+    #[test_case("-(1)" => "- 1" ; "prefix minus on a number")]
+    #[test_case("-(-(1))" => "- - 1" ; "nested prefix minus on a number")]
+    #[test_case("-(-1)" => "- -1" ; "prefix minus on a negative number")]
+    #[test_case("+(1)" => "+ 1" ; "prefix plus on a number")]
+    #[test_case("-(a+b)" => "- (a+b)" ; "prefix operator before bracket")]
+    #[test_case("\\+ \\+a" => "\\+ \\+a" ; "nested prefix operators")]
+    #[test_case("f((a, b), (c:-d))" => "f((a, b), (c:-d))" ; "arguments above 999")]
+    #[test_case("(a :- b, c ; d -> e)" => "a:-b, c;d->e" ; "control constructs")]
+    #[test_case("a = (b, c)" => "a=(b, c)" ; "comma operand")]
+    #[test_case("X is Y mod 2" => "X is Y mod 2" ; "alphanumeric operators")]
+    #[test_case("X in 1..5" => "X in 1..5" ; "clpfd domain")]
+    #[test_case("dynamic foo/1" => "dynamic foo/1" ; "alphanumeric prefix operator")]
+    #[test_case("(-) = a" => "(-)=a" ; "operator atom as operand")]
+    #[test_case("f(+, -)" => "f(+, -)" ; "operator atoms as arguments")]
+    #[test_case("{a, b}" => "{a, b}" ; "curly term")]
+    #[test_case("[a, b+c|T]" => "[a, b+c|T]" ; "partial list")]
+    #[test_case("'$VAR'(2) - '$VAR'(27)" => "C-B1" ; "numbered variables")]
+    fn operators(code: &str) -> String {
+        write(code, WriteOptions::WRITEQ)
+    }
+
+    #[test_case("'hello world'" => "'hello world'" ; "space")]
+    #[test_case("'It''s'" => "'It\\'s'" ; "quote")]
+    #[test_case("'a\\nb'" => "'a\\nb'" ; "newline")]
+    #[test_case("[]" => "[]" ; "empty list")]
+    #[test_case("''" => "''" ; "empty atom")]
+    #[test_case("','" => "','" ; "comma")]
+    #[test_case("'.'" => "'.'" ; "dot")]
+    #[test_case("f('X', x_1, '1a', =..)" => "f('X', x_1, '1a', =..)" ; "atom shapes")]
+    #[test_case("\"a \\\"string\\\"\"" => "\"a \\\"string\\\"\"" ; "string")]
+    fn quoted(code: &str) -> String {
+        write(code, WriteOptions::WRITEQ)
+    }
+
+    #[test_case("f('A b', \"s\", [x])" => "f(A b, s, [x])" ; "atoms and strings")]
+    #[test_case("'$VAR'(0)" => "A" ; "numbered variable")]
+    fn unquoted(code: &str) -> String {
+        write(code, WriteOptions::WRITE)
+    }
+
+    #[test_case("a+b*c" => "+(a, *(b, c))" ; "operators")]
+    #[test_case("[-, 'A']" => "[-, 'A']" ; "list")]
+    #[test_case("'$VAR'(1)" => "'$VAR'(1)" ; "numbered variable")]
+    fn canonical(code: &str) -> String {
+        write(code, WriteOptions::CANONICAL)
+    }
+
+    #[test_case("-(1)" ; "prefix minus on a number")]
+    #[test_case("-(-(1))" ; "nested prefix minus")]
+    #[test_case("- (-1)" ; "prefix minus on a negative number")]
+    #[test_case("1 - -1" ; "negative right operand")]
+    #[test_case("+(1)" ; "prefix plus on a number")]
+    #[test_case("f(-(1), -1, - a)" ; "arguments")]
+    fn writeq_reads_back(code: &str) {
+        let term = MinplParser::parse_query(&format!("{}.", code)).unwrap();
+        let written = term.formatted(WriteOptions::WRITEQ).to_string();
+
+        let read = MinplParser::parse_query(&format!("{}.", written)).unwrap();
+        assert_eq!(read, term, "{} was written as {}", code, written);
+    }
+
+    #[test]
+    fn priority_brackets_the_whole_term() {
+        let options = WriteOptions {
+            priority: 699,
+            ..WriteOptions::WRITEQ
+        };
+
+        assert_eq!(write("a :- b", options), "(a:-b)");
+        assert_eq!(write("a + b", options), "a+b");
+    }
+}
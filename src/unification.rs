@@ -8,57 +8,69 @@ pub enum UnifyResult {
 }
 
 pub fn unify(a: Term, b: Term) -> UnifyResult {
-    unify_terms(vec![(a, b)])
+    let mut assignments = Assignments::empty();
+    if unify_with(&a, &b, &mut assignments, &mut vec![]) {
+        UnifyResult::True(assignments)
+    } else {
+        UnifyResult::False
+    }
 }
 
-fn unify_terms(mut constraints: Vec<(Term, Term)>) -> UnifyResult {
-    if let Some((lhs, rhs)) = constraints.pop() {
-        if lhs == rhs {
-            unify_terms(constraints)
-        } else {
-            match (lhs, rhs) {
-                (Term::Variable { name }, term) => {
-                    let subistituded_constraints = constraints
-                        .into_iter()
-                        .map(|(ta, tb)| (ta.substitute(&name, &term), tb.substitute(&name, &term)))
-                        .collect();
-                    if let UnifyResult::True(assignments) = unify_terms(subistituded_constraints) {
-                        UnifyResult::True(assignments.with(name, term))
-                    } else {
-                        UnifyResult::False
-                    }
-                }
-                (term, Term::Variable { name }) => {
-                    let subistituded_constraints = constraints
-                        .into_iter()
-                        .map(|(ta, tb)| (ta.substitute(&name, &term), tb.substitute(&name, &term)))
-                        .collect();
-                    if let UnifyResult::True(assignments) = unify_terms(subistituded_constraints) {
-                        UnifyResult::True(assignments.with(name, term))
-                    } else {
-                        UnifyResult::False
-                    }
-                }
-                (
-                    Term::Functor {
-                        name: a_name,
-                        arguments: a_args,
-                    },
-                    Term::Functor {
-                        name: b_name,
-                        arguments: b_args,
-                    },
-                ) if a_name == b_name && a_args.len() == b_args.len() => {
-                    constraints
-                        .append(&mut a_args.into_iter().zip(b_args.into_iter()).rev().collect());
-                    unify_terms(constraints)
-                }
-                _ => UnifyResult::False,
+// A variable is bound to the variable holding a value rather than to the
+// value itself, so that both share the value.
+pub fn unify_with(
+    a: &Term,
+    b: &Term,
+    assignments: &mut Assignments,
+    trail: &mut Vec<String>,
+) -> bool {
+    let mut constraints = vec![(a.clone(), b.clone())];
+    while let Some((lhs, rhs)) = constraints.pop() {
+        let lhs = reference(assignments, lhs);
+        let rhs = reference(assignments, rhs);
+        let mut lhs_value = assignments.dereference(&lhs).clone();
+        let mut rhs_value = assignments.dereference(&rhs).clone();
+        if lhs_value == rhs_value {
+            continue;
+        }
+        match (&mut lhs_value, &mut rhs_value) {
+            (Term::Variable { name }, _) => bind(assignments, trail, name, rhs),
+            (_, Term::Variable { name }) => bind(assignments, trail, name, lhs),
+            (
+                Term::Functor {
+                    name: a_name,
+                    arguments: a_args,
+                },
+                Term::Functor {
+                    name: b_name,
+                    arguments: b_args,
+                },
+            ) if a_name == b_name && a_args.len() == b_args.len() => {
+                let a_args = std::mem::take(a_args);
+                let b_args = std::mem::take(b_args);
+                constraints.extend(a_args.into_iter().zip(b_args).rev());
             }
+            _ => return false,
         }
-    } else {
-        UnifyResult::True(Assignments::empty())
     }
+    true
+}
+
+fn bind(assignments: &mut Assignments, trail: &mut Vec<String>, name: &str, term: Term) {
+    assignments.add(name, term);
+    trail.push(String::from(name));
+}
+
+// Follows variables bound to variables, up to an unbound variable or the
+// variable holding a value.
+fn reference(assignments: &Assignments, mut term: Term) -> Term {
+    while let Term::Variable { name } = &term {
+        match assignments.get(name) {
+            Some(next @ Term::Variable { .. }) => term = next.clone(),
+            _ => break,
+        }
+    }
+    term
 }
 
 #[cfg(test)]
@@ -312,4 +324,31 @@ mod test {
             UnifyResult::True(Assignments::empty().with("X", Term::variable("Y")))
         )
     }
+
+    #[test]
+    fn bind_to_the_variable_holding_a_value() {
+        let f_a = Term::functor("f", [Term::atom("a")]);
+        let mut assignments = Assignments::empty()
+            .with("X", Term::functor("g", [f_a.clone()]))
+            .with("Y", Term::variable("X"));
+        let mut trail = vec![];
+
+        let unified = unify_with(
+            &Term::functor("h", [Term::variable("A"), Term::variable("X")]),
+            &Term::functor(
+                "h",
+                [
+                    Term::variable("Y"),
+                    Term::functor("g", [Term::variable("C")]),
+                ],
+            ),
+            &mut assignments,
+            &mut trail,
+        );
+
+        assert!(unified);
+        assert_eq!(assignments.get("A"), Some(&Term::variable("X")));
+        assert_eq!(assignments.get("C"), Some(&f_a));
+        assert_eq!(trail, vec!["A", "C"]);
+    }
 }